@@ -1,14 +1,35 @@
 use std::{env};
+use std::path::PathBuf;
 use serenity::all::token::validate;
 use serenity::prelude::*;
 use tracing::error;
 use utils::config::Config;
+use utils::storage::Storage;
 use utils::global_interface::GlobalInterface;
+use utils::http_server::start_server;
+use utils::metrics::watch_gateway_latency;
 use modules::load_modules;
 
+/// Read the config path from the command line (--config <path> or --config=<path>)
+fn config_path_argument() -> Option<PathBuf> {
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--config" {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(path) = arg.strip_prefix("--config=") {
+            return Some(PathBuf::from(path));
+        }
+    }
+    None
+}
+
 #[tokio::main]
 async fn main() {
-    let config_path = env::current_exe().expect("Failed to find executable path").parent().unwrap().join("config.json");
+    let config_path = match config_path_argument() {
+        Some(path) => { path }
+        None => { env::current_exe().expect("Failed to find executable path").parent().unwrap().join("config.json") }
+    };
     
     // Open Config
     if let Err(error) = Config::init(config_path.clone()) {
@@ -17,6 +38,13 @@ async fn main() {
     };
     let log_connector = utils::logger::init_logger();
 
+    // Open module storage
+    let storage_path = Config::get().module_config_directory.join("bidibip.sqlite");
+    if let Err(error) = Storage::init(storage_path.clone()) {
+        error!("Failed to open storage {} : {}", storage_path.display(), error);
+        return;
+    }
+
     // Set gateway intents, which decides what events the bot will be notified about
     let intents =
         GatewayIntents::GUILDS |
@@ -27,17 +55,36 @@ async fn main() {
             GatewayIntents::DIRECT_MESSAGES |
             GatewayIntents::GUILD_MODERATION;
 
-    if validate(&Config::get().token).is_err() {
-        error!("Invalid token '{}'. Please check config file first ({})", Config::get().token, config_path.display());
+    if validate(Config::get().token.expose()).is_err() {
+        error!("Invalid token. Please check config file ({}), secrets file or BIDIBIP_TOKEN environment variable first", config_path.display());
         return;
     }
 
-    let global_interface = GlobalInterface::new(log_connector).await;
+    let global_interface = match GlobalInterface::new(log_connector).await {
+        Ok(global_interface) => { global_interface }
+        Err(error) => {
+            error!("Failed to initialize bot : {}", error);
+            return;
+        }
+    };
     load_modules(global_interface.shared_data()).await;
+    let shared_data = global_interface.shared_data().clone();
 
     // Create a new instance of the Client, logging in as a bot.
-    let mut client = Client::builder(&Config::get().token, intents).event_handler(global_interface).await.expect("Failed to create client");
+    let mut client = Client::builder(Config::get().token.expose(), intents).event_handler(global_interface).await.expect("Failed to create client");
     client.cache.set_max_messages(Config::get().cache_message_size);
+
+    // Expose the health checks and the Prometheus metrics
+    let http = &Config::get().http;
+    if http.enabled {
+        if let Err(error) = start_server(http, shared_data).await {
+            error!("Failed to start HTTP listener on {} : {}", http.address, error);
+        }
+        if http.metrics {
+            watch_gateway_latency(client.shard_manager.clone());
+        }
+    }
+
     // Start listening for events by starting a single shard
     if let Err(why) = client.start().await {
         println!("Client error: {why:?}");
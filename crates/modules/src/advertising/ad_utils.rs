@@ -159,7 +159,7 @@ impl<T: Clone + ResetStep + Send + Sync> ButtonOption<T> {
                 CreateButton::new(make_custom_id::<Advertising>(button.0.to_string().as_str(), ""))
                     .label(button.1.to_string())
                     .style(if let Some(value) = &self.value {
-                        if value.1 == button.0.to_string() { ButtonStyle::Success } else { ButtonStyle::Secondary }
+                        if value.1 == button.0 { ButtonStyle::Success } else { ButtonStyle::Secondary }
                     } else { ButtonStyle::Primary })
             );
             out_options.insert(button.0.to_string(), button.2);
@@ -213,12 +213,8 @@ impl ResetStep for TextOption {
             let message = on_fail!(thread.message(http, message).await, "Failed to get question message")?;
             on_fail!(message.delete(http).await, "Failed to delete question message")?;
         }
-        if let Some(edit_button) = &mut self.edit_button {
-            edit_button.free()?;
-        }
-        if let Some(clear_button) = &mut self.clear_button {
-            clear_button.free()?;
-        }
+        self.edit_button = None;
+        self.clear_button = None;
         self.value = None;
         self.question_message = None;
         Ok(())
@@ -244,8 +240,8 @@ impl TextOption {
                 return Ok(false);
             }
 
-            let edit_button = ButtonId::new()?;
-            let clear_button = ButtonId::new()?;
+            let edit_button = ButtonId::new();
+            let clear_button = ButtonId::new();
             let mut buttons = vec![CreateButton::new(edit_button.custom_id::<Advertising>()).style(ButtonStyle::Secondary).label("Modifier")];
             if *optional {
                 buttons.push(CreateButton::new(clear_button.custom_id::<Advertising>()).style(ButtonStyle::Secondary).label("Supprimer"));
@@ -255,9 +251,6 @@ impl TextOption {
             on_fail!(question_message.edit(http, EditMessage::new().content(format!("## ▶  {question}\n`{}`", message.content.truncate_text(1900))).components(vec![CreateActionRow::Buttons(buttons)])).await,  format!("Failed to edit question message for {}", question))?;
             on_fail!(message.delete(http).await, "failed to delete response")?;
 
-            if let Some(clear_button) = &mut self.clear_button {
-                clear_button.free()?;
-            }
             self.clear_button = Some(clear_button);
             self.edit_button = Some(edit_button);
             self.value = Some(message.content.clone());
@@ -278,16 +271,8 @@ impl TextOption {
                     if let Some((action, _)) = component.data.get_custom_id_action::<Advertising>() {
                         if clear_button_id.raw().to_string().as_str() == action {
                             if let Some((question_message, title, _)) = &self.question_message {
-                                if let Some(edit_btn) = &mut self.edit_button {
-                                    edit_btn.free()?;
-                                }
-
-                                if let Some(clear_btn) = &mut self.clear_button {
-                                    clear_btn.free()?;
-                                }
-
-                                let edit_button = ButtonId::new()?;
-                                let clear_button = ButtonId::new()?;
+                                let edit_button = ButtonId::new();
+                                let clear_button = ButtonId::new();
 
                                 self.value = None;
                                 self.skipped = true;
@@ -317,7 +302,7 @@ impl TextOption {
                                 Some(val) => { val.clone() }
                             };
 
-                            let button = ButtonId::new()?;
+                            let button = ButtonId::new();
                             on_fail!(component.create_response(http,
                                 CreateInteractionResponse::Modal(CreateModal::new(button.custom_id::<Advertising>(), title.truncate_text(45))
                                     .components(vec![CreateActionRow::InputText(CreateInputText::new(InputTextStyle::Paragraph, "Nouveau contenu", "text").placeholder("Nouveau contenu").required(!optional).value(value))]))).await, "Failed to create edit modal")?;
@@ -330,12 +315,12 @@ impl TextOption {
             Interaction::Modal(modal) => {
                 if let Some(edition_id) = &self.waiting_edition_id {
                     if edition_id.custom_id::<Advertising>() == modal.data.custom_id {
-                        for component in &modal.data.components {
+                        if let Some(component) = modal.data.components.first() {
                             for component in &component.components {
                                 if let ActionRowComponent::InputText(text) = component {
                                     let question = assert_some!(&self.question_message, "Failed to get question message")?;
 
-                                    let content = if text.value.is_none() || text.value.as_ref().unwrap().len() == 0 {
+                                    let content = if text.value.is_none() || text.value.as_ref().unwrap().is_empty() {
                                         self.skipped = true;
                                         self.value = None;
                                         String::from(":negative_squared_cross_mark:")
@@ -345,7 +330,6 @@ impl TextOption {
                                     };
 
                                     on_fail_warn!(modal.defer(http).await, "Failed to close edition modal");
-                                    edition_id.clone().free()?;
                                     self.waiting_edition_id = None;
 
                                     let mut question_message = on_fail!(modal.channel_id.message(http, question.0).await, format!("Failed to get question message for {}", question.1))?;
@@ -354,7 +338,6 @@ impl TextOption {
                                     return Ok(true);
                                 }
                             }
-                            break;
                         }
                     }
                 }
@@ -375,7 +358,7 @@ impl TextOption {
         if self.question_message.is_some() { return Ok(self.value.is_none()); }
         let message = match &mut self.value {
             None => {
-                let skip_button = ButtonId::new()?;
+                let skip_button = ButtonId::new();
                 self.clear_button = Some(skip_button.clone());
                 let mut message = CreateMessage::new().content(format!("## ▶  {}\n> *Écris ta réponse sous ce message*", title.truncate_text(300)));
                 if optional {
@@ -384,8 +367,8 @@ impl TextOption {
                 on_fail!(thread.send_message(http,message).await, "Failed to send message")?
             }
             Some(value) => {
-                let edit_button = ButtonId::new()?;
-                let skip_button = ButtonId::new()?;
+                let edit_button = ButtonId::new();
+                let skip_button = ButtonId::new();
 
                 let mut buttons = vec![CreateButton::new(edit_button.custom_id::<Advertising>()).style(ButtonStyle::Secondary).label("Modifier")];
                 if optional {
@@ -6,25 +6,33 @@ use std::str::FromStr;
 use std::sync::Arc;
 use anyhow::Error;
 use serde::{Deserialize, Serialize};
-use serenity::all::{ButtonStyle, ChannelId, ChannelType, CommandInteraction, ComponentInteractionDataKind, Context, CreateForumPost, CreateInteractionResponse, CreateInteractionResponseFollowup, CreateInteractionResponseMessage, CreateMessage, CreateModal, CreateThread, EditMessage, ForumTagId, GetMessages, GuildChannel, InputTextStyle, Interaction, Mentionable, Message, RoleId, User, UserId};
+use serenity::all::{ButtonStyle, ChannelId, ChannelType, CommandInteraction, ComponentInteractionDataKind, Context, CreateForumPost, CreateInteractionResponse, CreateInteractionResponseFollowup, CreateInteractionResponseMessage, CreateMessage, CreateModal, CreateThread, EditMessage, ForumTagId, GetMessages, GuildChannel, Http, InputTextStyle, Interaction, Mentionable, Message, RoleId, User, UserId};
 use serenity::all::ActionRowComponent::InputText;
 use serenity::builder::{CreateActionRow, CreateButton, CreateInputText};
 use tokio::sync::RwLock;
+use tracing::warn;
 use utils::module::{BidibipModule, LoadModule};
+use utils::dispatch::ModuleEvents;
 use utils::global_interface::BidibipSharedData;
 use utils::error::BidibipError;
 use utils::{on_fail, on_fail_warn, assert_some, assert_warn_some};
 use crate::advertising::steps::main::{Contract, MainSteps};
 use crate::advertising::steps::{ResetStep, SubStep};
-use utils::utilities::{CommandHelper, TruncateText, Username};
-use utils::interaction_utils::{make_custom_id, InteractionUtils};
+use utils::utilities::{is_not_found, CommandHelper, TruncateText, Username};
+use utils::interaction_utils::{make_custom_id, CustomId, InteractionUtils};
 use utils::create_command_detailed::CreateCommandDetailed;
 use utils::config::Config;
+use utils::validation::ConfigValidator;
 use utils::message_reference::MessageReference;
 use utils::global_interface::PermissionData;
+use utils::storage::{Storage, Table};
+use utils::event_bus::{AdPublished, InternalEvent};
 
 pub struct Advertising {
     ad_config: RwLock<AdvertisingConfig>,
+    stored_ads_table: Table<UserId, HashMap<ChannelId, StoredAdData>>,
+    in_progress_ads_table: Table<UserId, (ChannelId, MainSteps)>,
+    shared_data: Arc<BidibipSharedData>,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -50,6 +58,24 @@ struct AdvertisingTags {
     on_site_flex: ForumTagId,
 }
 
+/// Modal asking the reviewer why the ad is refused
+#[derive(Serialize, Deserialize)]
+enum DenyModal {
+    Reason,
+}
+
+impl CustomId for DenyModal {
+    type Module = Advertising;
+
+    fn action(&self) -> &'static str {
+        "deny-reason"
+    }
+
+    fn from_legacy(custom_id: &str) -> Option<Self> {
+        if custom_id == "deny_modal" { Some(DenyModal::Reason) } else { None }
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 struct AdvertisingConfig {
     tags: AdvertisingTags,
@@ -57,10 +83,24 @@ struct AdvertisingConfig {
     reviewer_roles: Vec<RoleId>,
     in_progress_ad_channel: ChannelId,
     max_ad_per_user: u64,
+    // Runtime data is persisted in the storage, json values are only read to be imported
+    #[serde(default, skip_serializing)]
     stored_adds: HashMap<UserId, HashMap<ChannelId, StoredAdData>>,
+    #[serde(default, skip_serializing)]
     in_progress_ad: HashMap<UserId, (ChannelId, MainSteps)>,
 }
 
+impl AdvertisingConfig {
+    fn validate(&self, validator: &mut ConfigValidator) {
+        validator.channel("ad_forum", self.ad_forum);
+        validator.channel("in_progress_ad_channel", self.in_progress_ad_channel);
+        validator.check(!self.reviewer_roles.is_empty(), "`reviewer_roles` est vide");
+        for role in &self.reviewer_roles {
+            validator.role("reviewer_roles", *role);
+        }
+    }
+}
+
 impl Default for AdvertisingConfig {
     fn default() -> Self {
         Self {
@@ -76,6 +116,21 @@ impl Default for AdvertisingConfig {
 }
 
 impl Advertising {
+    /// Persist the stored and in progress ads of the given user
+    fn save_user_ads(&self, config: &AdvertisingConfig, user: UserId) -> Result<(), Error> {
+        Storage::get().transaction(|tx| {
+            match config.stored_adds.get(&user) {
+                None => { tx.remove(&self.stored_ads_table, &user)? }
+                Some(ads) => { tx.set(&self.stored_ads_table, &user, ads)? }
+            }
+            match config.in_progress_ad.get(&user) {
+                None => { tx.remove(&self.in_progress_ads_table, &user)? }
+                Some(ad) => { tx.set(&self.in_progress_ads_table, &user, ad)? }
+            }
+            Ok(())
+        })
+    }
+
     /// Create an ad edition thread with input data (allow using existing data to edit it)
     async fn init_channel_with_data(&self, ctx: &Context, config: &mut AdvertisingConfig, interaction: &Interaction, data: MainSteps) -> Result<(), BidibipError> {
         // Get user who did the action
@@ -95,7 +150,7 @@ impl Advertising {
         };
 
         // Create edition thread
-        let edition_thread = on_fail!(config.in_progress_ad_channel.create_thread( & ctx.http, CreateThread::new(format ! ("Annonce de {}", Username::from_user(&user).safe_full())).kind(ChannelType::PrivateThread)).await, "Failed to create thread")?;
+        let edition_thread = on_fail!(config.in_progress_ad_channel.create_thread( & ctx.http, CreateThread::new(format ! ("Annonce de {}", Username::from_user(user).safe_full())).kind(ChannelType::PrivateThread)).await, "Failed to create thread")?;
         on_fail!(edition_thread.send_message(&ctx.http, CreateMessage::new().content(format!("# Bienvenue dans le formulaire de création d'annonce {} !", user.name))).await, "Failed to send welcome message")?;
         config.in_progress_ad.insert(user.id, (edition_thread.id, data));
 
@@ -104,6 +159,7 @@ impl Advertising {
 
         // Step in
         self.advance_or_print(&mut assert_some!(config.in_progress_ad.get_mut(&user.id), "Failed to get main step data")?.1, ctx, &edition_thread, user).await?;
+        on_fail!(self.save_user_ads(config, user.id), "Failed to save ads")?;
 
         // Invite the user to see the edition thread
         let response = CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().ephemeral(true)
@@ -120,8 +176,8 @@ impl Advertising {
     /// Advance to the next step (ask next question or print preview message)
     async fn advance_or_print(&self, config: &mut MainSteps, ctx: &Context, thread: &GuildChannel, user: &User) -> Result<(), BidibipError> {
         if config.advance(ctx, thread).await? {
-            if let Err(err) = config.print_preview_message_in_channel(ctx, &thread.id, &user).await {
-                on_fail!(thread.send_message(&ctx.http, CreateMessage::new().content(format!(":no_entry:Impossible de formater l'annonce :no_entry: \n> {}", err.to_string()))).await, format!("Failed to send error reason : {}", err.to_string()))?;
+            if let Err(err) = config.print_preview_message_in_channel(ctx, &thread.id, user).await {
+                on_fail!(thread.send_message(&ctx.http, CreateMessage::new().content(format!(":no_entry:Impossible de formater l'annonce :no_entry: \n> {}", err))).await, format!("Failed to send error reason : {}", err.to_string()))?;
             }
         };
         Ok(())
@@ -130,46 +186,78 @@ impl Advertising {
 
 #[serenity::async_trait]
 impl BidibipModule for Advertising {
-    async fn execute_command(&self, ctx: Context, name: &str, command: CommandInteraction) -> Result<(), BidibipError> {
-        match name {
-            "annonce" => {
-                let mut ad_config = self.ad_config.write().await;
-                if let Some(stored_add) = ad_config.stored_adds.get(&command.user.id) {
-                    if stored_add.len() > 0 {
-                        if stored_add.len() as u64 >= ad_config.max_ad_per_user {
-                            on_fail!(command.create_response(&ctx.http, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
-                                .content("# :warning: Tu as déjà des annonces ouvertes !\n> Note : Tu as atteint le nombre maximal d'annonces simultanées")
-                            .ephemeral(true))).await, "Failed to send interaction response")?;
-                        } else {
-                            on_fail!(command.create_response(&ctx.http, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
-                                .content("# :warning: Tu as déjà des annonces ouvertes !")
-                                .ephemeral(true)
-                                .components(vec![CreateActionRow::Buttons(vec![
-                                        CreateButton::new(make_custom_id::<Advertising>("create-ad", "")).label("Créer une nouvelle annonce")
-                                    ])]))).await, "Failed to send interaction response")?;
-                        }
+    async fn validate_config(&self, validator: &mut ConfigValidator) {
+        self.ad_config.read().await.validate(validator);
+    }
+
+    async fn reload_config(&self, validator: &mut ConfigValidator) -> Result<bool, Error> {
+        let mut config = Config::get().read_module_config::<Advertising, AdvertisingConfig>()?;
+        config.validate(validator);
+        if validator.is_valid() {
+            let mut current = self.ad_config.write().await;
+            // Ads are not stored in the json file
+            config.stored_adds = std::mem::take(&mut current.stored_adds);
+            config.in_progress_ad = std::mem::take(&mut current.in_progress_ad);
+            *current = config;
+        }
+        Ok(true)
+    }
 
-                        for (channel, data) in stored_add {
-                            let title = match &data.description.title.value() {
-                                None => { "Annonce sans titre" }
-                                Some(title) => { title.as_str() }
-                            };
+    async fn reclaim_leaked_buttons(&self, http: &Http) -> Result<Vec<String>, Error> {
+        let mut config = self.ad_config.write().await;
+        let mut leaks = vec![];
+        let in_progress: Vec<(UserId, ChannelId)> = config.in_progress_ad.iter().map(|(user, (thread, _))| (*user, *thread)).collect();
+        for (user, thread) in in_progress {
+            match thread.to_channel(http).await {
+                Ok(_) => {}
+                Err(err) if is_not_found(&err) => {
+                    config.in_progress_ad.remove(&user);
+                    self.save_user_ads(&config, user)?;
+                    leaks.push(format!("edition thread {} of {} was deleted, the ad in progress is dropped", thread, user));
+                }
+                Err(err) => { warn!("Failed to check ad edition thread {} : {}", thread, err) }
+            }
+        }
+        Ok(leaks)
+    }
 
-                            on_fail!(command.create_followup(&ctx.http, CreateInteractionResponseFollowup::new().content(format!("**{}** : {}", title.truncate_text(300), data.ad_message.link(Config::get().server_id)))
-                                .ephemeral(true)
-                            .components(vec![CreateActionRow::Buttons(vec![
-                                        CreateButton::new(make_custom_id::<Advertising>("edit-ad", channel)).label("Modifier"),
-                                        CreateButton::new(make_custom_id::<Advertising>("delete-ad", channel)).label("Supprimer")
-                                    ])])).await, "Failed to send interaction response")?;
-                        }
+    async fn execute_command(&self, ctx: Context, name: &str, command: CommandInteraction) -> Result<(), BidibipError> {
+        if name == "annonce" {
+            let mut ad_config = self.ad_config.write().await;
+            if let Some(stored_add) = ad_config.stored_adds.get(&command.user.id) {
+                if !stored_add.is_empty() {
+                    if stored_add.len() as u64 >= ad_config.max_ad_per_user {
+                        on_fail!(command.create_response(&ctx.http, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+                            .content("# :warning: Tu as déjà des annonces ouvertes !\n> Note : Tu as atteint le nombre maximal d'annonces simultanées")
+                        .ephemeral(true))).await, "Failed to send interaction response")?;
                     } else {
-                        self.init_channel_with_data(&ctx, &mut ad_config, &Interaction::Command(command), MainSteps::default()).await?;
+                        on_fail!(command.create_response(&ctx.http, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+                            .content("# :warning: Tu as déjà des annonces ouvertes !")
+                            .ephemeral(true)
+                            .components(vec![CreateActionRow::Buttons(vec![
+                                    CreateButton::new(make_custom_id::<Advertising>("create-ad", "")).label("Créer une nouvelle annonce")
+                                ])]))).await, "Failed to send interaction response")?;
+                    }
+
+                    for (channel, data) in stored_add {
+                        let title = match &data.description.title.value() {
+                            None => { "Annonce sans titre" }
+                            Some(title) => { title.as_str() }
+                        };
+
+                        on_fail!(command.create_followup(&ctx.http, CreateInteractionResponseFollowup::new().content(format!("**{}** : {}", title.truncate_text(300), data.ad_message.link(command.guild_id.unwrap_or(Config::get().server_id))))
+                            .ephemeral(true)
+                        .components(vec![CreateActionRow::Buttons(vec![
+                                    CreateButton::new(make_custom_id::<Advertising>("edit-ad", channel)).label("Modifier"),
+                                    CreateButton::new(make_custom_id::<Advertising>("delete-ad", channel)).label("Supprimer")
+                                ])])).await, "Failed to send interaction response")?;
                     }
                 } else {
                     self.init_channel_with_data(&ctx, &mut ad_config, &Interaction::Command(command), MainSteps::default()).await?;
                 }
+            } else {
+                self.init_channel_with_data(&ctx, &mut ad_config, &Interaction::Command(command), MainSteps::default()).await?;
             }
-            _ => {}
         }
         Ok(())
     }
@@ -192,7 +280,7 @@ impl BidibipModule for Advertising {
                     let guild_channel = assert_some!(on_fail!(edition_thread.to_channel(&ctx.http).await, "Failed to get channel data")?.guild(), "Invalid guild thread data")?;
                     self.advance_or_print(ad_config, &ctx, &guild_channel, &message.author).await?;
                     // Save modifications
-                    on_fail!(Config::get().save_module_config::<Advertising, AdvertisingConfig>(&config), "failed to save config")?;
+                    on_fail!(self.save_user_ads(&config, message.author.id), "failed to save ads")?;
                     break;
                 }
                 items.append(&mut item.get_dependencies());
@@ -218,11 +306,9 @@ impl BidibipModule for Advertising {
                         let mut ad_config = self.ad_config.write().await;
                         if let Some(user_ads) = ad_config.stored_adds.get(&component.user.id) {
                             let edited_data_channel = ChannelId::new(u64::from_str(channel.as_str())?);
-                            let data = if let Some(data) = user_ads.get(&edited_data_channel) {
-                                Some(data.clone())
-                            } else { None };
+                            let data = user_ads.get(&edited_data_channel).cloned();
                             if let Some(mut data) = data {
-                                data.description.edited_post = Some(data.ad_message.clone());
+                                data.description.edited_post = Some(data.ad_message);
                                 self.init_channel_with_data(&ctx, &mut ad_config, &interaction, data.description).await?;
                             }
                         }
@@ -237,16 +323,15 @@ impl BidibipModule for Advertising {
                             assert_warn_some!(user_ads.remove(&removed_data_channel), "Ad data was empty, nothing to remove");
                             on_fail_warn!(component.create_response(&ctx.http, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().ephemeral(true).content("Ton annonce a bien été supprimée !"))).await, "Failed to delete interaction message");
                         }
-                        on_fail!(Config::get().save_module_config::<Advertising, AdvertisingConfig>(&ad_config), "Failed to save ad_config")?;
+                        on_fail!(self.save_user_ads(&ad_config, component.user.id), "Failed to save ads")?;
                     } else if component.data.get_custom_id_data::<Advertising>("pre-publish").is_some() {
                         let ad_config = self.ad_config.read().await;
 
                         for data in &ad_config.in_progress_ad {
-                            if data.1.0 == component.channel_id {
-                                if component.user.id != *data.0 {
+                            if data.1.0 == component.channel_id
+                                && component.user.id != *data.0 {
                                     component.respond_user_error(&ctx.http, "Tu n'es pas l'auteur de ce post !").await;
                                 }
-                            }
                         }
 
                         on_fail!(component.create_response(&ctx.http, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().ephemeral(true).content("Bien reçu, nous allons passer en revue ton annonce"))).await, "Failed to send confirmation message")?;
@@ -262,12 +347,13 @@ impl BidibipModule for Advertising {
                         ])).await, "Failed to edit message")?;
                     } else if component.data.get_custom_id_data::<Advertising>("deny").is_some() {
                         on_fail!(
-                            component.create_response(&ctx.http, CreateInteractionResponse::Modal(CreateModal::new("deny_modal", "Contenu problématique")
+                            component.create_response(&ctx.http, CreateInteractionResponse::Modal(CreateModal::new(DenyModal::Reason.encode()?, "Contenu problématique")
                             .components(vec![CreateActionRow::InputText(CreateInputText::new(InputTextStyle::Paragraph, "Raison", component.message.id.to_string()).required(true))]))).await, "Failed to create modal")?;
                     } else if component.data.get_custom_id_data::<Advertising>("validate").is_some() {
                         let mut ad_config = self.ad_config.write().await;
 
-                        let member = on_fail!(Config::get().server_id.member(&ctx.http, component.user.id).await, "Failed to get member data")?;
+                        let guild = assert_some!(component.guild_id, "Advertising validation outside of a guild")?;
+                        let member = on_fail!(guild.member(&ctx.http, component.user.id).await, "Failed to get member data")?;
 
                         let mut initial_user = None;
                         for data in &ad_config.in_progress_ad {
@@ -301,6 +387,7 @@ impl BidibipModule for Advertising {
                             return Ok(())
                         };
                         let edited_post = data.edited_post.take();
+                        let edited = edited_post.is_some();
 
                         data.clean_for_storage();
 
@@ -328,16 +415,18 @@ impl BidibipModule for Advertising {
                             MessageReference::from(assert_some!(messages.first(), "There is no message in this thread")?)
                         };
 
-                        ad_config.stored_adds.entry(initial_user.id).or_default().insert(post.channel(), StoredAdData {
+                        let post_channel = post.channel();
+                        ad_config.stored_adds.entry(initial_user.id).or_default().insert(post_channel, StoredAdData {
                             ad_message: post,
                             description: data,
                         });
                         ad_config.in_progress_ad.remove(&initial_user.id);
                         on_fail!(component.channel_id.delete(&ctx.http).await, "Failed to delete edition channel")?;
-                        on_fail!(Config::get().save_module_config::<Advertising, AdvertisingConfig>(&ad_config), "Failed to save ad_config")?;
+                        on_fail!(self.save_user_ads(&ad_config, initial_user.id), "Failed to save ads")?;
+                        self.shared_data.publish(&ctx, InternalEvent::AdPublished(AdPublished { guild, author: initial_user.id, reviewer: component.user.id, post: post_channel, edited })).await;
                     }
                     // Clicked on option button
-                    else if let Some(_) = component.data.get_custom_id_action::<Advertising>()
+                    else if component.data.get_custom_id_action::<Advertising>().is_some()
                     {
                         let mut ad_config = self.ad_config.write().await;
                         if let Some((edition_thread, in_progress)) = ad_config.in_progress_ad.get_mut(&component.user.id) {
@@ -355,14 +444,14 @@ impl BidibipModule for Advertising {
                         }
 
                         // Save modifications
-                        on_fail!(Config::get().save_module_config::<Advertising, AdvertisingConfig>(&mut ad_config), "Failed to save ad_config")?;
+                        on_fail!(self.save_user_ads(&ad_config, component.user.id), "Failed to save ads")?;
                     }
                 }
             }
             Interaction::Modal(modal) => {
                 let mut ad_config = self.ad_config.write().await;
 
-                if modal.data.custom_id == "deny_modal" {
+                if DenyModal::decode(&modal.data.custom_id).is_some() {
                     if let Some(action_row) = modal.data.components.first() {
                         if let Some(InputText(text)) = action_row.components.first() {
                             if let Some(value) = &text.value {
@@ -372,7 +461,7 @@ impl BidibipModule for Advertising {
                                 let mut user = None;
                                 for in_progress in &mut ad_config.in_progress_ad {
                                     if in_progress.1.0 == modal.channel_id {
-                                        user = Some(in_progress.0.clone());
+                                        user = Some(*in_progress.0);
                                         demo_message = in_progress.1.1.demo_message.take();
                                         break;
                                     }
@@ -402,7 +491,7 @@ impl BidibipModule for Advertising {
                     }
 
                     // Save modifications
-                    on_fail!(Config::get().save_module_config::<Advertising, AdvertisingConfig>(&mut ad_config), "Failed to save ad_config")?;
+                    on_fail!(self.save_user_ads(&ad_config, modal.user.id), "Failed to save ads")?;
                 }
             }
             _ => {}
@@ -422,10 +511,28 @@ impl LoadModule<Advertising> for Advertising {
         "Créer une annonce d'offre ou de recherche d'emploi"
     }
 
-    async fn load(_: &Arc<BidibipSharedData>) -> Result<Advertising, Error> {
-        let module = Self { ad_config: Default::default() };
-        let warn_config = Config::get().load_module_config::<Advertising, AdvertisingConfig>()?;
-        *module.ad_config.write().await = warn_config;
-        Ok(module)
+    fn events() -> ModuleEvents {
+        ModuleEvents::MESSAGE | ModuleEvents::INTERACTION_CREATE
+    }
+
+    fn permission_actions() -> Vec<&'static str> {
+        vec!["validate", "deny"]
+    }
+
+    async fn load(shared_data: &Arc<BidibipSharedData>) -> Result<Advertising, Error> {
+        let mut ad_config = Config::get().load_module_config::<Advertising, AdvertisingConfig>()?;
+        let stored_ads_table = Storage::get().table::<Advertising, _, _>("stored_ads")?;
+        let in_progress_ads_table = Storage::get().table::<Advertising, _, _>("in_progress_ads")?;
+
+        // Move ads from the json config to the storage
+        if !ad_config.stored_adds.is_empty() || !ad_config.in_progress_ad.is_empty() {
+            stored_ads_table.import(ad_config.stored_adds.drain())?;
+            in_progress_ads_table.import(ad_config.in_progress_ad.drain())?;
+            Config::get().save_module_config::<Advertising, AdvertisingConfig>(&ad_config)?;
+        }
+        ad_config.stored_adds = stored_ads_table.entries()?.into_iter().collect();
+        ad_config.in_progress_ad = in_progress_ads_table.entries()?.into_iter().collect();
+
+        Ok(Self { ad_config: RwLock::new(ad_config), stored_ads_table, in_progress_ads_table, shared_data: shared_data.clone() })
     }
 }
\ No newline at end of file
@@ -43,11 +43,10 @@ impl SubStep for FixedTermInfos {
             return Ok(false);
         }
 
-        if self.compensation.is_unset() {
-            if self.compensation.try_init(&ctx.http, thread, "Rémunération", false).await? {
+        if self.compensation.is_unset()
+            && self.compensation.try_init(&ctx.http, thread, "Rémunération", false).await? {
                 return Ok(false);
             }
-        }
         Ok(true)
     }
 
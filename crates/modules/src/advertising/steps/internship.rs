@@ -70,51 +70,40 @@ impl SubStep for InternshipInfos {
     }
 
     async fn advance(&mut self, ctx: &Context, thread: &GuildChannel) -> Result<bool, BidibipError> {
-        if self.duration.is_unset() {
-            if self.duration.try_init(&ctx.http, thread, "Durée du stage", false).await? {
+        if self.duration.is_unset()
+            && self.duration.try_init(&ctx.http, thread, "Durée du stage", false).await? {
                 return Ok(false);
             }
-        }
 
-        if self.compensation.is_unset() {
-            if self.compensation.try_init(&ctx.http, thread, "Le stage est-il rémunéré ?", vec![
+        if self.compensation.is_unset()
+            && self.compensation.try_init(&ctx.http, thread, "Le stage est-il rémunéré ?", vec![
                 ("y", "Oui", Compensation::Yes(TextOption::default())),
                 ("n", "No", Compensation::No),
             ]).await? {
                 return Ok(false);
             }
-        }
-        if let Some(compensation) = self.compensation.value_mut() {
-            if let Compensation::Yes(value) = compensation {
-                if value.is_unset() {
-                    if value.try_init(&ctx.http, thread, "Quelle est la gratification ? (4,35€/h minimum pour un stage de plus de 10 semaines)", false).await? {
-                        return Ok(false);
-                    }
+        if let Some(Compensation::Yes(value)) = self.compensation.value_mut() {
+            if value.is_unset()
+                && value.try_init(&ctx.http, thread, "Quelle est la gratification ? (4,35€/h minimum pour un stage de plus de 10 semaines)", false).await? {
+                    return Ok(false);
                 }
-            }
         }
         Ok(true)
     }
 
 
     async fn receive_message(&mut self, ctx: &Context, thread: &ChannelId, message: &Message) -> Result<bool, BidibipError> {
-        if let Some(compensation) = self.compensation.value_mut() {
-            if let Compensation::Yes(value) = compensation {
-                if value.is_unset() {
-                    if value.try_set(&ctx.http, thread, message).await? { return Ok(true); }
-                }
-            }
+        if let Some(Compensation::Yes(value)) = self.compensation.value_mut() {
+            if value.is_unset()
+                && value.try_set(&ctx.http, thread, message).await? { return Ok(true); }
         }
         Ok(self.duration.try_set(&ctx.http, thread, message).await?)
     }
 
     async fn on_interaction(&mut self, ctx: &Context, interaction: &Interaction) -> Result<bool, BidibipError> {
-        if let Some(compensation) = self.compensation.value_mut() {
-            if let Compensation::Yes(value) = compensation {
-                if value.is_unset() {
-                    if value.try_edit(&ctx.http, interaction).await? { return Ok(true); }
-                }
-            }
+        if let Some(Compensation::Yes(value)) = self.compensation.value_mut() {
+            if value.is_unset()
+                && value.try_edit(&ctx.http, interaction).await? { return Ok(true); }
         }
         Ok(self.duration.try_edit(&ctx.http, interaction).await? || self.compensation.try_set(&ctx.http, interaction).await?)
     }
@@ -53,6 +53,7 @@ impl ResetStep for Contract {
 }
 
 #[derive(Serialize, Deserialize, Clone)]
+#[allow(clippy::large_enum_variant)]
 pub enum What {
     Recruiter(RecruiterInfos),
     Worker(WorkerInfos),
@@ -155,26 +156,23 @@ impl ResetStep for MainSteps {
 #[serenity::async_trait]
 impl SubStep for MainSteps {
     async fn advance(&mut self, ctx: &Context, thread: &GuildChannel) -> Result<bool, BidibipError> {
-        if self.title.is_unset() {
-            if self.title.try_init(&ctx.http, thread, "Donne un titre à ton annonce", false).await? {
+        if self.title.is_unset()
+            && self.title.try_init(&ctx.http, thread, "Donne un titre à ton annonce", false).await? {
                 return Ok(false);
             }
-        }
 
-        if self.description.is_unset() {
-            if self.description.try_init(&ctx.http, thread, "Décris ton annonce, en quoi elle consiste, qui tu es etc...", false).await? {
+        if self.description.is_unset()
+            && self.description.try_init(&ctx.http, thread, "Décris ton annonce, en quoi elle consiste, qui tu es etc...", false).await? {
                 return Ok(false);
             }
-        }
 
-        if self.who_are_you.is_unset() {
-            if self.who_are_you.try_init(&ctx.http, thread, "Qui es tu ? Décris toi, ton entreprise, ton projet, ton expérience etc...", false).await? {
+        if self.who_are_you.is_unset()
+            && self.who_are_you.try_init(&ctx.http, thread, "Qui es tu ? Décris toi, ton entreprise, ton projet, ton expérience etc...", false).await? {
                 return Ok(false);
             }
-        }
 
-        if self.kind.is_unset() {
-            if self.kind.try_init(&ctx.http, thread, "Quel type de contrat recherches-tu ?", vec![
+        if self.kind.is_unset()
+            && self.kind.try_init(&ctx.http, thread, "Quel type de contrat recherches-tu ?", vec![
                 ("volunteering", "🤝 Bénévolat (non rémunéré)", Contract::Volunteering(VolunteeringInfos::default())),
                 ("internship", "🪂 Stage", Contract::Internship(InternshipInfos::default())),
                 ("workstudy", "🤓 Alternance (rémunéré)", Contract::WorkStudy(WorkStudyInfos::default())),
@@ -184,7 +182,6 @@ impl SubStep for MainSteps {
             ]).await? {
                 return Ok(false);
             }
-        }
 
         if let Some(kind) = self.kind.value_mut() {
             if !match kind {
@@ -197,14 +194,13 @@ impl SubStep for MainSteps {
             } { return Ok(false); }
         }
 
-        if self.is_recruiter.is_unset() {
-            if self.is_recruiter.try_init(&ctx.http, thread, "Es-tu recruteur ou recherches tu du travail ?", vec![
+        if self.is_recruiter.is_unset()
+            && self.is_recruiter.try_init(&ctx.http, thread, "Es-tu recruteur ou recherches tu du travail ?", vec![
                 ("worker", "🔧 Je cherche du travail", What::Worker(WorkerInfos::default())),
                 ("recruiter", "🕵️‍♀️ Je recrute", What::Recruiter(RecruiterInfos::default())),
             ]).await? {
                 return Ok(false);
             }
-        }
 
         if let Some(recruiter) = self.is_recruiter.value_mut() {
             if !match recruiter {
@@ -231,11 +227,10 @@ impl SubStep for MainSteps {
             }
         }
 
-        if self.other_urls.is_unset() {
-            if self.other_urls.try_init(&ctx.http, thread, "Ajoutes d'autres informations (liens etc...)", true).await? {
+        if self.other_urls.is_unset()
+            && self.other_urls.try_init(&ctx.http, thread, "Ajoutes d'autres informations (liens etc...)", true).await? {
                 return Ok(false);
             }
-        }
 
         Ok(true)
     }
@@ -347,7 +342,7 @@ impl MainSteps {
             .author(CreateEmbedAuthor::new(goal).icon_url(if let Some(avatar) = user.avatar_url() { avatar } else { user.default_avatar_url() }))
             .title(title)
             .color(Colour::PURPLE)
-            .description(format!("{}", description.truncate_text(4000)));
+            .description(description.truncate_text(4000).to_string());
 
 
         let mut fields = vec![];
@@ -388,13 +383,6 @@ impl MainSteps {
         }
 
         embeds.push(last_embed);
-
-        #[derive(Serialize, Deserialize, Clone, Default)]
-        pub struct MainSteps {
-            pub contact: ButtonOption<Contact>,
-            pub other_urls: TextOption,
-            demo_message: Option<MessageId>,
-        }
         embeds.insert(0, main_embed);
         embeds
     }
@@ -30,12 +30,11 @@ impl SubStep for OpenEndedInfos {
         }, true));
     }
     async fn advance(&mut self, ctx: &Context, thread: &GuildChannel) -> Result<bool, BidibipError> {
-        if self.compensation.is_unset() {
-            if self.compensation.try_init(&ctx.http, thread, "Rémunération", false).await?
+        if self.compensation.is_unset()
+            && self.compensation.try_init(&ctx.http, thread, "Rémunération", false).await?
             {
                 return Ok(false);
             }
-        }
         Ok(true)
     }
 
@@ -104,53 +104,47 @@ impl SubStep for RecruiterInfos {
         }, true));
     }
     async fn advance(&mut self, ctx: &Context, thread: &GuildChannel) -> Result<bool, BidibipError> {
-        if self.location.is_unset() {
-            if self.location.try_init(&ctx.http, thread, "Quelles sont les modalités de travail ?", vec![
+        if self.location.is_unset()
+            && self.location.try_init(&ctx.http, thread, "Quelles sont les modalités de travail ?", vec![
                 ("remote", "🌍 Distanciel", Location::Remote),
                 ("flex", "🤷‍♀️ Télétravail possible", Location::OnSiteFlex(TextOption::default())),
                 ("on_site", "🏣 Présentiel uniquement", Location::OnSite(TextOption::default())),
             ]).await? {
                 return Ok(false);
             }
-        }
 
         if let Some(value) = self.location.value_mut() {
             match value {
                 Location::Remote => {}
                 Location::OnSiteFlex(val) => {
-                    if val.is_unset() {
-                        if val.try_init(&ctx.http, thread, "Quelle est ta ville / région ?", false).await? {
+                    if val.is_unset()
+                        && val.try_init(&ctx.http, thread, "Quelle est ta ville / région ?", false).await? {
                             return Ok(false);
                         }
-                    }
                 }
                 Location::OnSite(val) => {
-                    if val.is_unset() {
-                        if val.try_init(&ctx.http, thread, "Quelle est ta ville / région ?", false).await? {
+                    if val.is_unset()
+                        && val.try_init(&ctx.http, thread, "Quelle est ta ville / région ?", false).await? {
                             return Ok(false);
                         }
-                    }
                 }
             }
         }
 
-        if self.studio.is_unset() {
-            if self.studio.try_init(&ctx.http, thread, "Quel est le nom de ton entreprise / studio ?", false).await? {
+        if self.studio.is_unset()
+            && self.studio.try_init(&ctx.http, thread, "Quel est le nom de ton entreprise / studio ?", false).await? {
                 return Ok(false);
             }
-        }
 
-        if self.responsibilities.is_unset() {
-            if self.responsibilities.try_init(&ctx.http, thread, "Quelles sont les responsabilitées demandées ?", false).await? {
+        if self.responsibilities.is_unset()
+            && self.responsibilities.try_init(&ctx.http, thread, "Quelles sont les responsabilitées demandées ?", false).await? {
                 return Ok(false);
             }
-        }
 
-        if self.qualifications.is_unset() {
-            if self.qualifications.try_init(&ctx.http, thread, "Quelles sont les compétences requises ?", false).await? {
+        if self.qualifications.is_unset()
+            && self.qualifications.try_init(&ctx.http, thread, "Quelles sont les compétences requises ?", false).await? {
                 return Ok(false);
             }
-        }
 
         Ok(true)
     }
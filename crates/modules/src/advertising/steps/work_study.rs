@@ -38,17 +38,15 @@ impl SubStep for WorkStudyInfos {
     }
 
     async fn advance(&mut self, ctx: &Context, thread: &GuildChannel) -> Result<bool, BidibipError> {
-        if self.duration.is_unset() {
-            if self.duration.try_init(&ctx.http, thread, "Durée du contrat", false).await? {
+        if self.duration.is_unset()
+            && self.duration.try_init(&ctx.http, thread, "Durée du contrat", false).await? {
                 return Ok(false);
             }
-        }
 
-        if self.compensation.is_unset() {
-            if self.compensation.try_init(&ctx.http, thread, "Rémunération", false).await? {
+        if self.compensation.is_unset()
+            && self.compensation.try_init(&ctx.http, thread, "Rémunération", false).await? {
                 return Ok(false);
             }
-        }
         Ok(true)
     }
 
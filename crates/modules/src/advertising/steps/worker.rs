@@ -84,40 +84,36 @@ impl SubStep for WorkerInfos {
     }
 
     async fn advance(&mut self, ctx: &Context, thread: &GuildChannel) -> Result<bool, BidibipError> {
-        if self.location.is_unset() {
-            if self.location.try_init(&ctx.http, thread, "Souhaites-tu travailler à distance ou en présentiel ?", vec![
+        if self.location.is_unset()
+            && self.location.try_init(&ctx.http, thread, "Souhaites-tu travailler à distance ou en présentiel ?", vec![
                 ("remote", "🌍 Distanciel", Location::Remote),
                 ("any", "🤷‍♀️ Télétravail possible", Location::Anywhere(TextOption::default())),
                 ("on_site", "🏣 Présentiel uniquement", Location::OnSite(TextOption::default()))]).await? {
                 return Ok(false);
             }
-        }
 
         if let Some(location) = self.location.value_mut() {
             match location {
                 Location::Remote => {}
                 Location::Anywhere(loc) => {
-                    if loc.is_unset() {
-                        if loc.try_init(&ctx.http, thread, "Indique ta ville / région", false).await? {
+                    if loc.is_unset()
+                        && loc.try_init(&ctx.http, thread, "Indique ta ville / région", false).await? {
                             return Ok(false);
                         }
-                    }
                 }
                 Location::OnSite(loc) => {
-                    if loc.is_unset() {
-                        if loc.try_init(&ctx.http, thread, "Indique ta ville / région", false).await? {
+                    if loc.is_unset()
+                        && loc.try_init(&ctx.http, thread, "Indique ta ville / région", false).await? {
                             return Ok(false);
                         }
-                    }
                 }
             }
         }
 
-        if self.skills.is_unset() {
-            if self.skills.try_init(&ctx.http, thread, "Quelles sont tes compétences ?", false).await? {
+        if self.skills.is_unset()
+            && self.skills.try_init(&ctx.http, thread, "Quelles sont tes compétences ?", false).await? {
                 return Ok(false);
             }
-        }
 
         Ok(true)
     }
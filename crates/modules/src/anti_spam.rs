@@ -3,18 +3,25 @@ use std::sync::Arc;
 use anyhow::Error;
 use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
-use serenity::all::{ButtonStyle, ChannelId, Context, CreateActionRow, CreateInteractionResponse, CreateInteractionResponseMessage, Interaction, Mentionable, Message, MessageId, RoleId, UserId};
+use serenity::all::{ButtonStyle, ChannelId, Context, CreateActionRow, CreateInteractionResponse, CreateInteractionResponseMessage, GuildId, Http, Interaction, Mentionable, Message, MessageId, RoleId, UserId};
 use serenity::all::Interaction::Component;
 use serenity::builder::{CreateButton, CreateMessage};
 use tokio::sync::RwLock;
 use tracing::log::warn;
 use utils::module::{LoadModule, BidibipModule};
+use utils::dispatch::ModuleEvents;
 use utils::global_interface::BidibipSharedData;
 use utils::error::BidibipError;
 use utils::config::ButtonId;
 use utils::message_reference::MessageReference;
-use utils::config::Config;
-use utils::on_fail;
+use utils::config::{Config, PerGuild};
+use utils::validation::ConfigValidator;
+use utils::{assert_some, on_fail, on_fail_warn};
+use utils::storage::{Storage, Table};
+use utils::utilities::is_not_found;
+use utils::actions::DiscordActions;
+use utils::metrics::Metrics;
+use crate::warn::{ActionType, Warn};
 
 #[derive(Default)]
 struct LastMessage {
@@ -28,14 +35,31 @@ struct SpammerContext {
     kick_button: ButtonId,
     pardon_button: ButtonId,
     spammer: UserId,
+    // Spammers stored before multi-guild support belong to the main guild
+    #[serde(default)]
+    guild: Option<GuildId>,
+    // Spammed message and the channels it was sent in, recorded with the sanction
+    #[serde(default)]
+    content: String,
+    #[serde(default)]
+    channels: Vec<ChannelId>,
+}
+
+/// Settings of the anti spam specific to each guild
+#[derive(Deserialize, Serialize, Default)]
+struct AntiSpamGuildConfig {
+    mute_role: RoleId,
+    moderation_channel: ChannelId,
 }
 
 #[derive(Deserialize, Serialize)]
 struct AntiSpamConfig {
     min_occurrences: usize,
     max_delay_ms: i64,
-    mute_role: RoleId,
-    moderation_channel: ChannelId,
+    #[serde(flatten)]
+    guild: PerGuild<AntiSpamGuildConfig>,
+    // Runtime data is persisted in the storage, json values are only read to be imported
+    #[serde(default, skip_serializing)]
     spammers: HashMap<MessageId, SpammerContext>,
 }
 
@@ -44,16 +68,27 @@ impl Default for AntiSpamConfig {
         Self {
             min_occurrences: 3,
             max_delay_ms: 60000,
-            mute_role: Default::default(),
-            moderation_channel: Default::default(),
+            guild: Default::default(),
             spammers: Default::default(),
         }
     }
 }
 
+impl AntiSpamConfig {
+    fn validate(&self, validator: &mut ConfigValidator) {
+        self.guild.validate(validator, |config, validator| {
+            validator.role("mute_role", config.mute_role);
+            validator.channel("moderation_channel", config.moderation_channel);
+        });
+    }
+}
+
 pub struct AntiSpam {
     history: RwLock<HashMap<UserId, LastMessage>>,
     anti_spam_config: RwLock<AntiSpamConfig>,
+    // Key is the moderation message id
+    spammers: Table<MessageId, SpammerContext>,
+    shared_data: Arc<BidibipSharedData>,
 }
 
 #[serenity::async_trait]
@@ -66,14 +101,72 @@ impl LoadModule<AntiSpam> for AntiSpam {
         "Protection contre les spams potentiels"
     }
 
-    async fn load(_: &Arc<BidibipSharedData>) -> Result<AntiSpam, Error> {
-        Ok(AntiSpam { history: Default::default(), anti_spam_config: RwLock::new(Config::get().load_module_config::<AntiSpam, AntiSpamConfig>()?) })
+    fn events() -> ModuleEvents {
+        ModuleEvents::MESSAGE | ModuleEvents::INTERACTION_CREATE
+    }
+
+    fn permission_actions() -> Vec<&'static str> {
+        vec!["kick", "pardon"]
+    }
+
+    async fn load(shared_data: &Arc<BidibipSharedData>) -> Result<AntiSpam, Error> {
+        let mut config = Config::get().load_module_config::<AntiSpam, AntiSpamConfig>()?;
+        let spammers = Storage::get().table::<AntiSpam, _, _>("spammers")?;
+
+        // Move spammer contexts from the json config to the storage
+        if !config.spammers.is_empty() {
+            spammers.import(config.spammers.drain())?;
+            Config::get().save_module_config::<AntiSpam, AntiSpamConfig>(&config)?;
+        }
+        Ok(AntiSpam { history: Default::default(), anti_spam_config: RwLock::new(config), spammers, shared_data: shared_data.clone() })
     }
 }
 
 #[serenity::async_trait]
 impl BidibipModule for AntiSpam {
+    async fn validate_config(&self, validator: &mut ConfigValidator) {
+        self.anti_spam_config.read().await.validate(validator);
+    }
+
+    async fn reload_config(&self, validator: &mut ConfigValidator) -> Result<bool, Error> {
+        let config = Config::get().read_module_config::<AntiSpam, AntiSpamConfig>()?;
+        config.validate(validator);
+        if validator.is_valid() {
+            *self.anti_spam_config.write().await = config;
+        }
+        Ok(true)
+    }
+
+    async fn reclaim_leaked_buttons(&self, http: &Http) -> Result<Vec<String>, Error> {
+        let config = self.anti_spam_config.read().await;
+        let mut leaks = vec![];
+        for (message, infos) in self.spammers.entries()? {
+            let moderation_channel = match config.guild.get(infos.guild.unwrap_or(Config::get().server_id)) {
+                None => { continue; }
+                Some(config) => { config.moderation_channel }
+            };
+            match moderation_channel.message(http, message).await {
+                Ok(_) => {}
+                Err(err) if is_not_found(&err) => {
+                    self.spammers.remove(&message)?;
+                    leaks.push(format!("moderation message {} about {} was deleted, the spammer may still be muted", message, infos.spammer));
+                }
+                Err(err) => { warn!("Failed to check anti spam message {} : {}", message, err) }
+            }
+        }
+        Ok(leaks)
+    }
+
     async fn message(&self, ctx: Context, msg: Message) -> Result<(), BidibipError> {
+        // The anti spam only runs on the guilds it is configured for
+        let guild = match msg.guild_id {
+            None => { return Ok(()) }
+            Some(guild) => { guild }
+        };
+        if self.anti_spam_config.read().await.guild.get(guild).is_none() {
+            return Ok(());
+        }
+
         let mut history = self.history.write().await;
 
         let entry = history.entry(msg.author.id).or_default();
@@ -92,7 +185,7 @@ impl BidibipModule for AntiSpam {
             for (date, message) in &entry.occurrences {
                 let elapsed = Utc::now() - *date;
                 if elapsed < Duration::milliseconds(self.anti_spam_config.read().await.max_delay_ms) {
-                    spam_messages.push(message.clone());
+                    spam_messages.push(*message);
                 }
             }
 
@@ -102,37 +195,45 @@ impl BidibipModule for AntiSpam {
                     return Ok(());
                 }
                 entry.warned = true;
+                Metrics::get().spam_detections.inc();
 
-                let mut config = self.anti_spam_config.write().await;
+                let config = self.anti_spam_config.read().await;
+                let config = assert_some!(config.guild.get(guild), "Missing anti spam config of the guild")?;
 
-                let member = on_fail!(Config::get().server_id.member(&ctx.http, msg.author.id).await, "Not a member")?;
-                on_fail!(member.add_role(&ctx.http, config.mute_role).await, "Failed to mute potential spammer")?;
+                let member = on_fail!(guild.member(&ctx.http, msg.author.id).await, "Not a member")?;
+                let actions = DiscordActions::of::<AntiSpam>(&ctx.http);
+                on_fail!(actions.add_role(guild, &member.user, config.mute_role).await, "Failed to mute potential spammer")?;
 
-                let kick_button = ButtonId::new()?;
-                let pardon_button = ButtonId::new()?;
+                let kick_button = ButtonId::new();
+                let pardon_button = ButtonId::new();
 
                 let modo_message = on_fail!(config.moderation_channel.send_message(&ctx.http, CreateMessage::new()
                     .content(format!("@everyone Spam potentiel de {} : `{}`", msg.author.mention(), msg.content))
                 .components(vec![CreateActionRow::Buttons(vec![
-                        CreateButton::new(kick_button.custom_id::<AntiSpam>()).style(ButtonStyle::Danger).label("Kick"),
-                        CreateButton::new(pardon_button.custom_id::<AntiSpam>()).style(ButtonStyle::Success).label("Pardonner")
+                        CreateButton::new(kick_button.action_custom_id::<AntiSpam>("kick")).style(ButtonStyle::Danger).label("Kick"),
+                        CreateButton::new(pardon_button.action_custom_id::<AntiSpam>("pardon")).style(ButtonStyle::Success).label("Pardonner")
                     ])])).await, "Failed to send warn message in modo channel")?;
 
+                let mut channels: Vec<ChannelId> = spam_messages.iter().map(|message| message.channel()).collect();
+                channels.push(msg.channel_id);
+
                 for message in spam_messages {
                     match message.message(&ctx.http).await {
                         Ok(message) => {
-                            on_fail!(message.delete(&ctx.http).await, "Failed to delete spam message")?;
+                            on_fail!(actions.delete_message(guild, &message).await, "Failed to delete spam message")?;
                         }
                         Err(err) => { warn!("Failed to get message to delete : {}", err) }
                     }
                 }
 
-                config.spammers.insert(modo_message.id, SpammerContext {
+                self.spammers.set(&modo_message.id, &SpammerContext {
                     kick_button,
                     pardon_button,
                     spammer: msg.author.id,
-                });
-                Config::get().save_module_config::<AntiSpam, AntiSpamConfig>(&config)?;
+                    guild: Some(guild),
+                    content: msg.content.clone(),
+                    channels,
+                })?;
             }
         } else {
             entry.content = msg.content.clone();
@@ -145,28 +246,41 @@ impl BidibipModule for AntiSpam {
 
     async fn interaction_create(&self, ctx: Context, interaction: Interaction) -> Result<(), BidibipError> {
         if let Component(component) = interaction {
-            let mut config = self.anti_spam_config.write().await;
-            let mute_role = config.mute_role;
-            if let Some(infos) = config.spammers.get_mut(&component.message.id) {
-                if infos.kick_button.custom_id::<AntiSpam>().to_string() == component.data.custom_id {
-                    infos.kick_button.free()?;
-                    infos.pardon_button.free()?;
-                    let member = on_fail!(Config::get().server_id.member(&ctx.http, infos.spammer).await, "Not a member")?;
-                    on_fail!(member.kick_with_reason(&ctx, "Spam détecté").await, "Failed to kick spammer")?;
+            if let Some(infos) = self.spammers.get(&component.message.id)? {
+                let guild = assert_some!(component.guild_id, "Anti spam button used outside of a guild")?;
+                let mute_role = assert_some!(self.anti_spam_config.read().await.guild.get(guild).map(|config| config.mute_role), format!("Missing anti spam config of guild {}", guild))?;
+                // Buttons sent before the action was added to the custom id only contain the button id
+                let is_button = |button: &ButtonId, action: &str| { component.data.custom_id == button.action_custom_id::<AntiSpam>(action) || component.data.custom_id == button.custom_id::<AntiSpam>() };
+                if is_button(&infos.kick_button, "kick") {
+                    let member = on_fail!(guild.member(&ctx.http, infos.spammer).await, "Not a member")?;
+                    let actions = DiscordActions::of::<AntiSpam>(&ctx.http);
+                    on_fail!(actions.kick(guild, &member.user, "Spam détecté").await, "Failed to kick spammer")?;
                     on_fail!(component.message.delete(&ctx.http).await, "Failed to delete anti spam message")?;
+                    self.spammers.remove(&component.message.id)?;
+                    // In dry run the spammer is still there : only the moderation is told, and nothing is recorded
+                    if actions.is_dry_run() {
+                        on_fail!(component.create_response(&ctx.http, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().content(format!("[dry run] {} aurait été kick par {} pour cause de spam", infos.spammer.mention(), component.user.mention())))).await, "Failed to send response")?;
+                        return Ok(());
+                    }
                     on_fail!(component.create_response(&ctx.http, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().content(format!("{} a été kick par {} pour cause de spam", infos.spammer.mention(), component.user.mention())))).await, "Failed to send response")?;
-                    config.spammers.remove(&component.message.id);
-                    Config::get().save_module_config::<AntiSpam, AntiSpamConfig>(&config)?;
-                } else if infos.pardon_button.custom_id::<AntiSpam>().to_string() == component.data.custom_id {
-                    infos.kick_button.free()?;
-                    infos.pardon_button.free()?;
-                    let member = on_fail!(Config::get().server_id.member(&ctx.http, infos.spammer).await, "Not a member")?;
-                    on_fail!(member.remove_role(&ctx.http, mute_role).await, "Failed to remove mute role")?;
+                    // Kicks done by Bidibip are not recorded from the audit log
+                    match self.shared_data.service::<Warn>(Some(guild)).await {
+                        None => { warn!("Module {} is disabled, the anti spam kick of {} is not recorded in the warn history", Warn::name(), infos.spammer) }
+                        Some(warn) => {
+                            // Spammers stored before the message was kept have no details
+                            let details = if infos.content.is_empty() { None } else {
+                                Some(format!("Message : `{}`\nSalons : {}", infos.content, infos.channels.iter().map(|channel| channel.mention().to_string()).collect::<Vec<_>>().join(", ")))
+                            };
+                            on_fail_warn!(warn.record_sanction::<AntiSpam>(&ctx, guild, &component.user, &member.user, ActionType::Kick, "Spam détecté", details).await, "Failed to record anti spam kick")
+                        }
+                    }
+                } else if is_button(&infos.pardon_button, "pardon") {
+                    let member = on_fail!(guild.member(&ctx.http, infos.spammer).await, "Not a member")?;
+                    on_fail!(DiscordActions::of::<AntiSpam>(&ctx.http).remove_role(guild, &member.user, mute_role).await, "Failed to remove mute role")?;
                     on_fail!(component.create_response(&ctx.http, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().content(format!("{} a été pardonné par {}", infos.spammer.mention(), component.user.mention())))).await, "Failed to send response")?;
                     on_fail!(component.message.delete(&ctx.http).await, "Failed to delete anti spam message")?;
                     self.history.write().await.remove(&infos.spammer);
-                    config.spammers.remove(&component.message.id);
-                    Config::get().save_module_config::<AntiSpam, AntiSpamConfig>(&config)?;
+                    self.spammers.remove(&component.message.id)?;
                 }
             }
         }
@@ -3,6 +3,7 @@ use anyhow::Error;
 use serenity::all::{Colour, CommandInteraction, CommandType, Context, CreateInteractionResponse, CreateInteractionResponseMessage};
 use serenity::builder::CreateEmbed;
 use utils::module::{LoadModule, BidibipModule};
+use utils::dispatch::ModuleEvents;
 use utils::global_interface::BidibipSharedData;
 use utils::error::BidibipError;
 use utils::{on_fail, assert_some};
@@ -24,6 +25,10 @@ impl LoadModule<Help> for Help {
         "Voir la liste des commandes disponibles"
     }
 
+    fn events() -> ModuleEvents {
+        ModuleEvents::empty()
+    }
+
     async fn load(shared_data: &Arc<BidibipSharedData>) -> Result<Help, Error> {
         Ok(Help { shared_data: shared_data.clone() })
     }
@@ -34,8 +39,8 @@ impl BidibipModule for Help {
     async fn execute_command(&self, ctx: Context, _: &str, command: CommandInteraction) -> Result<(), BidibipError> {
         let mut embed = CreateEmbed::new().title("Aide de Bidibip").description("Liste des commandes disponibles :").color(Colour::DARK_GREEN);
 
-        for module in self.shared_data.get_enabled_modules().await {
-            let permissions = self.shared_data.permissions.read().await.clone();
+        let permissions = self.shared_data.permissions(assert_some!(command.guild_id, "Help command used outside of a guild")?).await;
+        for module in self.shared_data.get_guild_modules(command.guild_id).await {
             for found_command in module.module.fetch_commands(&permissions) {
                 let member = assert_some!(command.member.clone(), "Failed to get member data")?;
                 let permissions = assert_some!(member.permissions, "Failed to get user permissions")?;
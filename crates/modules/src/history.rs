@@ -8,9 +8,11 @@ use serenity::builder::CreateEmbed;
 use tokio::sync::RwLock;
 use tracing::{info};
 use utils::module::{LoadModule, BidibipModule};
+use utils::dispatch::ModuleEvents;
 use utils::global_interface::BidibipSharedData;
 use utils::error::BidibipError;
 use utils::config::Config;
+use utils::validation::ConfigValidator;
 use utils::on_fail;
 use utils::utilities::{ResultDebug, TruncateText, Username};
 
@@ -34,6 +36,10 @@ impl LoadModule<History> for History {
         "Historique des messages modifiés et supprimés"
     }
 
+    fn events() -> ModuleEvents {
+        ModuleEvents::MESSAGE_DELETE | ModuleEvents::MESSAGE_UPDATE
+    }
+
     async fn load(_: &Arc<BidibipSharedData>) -> Result<History, Error> {
         let module = Self { history_config: Default::default() };
         let modo_config = Config::get().load_module_config::<History, HistoryConfig>()?;
@@ -44,6 +50,11 @@ impl LoadModule<History> for History {
 
 #[serenity::async_trait]
 impl BidibipModule for History {
+    async fn reload_config(&self, _: &mut ConfigValidator) -> Result<bool, Error> {
+        *self.history_config.write().await = Config::get().read_module_config::<History, HistoryConfig>()?;
+        Ok(true)
+    }
+
     async fn message_delete(&self, ctx: Context, channel_id: ChannelId, deleted_message_id: MessageId, guild_id: Option<GuildId>) -> Result<(), BidibipError> {
         if self.history_config.read().await.channel_blacklist.contains(&channel_id) {
             return Ok(())
@@ -93,18 +104,18 @@ impl BidibipModule for History {
                     format!("{} ({})", Username::from_user(user).safe_full(), user.id)
                 }
             };
-            Config::get().channels.log_channel.send_message(
+            Config::get().guild_channels(guild_id.unwrap_or(Config::get().server_id)).log_channel.send_message(
                 &ctx.http,
                 CreateMessage::new().embed(
                     CreateEmbed::new()
                         .color(Colour::RED)
                         .title(format!("Message du {} supprimé par {}", date, from_name))
                         .description(deleted_message_id.link(channel_id, guild_id))
-                        .field(format!("de : {}", &user_name), &old_message_content.truncate_text(1024), false))).await.on_fail("Failed to print message rename log");
+                        .field(format!("de : {}", &user_name), old_message_content.truncate_text(1024), false))).await.on_fail("Failed to print message rename log");
 
             info!(target: "log","Message {} de {} du {} supprimé par {} : {}", deleted_message_id.link(channel_id, guild_id), user_name, date, from_name, old_message_content);
         } else {
-            Config::get().channels.log_channel.send_message(
+            Config::get().guild_channels(guild_id.unwrap_or(Config::get().server_id)).log_channel.send_message(
                 &ctx.http,
                 CreateMessage::new().embed(
                     CreateEmbed::new()
@@ -187,13 +198,13 @@ impl BidibipModule for History {
             })
             .description(format!("Message modifié : {}", new_url));
         if !old_text.is_empty() {
-            embed = embed.field("ancien", &old_text.truncate_text(1024), false);
+            embed = embed.field("ancien", old_text.truncate_text(1024), false);
         }
         if !new_text.is_empty() {
-            embed = embed.field("nouveau", &new_text.truncate_text(1024), false);
+            embed = embed.field("nouveau", new_text.truncate_text(1024), false);
         }
 
-        Config::get().channels.log_channel.send_message(
+        Config::get().guild_channels(event.guild_id.unwrap_or(Config::get().server_id)).log_channel.send_message(
             &ctx.http,
             CreateMessage::new().embed(embed)).await.on_fail("Failed to print message rename log");
 
@@ -202,7 +213,7 @@ impl BidibipModule for History {
                        "Unknown user".to_string()
                     }
                     Some(user) => {
-                        Username::from_user(&user).full()
+                        Username::from_user(user).full()
                     }
                 }, old_text, new_text);
         Ok(())
@@ -1,41 +1,61 @@
 use std::sync::{Arc};
-use tracing::{error};
+use tracing::{error, info};
+use utils::config::MigrationRegistry;
 use utils::global_interface::BidibipSharedData;
 use utils::module::{LoadModule, BidibipModule};
-mod say;
-mod warn;
-mod log;
-mod history;
-mod modo;
-mod help;
-mod utilities;
-mod welcome;
-mod reglement;
-mod repost;
-mod advertising;
-mod user_count;
-mod anti_spam;
+pub mod say;
+pub mod warn;
+pub mod log;
+pub mod history;
+pub mod modo;
+pub mod help;
+pub mod utilities;
+pub mod welcome;
+pub mod reglement;
+pub mod repost;
+pub mod advertising;
+pub mod user_count;
+pub mod anti_spam;
 async fn load_module_helper<T: 'static + LoadModule<T> + BidibipModule>(shared_data: &Arc<BidibipSharedData>) {
     match T::load(shared_data).await {
         Ok(module) => {
             shared_data.register_module(module).await;
         }
-        Err(err) => { error!("Failed to load module {} : {}", T::name(), err) }
+        Err(err) => {
+            error!("Failed to load module {} : {}", T::name(), err);
+            shared_data.register_load_failure(T::name(), err.to_string()).await;
+        }
+    }
+}
+
+/// Every module of the bot, in load order
+macro_rules! with_modules {
+    ($apply:ident) => {
+        $apply!(say::Say, warn::Warn, log::Log, history::History, help::Help, modo::Modo, utilities::Utilities, welcome::Welcome,
+            reglement::Reglement, repost::Repost, advertising::Advertising, user_count::UserCount, anti_spam::AntiSpam)
+    };
+}
+
+/// Upgrade the module configs before loading the modules
+fn migrate_module_configs() {
+    let mut registry = MigrationRegistry::default();
+    macro_rules! register {
+        ($($module:ty),*) => { $(registry.register::<$module>();)* };
+    }
+    with_modules!(register);
+
+    let report = registry.run();
+    if report.failed() {
+        error!("{}", report);
+    } else {
+        info!("{}", report);
     }
 }
 
 pub async fn load_modules(shared_data: &Arc<BidibipSharedData>) {
-    load_module_helper::<say::Say>(shared_data).await;
-    load_module_helper::<warn::Warn>(shared_data).await;
-    load_module_helper::<log::Log>(shared_data).await;
-    load_module_helper::<history::History>(shared_data).await;
-    load_module_helper::<help::Help>(shared_data).await;
-    load_module_helper::<modo::Modo>(shared_data).await;
-    load_module_helper::<utilities::Utilities>(shared_data).await;
-    load_module_helper::<welcome::Welcome>(shared_data).await;
-    load_module_helper::<reglement::Reglement>(shared_data).await;
-    load_module_helper::<repost::Repost>(shared_data).await;
-    load_module_helper::<advertising::Advertising>(shared_data).await;
-    load_module_helper::<user_count::UserCount>(shared_data).await;
-    load_module_helper::<anti_spam::AntiSpam>(shared_data).await;
-}
\ No newline at end of file
+    migrate_module_configs();
+    macro_rules! load {
+        ($($module:ty),*) => { $(load_module_helper::<$module>(shared_data).await;)* };
+    }
+    with_modules!(load);
+}
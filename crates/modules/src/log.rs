@@ -3,9 +3,11 @@ use anyhow::Error;
 use serenity::all::{ComponentInteractionDataKind, Context, GuildId, Interaction, Member, Mentionable, ResolvedValue, User};
 use tracing::{info};
 use utils::module::{LoadModule, BidibipModule};
+use utils::dispatch::ModuleEvents;
 use utils::global_interface::BidibipSharedData;
 use utils::error::BidibipError;
 use utils::utilities::Username;
+use utils::event_bus::{InternalEvent, InternalEvents};
 
 pub struct Log {}
 
@@ -19,6 +21,14 @@ impl LoadModule<Log> for Log {
         "logs du serveur dans un channel dédié"
     }
 
+    fn events() -> ModuleEvents {
+        ModuleEvents::GUILD_MEMBER_ADDITION | ModuleEvents::GUILD_MEMBER_REMOVAL | ModuleEvents::INTERACTION_CREATE
+    }
+
+    fn subscriptions() -> InternalEvents {
+        InternalEvents::SANCTION_APPLIED | InternalEvents::TICKET_OPENED | InternalEvents::AD_PUBLISHED
+    }
+
     async fn load(_: &Arc<BidibipSharedData>) -> Result<Log, Error> {
         Ok(Log {})
     }
@@ -36,6 +46,15 @@ impl BidibipModule for Log {
         Ok(())
     }
 
+    async fn internal_event(&self, _: Context, event: InternalEvent) -> Result<(), BidibipError> {
+        match event {
+            InternalEvent::SanctionApplied(sanction) => { info!("Sanction {} appliquée à {} par {} ({}) : {}", sanction.sanction, sanction.user.mention(), sanction.moderator.mention(), sanction.source, sanction.reason) }
+            InternalEvent::TicketOpened(ticket) => { info!("{} a ouvert un ticket de modération {}", ticket.user.mention(), ticket.thread.mention()) }
+            InternalEvent::AdPublished(ad) => { info!("Annonce {} de {} {} par {}", ad.post.mention(), ad.author.mention(), if ad.edited { "modifiée" } else { "publiée" }, ad.reviewer.mention()) }
+        }
+        Ok(())
+    }
+
     async fn interaction_create(&self, _ctx: Context, interaction: Interaction) -> Result<(), BidibipError> {
         match interaction {
             Interaction::Command(command_interaction) => {
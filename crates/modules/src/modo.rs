@@ -2,22 +2,28 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use anyhow::Error;
 use serde::{Deserialize, Serialize};
-use serenity::all::{ButtonStyle, ChannelId, ChannelType, CommandInteraction, Context, CreateButton, CreateEmbedAuthor, CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage, CreateThread, EditThread, Interaction, Mentionable, UserId};
+use serde_json::{Map, Value};
+use serenity::all::{ButtonStyle, ChannelId, ChannelType, CommandInteraction, Context, CreateButton, CreateEmbedAuthor, CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage, CreateThread, EditThread, GuildId, Interaction, Mentionable, UserId};
 use serenity::builder::{CreateActionRow, CreateEmbed};
 use tokio::sync::RwLock;
 use tracing::{warn};
 use utils::module::{LoadModule, BidibipModule};
+use utils::dispatch::ModuleEvents;
 use utils::global_interface::BidibipSharedData;
 use utils::error::BidibipError;
-use utils::config::Config;
+use utils::config::{Config, ConfigMigration, PerGuild};
+use utils::validation::ConfigValidator;
 use utils::{on_fail, assert_some};
 use utils::utilities::Username;
 use utils::global_interface::PermissionData;
 use utils::create_command_detailed::CreateCommandDetailed;
 use utils::utilities::CommandHelper;
+use utils::interaction_utils::CustomId;
+use utils::event_bus::{InternalEvent, TicketOpened};
 
 pub struct Modo {
     modo_config: RwLock<ModoConfig>,
+    shared_data: Arc<BidibipSharedData>,
 }
 
 
@@ -26,10 +32,41 @@ struct UserTickets {
     thread: ChannelId,
 }
 
+/// Settings of the moderation tickets specific to each guild
 #[derive(Serialize, Deserialize, Default)]
-struct ModoConfig {
+struct ModoGuildConfig {
     modo_channel: ChannelId,
-    tickets: HashMap<UserId, UserTickets>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct ModoConfig {
+    #[serde(flatten)]
+    guild: PerGuild<ModoGuildConfig>,
+    // Guild - user - ticket
+    tickets: HashMap<GuildId, HashMap<UserId, UserTickets>>,
+}
+
+impl ModoConfig {
+    fn validate(&self, validator: &mut ConfigValidator) {
+        self.guild.validate(validator, |config, validator| validator.channel("modo_channel", config.modo_channel));
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+enum ModoButton {
+    CloseThread,
+}
+
+impl CustomId for ModoButton {
+    type Module = Modo;
+
+    fn action(&self) -> &'static str {
+        "close-thread"
+    }
+
+    fn from_legacy(custom_id: &str) -> Option<Self> {
+        if custom_id == "modo_close_thread" { Some(ModoButton::CloseThread) } else { None }
+    }
 }
 
 #[serenity::async_trait]
@@ -42,12 +79,25 @@ impl LoadModule<Modo> for Modo {
         "Ouvre un canal directe avec la modération"
     }
 
-    async fn load(_: &Arc<BidibipSharedData>) -> Result<Modo, Error> {
-        let module = Self { modo_config: Default::default() };
+    fn events() -> ModuleEvents {
+        ModuleEvents::INTERACTION_CREATE
+    }
+
+    fn migrations() -> Vec<ConfigMigration> {
+        vec![ConfigMigration::new("tickets rangés par serveur", |config| {
+            // Existing tickets belong to the main guild
+            if let Some(tickets) = config.remove("tickets") {
+                let mut guilds = Map::new();
+                guilds.insert(Config::get().server_id.to_string(), tickets);
+                config.insert("tickets".to_string(), Value::Object(guilds));
+            }
+            Ok(())
+        })]
+    }
+
+    async fn load(shared_data: &Arc<BidibipSharedData>) -> Result<Modo, Error> {
+        let module = Self { modo_config: Default::default(), shared_data: shared_data.clone() };
         let modo_config = Config::get().load_module_config::<Modo, ModoConfig>()?;
-        if modo_config.modo_channel == 0 {
-            return Err(Error::msg("Invalid modo channel id"));
-        }
         *module.modo_config.write().await = modo_config;
         Ok(module)
     }
@@ -55,32 +105,55 @@ impl LoadModule<Modo> for Modo {
 
 #[serenity::async_trait]
 impl BidibipModule for Modo {
+    async fn validate_config(&self, validator: &mut ConfigValidator) {
+        self.modo_config.read().await.validate(validator);
+    }
+
+    async fn reload_config(&self, validator: &mut ConfigValidator) -> Result<bool, Error> {
+        let config = Config::get().read_module_config::<Modo, ModoConfig>()?;
+        config.validate(validator);
+        if validator.is_valid() {
+            *self.modo_config.write().await = config;
+        }
+        Ok(true)
+    }
+
     async fn execute_command(&self, ctx: Context, name: &str, command: CommandInteraction) -> Result<(), BidibipError> {
         if name == "modo" {
+            let guild = assert_some!(command.guild_id, "Modo command used outside of a guild")?;
             let mut modo_config = self.modo_config.write().await;
+            let modo_channel = match modo_config.guild.get(guild) {
+                None => {
+                    command.respond_user_error(&ctx.http, "La modération n'est pas encore configurée sur ce serveur").await;
+                    return Ok(());
+                }
+                Some(config) => { config.modo_channel }
+            };
+            let tickets = modo_config.tickets.entry(guild).or_default();
 
             // Get or create thread
             let mut thread = None;
-            if modo_config.tickets.contains_key(&command.user.id) {
-                let ticket = assert_some!(modo_config.tickets.get(&command.user.id), "This should never happen !!")?;
+            if tickets.contains_key(&command.user.id) {
+                let ticket = assert_some!(tickets.get(&command.user.id), "This should never happen !!")?;
                 match ticket.thread.to_channel(&ctx.http).await {
                     Ok(channel) => {
                         if let Some(guild_channel) = channel.guild() {
                             thread = Some(guild_channel);
                         } else {
-                            modo_config.tickets.remove(&command.user.id);
+                            tickets.remove(&command.user.id);
                             warn!("Failed to get guild_channel for modo command !");
                         }
                     }
                     Err(err) => {
-                        modo_config.tickets.remove(&command.user.id);
+                        tickets.remove(&command.user.id);
                         warn!("Failed to find existing modo thread ! {}", err);
                     }
                 }
             }
             if thread.is_none() {
-                let new_thread = on_fail!(modo_config.modo_channel.create_thread(&ctx.http, CreateThread::new(Username::from_user(&command.user).safe_full()).invitable(false).kind(ChannelType::PrivateThread)).await, "Failed to create modo thread")?;
-                modo_config.tickets.insert(command.user.id, UserTickets { thread: new_thread.id });
+                let new_thread = on_fail!(modo_channel.create_thread(&ctx.http, CreateThread::new(Username::from_user(&command.user).safe_full()).invitable(false).kind(ChannelType::PrivateThread)).await, "Failed to create modo thread")?;
+                tickets.insert(command.user.id, UserTickets { thread: new_thread.id });
+                self.shared_data.publish(&ctx, InternalEvent::TicketOpened(TicketOpened { guild, user: command.user.id, thread: new_thread.id })).await;
                 thread = Some(new_thread);
             };
 
@@ -88,7 +161,7 @@ impl BidibipModule for Modo {
             let thread = assert_some!(thread, "Failed to get thread for modo command")?;
 
             on_fail!(thread.id.add_thread_member(&ctx.http, command.user.id).await, "Failed to add user to modo thread")?;
-            let mention_to_admins = Config::get().roles.administrator.mention();
+            let mention_to_admins = Config::get().guild_roles(guild).administrator.mention();
 
             let mut embed = CreateEmbed::new().field("Canal de communication ouvert :robot:", format!("Tu es maintenant en communication directe avec les {}.\nA toi de nous dire ce qui ne va pas.", mention_to_admins), false);
 
@@ -101,7 +174,7 @@ impl BidibipModule for Modo {
             on_fail!(thread.send_message(&ctx.http, CreateMessage::new()
                     .content(format!("{} {}", command.user.mention(), mention_to_admins))
                     .embed(embed)
-                    .components(vec![CreateActionRow::Buttons(vec![CreateButton::new("modo_close_thread").label("Fermer la discussion").style(ButtonStyle::Secondary)])])).await, "Failed to send modo welcome message")?;
+                    .components(vec![CreateActionRow::Buttons(vec![CreateButton::new(ModoButton::CloseThread.encode()?).label("Fermer la discussion").style(ButtonStyle::Secondary)])])).await, "Failed to send modo welcome message")?;
 
             on_fail!(thread.id.edit_thread(&ctx.http, EditThread::new().archived(false).locked(false)).await, "Failed to unarchive thread")?;
 
@@ -121,9 +194,9 @@ impl BidibipModule for Modo {
 
     async fn interaction_create(&self, ctx: Context, interaction: Interaction) -> Result<(), BidibipError> {
         if let Interaction::Component(component) = interaction {
-            if component.data.custom_id == "modo_close_thread" {
+            if let Some(ModoButton::CloseThread) = ModoButton::decode(&component.data.custom_id) {
                 let modo_config = self.modo_config.read().await;
-                for (user, ticket_data) in &modo_config.tickets {
+                for (user, ticket_data) in modo_config.tickets.values().flatten() {
                     if ticket_data.thread == component.channel_id.get() {
                         on_fail!(component.channel_id.remove_thread_member(&ctx.http, *user).await, "Failed to remove user from modo thread")?;
                         on_fail!(component.channel_id.edit_thread(&ctx.http, EditThread::new().archived(true).locked(true)).await,"Failed to archive thread")?;
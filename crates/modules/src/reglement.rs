@@ -1,16 +1,21 @@
 use std::sync::Arc;
 use anyhow::Error;
 use serde::{Deserialize, Serialize};
-use serenity::all::{ChannelId, ComponentInteractionDataKind, Context, GetMessages, GuildId, Interaction, Message};
+use serenity::all::{ChannelId, ComponentInteractionDataKind, Context, GetMessages, Interaction, Message};
+use tokio::sync::RwLock;
 use utils::module::{LoadModule, BidibipModule};
+use utils::dispatch::ModuleEvents;
 use utils::global_interface::BidibipSharedData;
 use utils::error::BidibipError;
 use utils::config::Config;
-use utils::{on_fail, on_fail_warn};
+use utils::validation::ConfigValidator;
+use utils::{assert_some, on_fail, on_fail_warn};
+use utils::interaction_utils::CustomId;
 use utils::json_to_message::json_to_message;
+use utils::actions::DiscordActions;
 
 pub struct Reglement {
-    reglement_config: ReglementConfig,
+    reglement_config: RwLock<ReglementConfig>,
 }
 
 #[derive(Serialize, Deserialize, Default)]
@@ -18,10 +23,47 @@ struct ReglementConfig {
     reglement_channel: ChannelId,
 }
 
+impl ReglementConfig {
+    fn validate(&self, validator: &mut ConfigValidator) {
+        validator.channel("reglement_channel", self.reglement_channel);
+    }
+}
+
+/// The approval button is declared in the reglement json, with the `reglement_approval` id
+#[derive(Serialize, Deserialize)]
+enum ReglementButton {
+    Approve,
+}
+
+impl CustomId for ReglementButton {
+    type Module = Reglement;
+
+    fn action(&self) -> &'static str {
+        "approve"
+    }
+
+    fn from_legacy(custom_id: &str) -> Option<Self> {
+        if custom_id == "reglement_approval" { Some(ReglementButton::Approve) } else { None }
+    }
+}
+
 #[serenity::async_trait]
 impl BidibipModule for Reglement {
+    async fn validate_config(&self, validator: &mut ConfigValidator) {
+        self.reglement_config.read().await.validate(validator);
+    }
+
+    async fn reload_config(&self, validator: &mut ConfigValidator) -> Result<bool, Error> {
+        let config = Config::get().read_module_config::<Reglement, ReglementConfig>()?;
+        config.validate(validator);
+        if validator.is_valid() {
+            *self.reglement_config.write().await = config;
+        }
+        Ok(true)
+    }
+
     async fn message(&self, ctx: Context, new_message: Message)   -> Result<(), BidibipError> {
-        if new_message.channel_id == self.reglement_config.reglement_channel {
+        if new_message.channel_id == self.reglement_config.read().await.reglement_channel {
             if let Some(file) = new_message.attachments.first() {
                 let data = on_fail!(String::from_utf8(on_fail!(file.download().await, "Failed to download reglement json")?), "Sent json is not a valid utf8 file")?;
 
@@ -39,21 +81,16 @@ impl BidibipModule for Reglement {
     }
 
     async fn interaction_create(&self, ctx: Context, interaction: Interaction)   -> Result<(), BidibipError> {
-        match interaction {
-            Interaction::Component(component) => {
-                if component.data.custom_id != "reglement_approval" {
-                    return Ok(());
-                }
-                match component.data.kind {
-                    ComponentInteractionDataKind::Button => {
-                        let member = on_fail!(GuildId::from(Config::get().server_id).member(&ctx.http, component.user.id).await, "Failed to get member data")?;
-                        on_fail!(member.add_role(&ctx.http, Config::get().roles.member).await, "Failed to give member role")?;
-                        on_fail_warn!(component.defer(&ctx.http).await, "Failed to defer command interaction");
-                    }
-                    _ => {}
-                }
+        if let Interaction::Component(component) = interaction {
+            if ReglementButton::decode(&component.data.custom_id).is_none() {
+                return Ok(());
+            }
+            if let ComponentInteractionDataKind::Button = component.data.kind {
+                let guild = assert_some!(component.guild_id, "Reglement approval outside of a guild")?;
+                let member = on_fail!(guild.member(&ctx.http, component.user.id).await, "Failed to get member data")?;
+                on_fail!(DiscordActions::of::<Reglement>(&ctx.http).add_role(guild, &member.user, Config::get().guild_roles(guild).member).await, "Failed to give member role")?;
+                on_fail_warn!(component.defer(&ctx.http).await, "Failed to defer command interaction");
             }
-            _ => {}
         }
         Ok(())
     }
@@ -69,11 +106,12 @@ impl LoadModule<Reglement> for Reglement {
         "Outil de mise à jour automatique du réglement"
     }
 
+    fn events() -> ModuleEvents {
+        ModuleEvents::MESSAGE | ModuleEvents::INTERACTION_CREATE
+    }
+
     async fn load(_: &Arc<BidibipSharedData>) -> Result<Reglement, Error> {
         let config = Config::get().load_module_config::<Reglement, ReglementConfig>()?;
-        if config.reglement_channel == 0 {
-            return Err(Error::msg("Invalid reglement channel id"));
-        }
-        Ok(Reglement { reglement_config: config })
+        Ok(Reglement { reglement_config: RwLock::new(config) })
     }
 }
\ No newline at end of file
@@ -4,7 +4,7 @@ use std::sync::{Arc};
 use std::time::Duration;
 use anyhow::Error;
 use serde::{Deserialize, Serialize};
-use serenity::all::{ButtonStyle, ChannelId, ChannelType, CommandInteraction, CommandOptionType, ComponentInteraction, ComponentInteractionDataKind, Context, CreateActionRow, CreateCommandOption, CreateEmbedAuthor, CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage, EditChannel, EditInteractionResponse, EditMessage, GetMessages, GuildChannel, GuildId, Interaction, Member, Mentionable, Message, MessageId, PartialGuildChannel, ResolvedValue, UserId};
+use serenity::all::{ButtonStyle, ChannelId, ChannelType, CommandInteraction, ComponentInteraction, ComponentInteractionDataKind, Context, CreateActionRow, CreateCommandOption, CreateEmbedAuthor, CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage, EditChannel, EditInteractionResponse, EditMessage, GetMessages, GuildChannel, Interaction, Member, Mentionable, Message, MessageId, PartialChannel, PartialGuildChannel, UserId};
 use serenity::all::colours::roles::GREEN;
 use serenity::builder::{CreateButton, CreateEmbed};
 use tokio::sync::RwLock;
@@ -12,18 +12,90 @@ use tokio::time::sleep;
 use tracing::{error};
 use tracing::log::warn;
 use utils::module::{LoadModule, BidibipModule};
+use utils::dispatch::ModuleEvents;
 use utils::global_interface::BidibipSharedData;
 use utils::error::BidibipError;
 use utils::message_reference::MessageReference;
 use utils::config::Config;
-use utils::{on_fail, assert_condition, assert_some, on_fail_warn};
-use utils::utilities::{OptionHelper, TruncateText, Username};
+use utils::validation::ConfigValidator;
+use utils::{on_fail, assert_some, on_fail_warn, command_options, command_router};
+use utils::slash_command::{CommandParser, SlashCommand};
+use utils::utilities::{CommandHelper, TruncateText, Username};
 use utils::global_interface::PermissionData;
 use utils::create_command_detailed::CreateCommandDetailed;
-use utils::interaction_utils::{make_custom_id, InteractionUtils};
+use utils::interaction_utils::CustomId;
+use utils::storage::{Storage, Table};
+
+command_options! {
+    struct SetForumLinkCommand {
+        forum: PartialChannel => ("forum", "Forum où seront suivis les nouveaux posts", |option: CreateCommandOption| option.channel_types(vec![ChannelType::Forum])),
+        channel: PartialChannel => ("repost-channel", "Canal où seront repostés les évenements du forum", |option: CreateCommandOption| option.channel_types(vec![ChannelType::Text])),
+        vote: bool => ("vote", "Active les fonctionnalités de vote"),
+        enabled: bool => ("enabled", "Active ou désactive le lien"),
+    }
+}
+
+impl SlashCommand for SetForumLinkCommand {
+    const NAME: &'static str = "set-forum-link";
+    const DESCRIPTION: &'static str = "Lie un forum à un channel de repost";
+}
+
+command_options! {
+    struct ReposteCommand {
+        message: String => ("message", "lien du message à promouvoir"),
+    }
+}
+
+impl SlashCommand for ReposteCommand {
+    const NAME: &'static str = "reposte";
+    const DESCRIPTION: &'static str = "Promeut le message donné dans le salon de repost";
+}
+
+command_router! {
+    enum RepostCommand {
+        SetForumLink(SetForumLinkCommand),
+        Reposte(ReposteCommand),
+    }
+}
+
+/// Vote buttons of a thread
+#[derive(Serialize, Deserialize)]
+enum VoteButton {
+    Yes(ChannelId),
+    No(ChannelId),
+    SeeVotes(ChannelId),
+}
+
+impl CustomId for VoteButton {
+    type Module = Repost;
+
+    fn action(&self) -> &'static str {
+        match self {
+            VoteButton::Yes(_) => { "vote-yes" }
+            VoteButton::No(_) => { "vote-no" }
+            VoteButton::SeeVotes(_) => { "see-votes" }
+        }
+    }
+
+    fn from_legacy(custom_id: &str) -> Option<Self> {
+        let mut split = custom_id.split("::");
+        if split.next()? != Repost::name() {
+            return None;
+        }
+        let action = split.next()?;
+        let thread = ChannelId::new(u64::from_str(split.next()?).ok()?);
+        match action {
+            "vote-yes" => { Some(VoteButton::Yes(thread)) }
+            "vote-no" => { Some(VoteButton::No(thread)) }
+            "see-votes" => { Some(VoteButton::SeeVotes(thread)) }
+            _ => { None }
+        }
+    }
+}
 
 pub struct Repost {
     repost_config: RwLock<RepostConfig>,
+    votes_table: Table<ChannelId, VoteConfig>,
 }
 
 #[derive(Default, Serialize, Deserialize, Clone)]
@@ -48,11 +120,23 @@ struct RepostChannelConfig {
 struct RepostConfig {
     // Forum - RepostChannel
     forums: HashMap<ChannelId, RepostChannelConfig>,
-    // Channel - config
+    // Channel - config (runtime data is persisted in the storage, json values are only read to be imported)
+    #[serde(default, skip_serializing)]
     votes: HashMap<ChannelId, VoteConfig>,
 }
 
-fn find_urls(initial_text: &String) -> Vec<String> {
+impl RepostConfig {
+    fn validate(&self, validator: &mut ConfigValidator) {
+        for (forum, forum_config) in &self.forums {
+            validator.channel("forums", *forum);
+            for channel in &forum_config.repost_channel {
+                validator.channel(format!("forums.{}.repost_channel", forum).as_str(), *channel);
+            }
+        }
+    }
+}
+
+fn find_urls(initial_text: &str) -> Vec<String> {
     let split = initial_text.split(|c: char| { c.is_whitespace() || c == '[' || c == ']' || c == '(' || c == ')' });
     let mut attachments = vec![];
     for i in split {
@@ -92,12 +176,11 @@ fn make_repost_message(source_message: &Message, thread: &GuildChannel, forum_na
     }
 
     for url in &urls { // only for first image
-        if !url.matches(r#".(mp4|mov|avi|mkv|flv|jpg|jpeg|png|webp|avif|gif)$"#).count() > 0 {
-            if url.starts_with("http") {
+        if !url.matches(r#".(mp4|mov|avi|mkv|flv|jpg|jpeg|png|webp|avif|gif)$"#).count() > 0
+            && url.starts_with("http") {
                 author = author.url(url);
                 break;
             }
-        }
     }
 
     first_embed = first_embed.author(author);
@@ -133,16 +216,16 @@ impl Repost {
                 let yes = cfg.yes.len();
                 let status = if yes > no { "✅" } else { "❌" };
                 if let Err(err) = thr.edit(&http, EditChannel::new().name(format!("[{}{}-{}] {}", status, yes, no, cfg.thread_name))).await {
-                    return error!("Failed to update thread name : {}", err);
+                    error!("Failed to update thread name : {}", err)
                 }
             });
             let no = config.no.len();
             let yes = config.yes.len();
 
             let mut vote_buttons = vec![
-                CreateButton::new(make_custom_id::<Repost>("vote-yes", thread.id)).style(ButtonStyle::Success).label(format!("Pour ✅ {yes}")),
-                CreateButton::new(make_custom_id::<Repost>("vote-no", thread.id)).style(ButtonStyle::Danger).label(format!("Contre ❌ {no}")),
-                CreateButton::new(make_custom_id::<Repost>("see-votes", thread.id)).style(ButtonStyle::Secondary).label("Voir les votes".to_string()),
+                CreateButton::new(VoteButton::Yes(thread.id).encode()?).style(ButtonStyle::Success).label(format!("Pour ✅ {yes}")),
+                CreateButton::new(VoteButton::No(thread.id).encode()?).style(ButtonStyle::Danger).label(format!("Contre ❌ {no}")),
+                CreateButton::new(VoteButton::SeeVotes(thread.id).encode()?).style(ButtonStyle::Secondary).label("Voir les votes".to_string()),
             ];
 
             let mut message = config.vote_message.message(&ctx.http).await?;
@@ -160,13 +243,21 @@ impl Repost {
     }
 
     async fn save_config(&self, config: &RepostConfig) -> Result<(), Error> {
-        if let Err(err) = Config::get().save_module_config::<Repost, RepostConfig>(&config) {
+        if let Err(err) = Config::get().save_module_config::<Repost, RepostConfig>(config) {
             Err(Error::msg(format!("Failed to save repost config : {}", err)))
         } else {
             Ok(())
         }
     }
 
+    /// Persist the votes of the given thread
+    fn save_votes(&self, config: &RepostConfig, thread: ChannelId) -> Result<(), Error> {
+        match config.votes.get(&thread) {
+            None => { self.votes_table.remove(&thread) }
+            Some(votes) => { self.votes_table.set(&thread, votes) }
+        }
+    }
+
     async fn user_vote(&self, ctx: Context, channel: ChannelId, component: ComponentInteraction, is_yes: bool) -> Result<(), BidibipError> {
         let mut config = self.repost_config.write().await;
         if let Some(vote_config) = config.votes.get_mut(&channel) {
@@ -185,24 +276,24 @@ impl Repost {
 
             if is_yes {
                 vote_config.no.remove(&component.user.id);
-                if vote_config.yes.contains_key(&component.user.id) {
-                    vote_config.yes.remove(&component.user.id);
+                if let std::collections::hash_map::Entry::Vacant(e) = vote_config.yes.entry(component.user.id) {
+                    e.insert(Username::from_user(&component.user));
                 } else {
-                    vote_config.yes.insert(component.user.id, Username::from_user(&component.user));
+                    vote_config.yes.remove(&component.user.id);
                 }
 
                 on_fail!(self.update_vote_messages(&ctx, guild, &config).await, "Failed to update vote messages")?;
             } else {
                 vote_config.yes.remove(&component.user.id);
-                if vote_config.no.contains_key(&component.user.id) {
-                    vote_config.no.remove(&component.user.id);
+                if let std::collections::hash_map::Entry::Vacant(e) = vote_config.no.entry(component.user.id) {
+                    e.insert(Username::from_user(&component.user));
                 } else {
-                    vote_config.no.insert(component.user.id, Username::from_user(&component.user));
+                    vote_config.no.remove(&component.user.id);
                 }
                 on_fail!(self.update_vote_messages(&ctx, guild, &config).await, "Failed to update vote messages")?;
             }
         }
-        on_fail!(self.save_config(&config).await, "failed to save config")?;
+        on_fail!(self.save_votes(&config, channel), "failed to save votes")?;
         on_fail!(component.create_response(&ctx.http,CreateInteractionResponse::Message(
                                                                             CreateInteractionResponseMessage::new()
                                                                                 .ephemeral(true)
@@ -213,50 +304,38 @@ impl Repost {
 
 #[serenity::async_trait]
 impl BidibipModule for Repost {
-    async fn execute_command(&self, ctx: Context, name: &str, command: CommandInteraction) -> Result<(), BidibipError> {
-        match name {
-            "set-forum-link" => {
-                let forum = assert_some!(command.data.options().find("forum"), "missing forum parameter")?;
-                let forum = if let ResolvedValue::Channel(channel) = forum {
-                    assert_condition!(channel.kind == ChannelType::Forum, "Not a forum")?;
-                    channel.id
-                } else {
-                    error!("forum parameter is not a channel");
-                    return Err(BidibipError::msg("forum parameter is not a channel"));
-                };
-
-                let forum_param = assert_some!(command.data.options().find("repost-channel"), "missing repost-channel parameter")?;
-                let channel = if let ResolvedValue::Channel(channel) = forum_param {
-                    assert_condition!(channel.kind == ChannelType::Text, "Not a regular channel")?;
-                    channel.id
-                } else {
-                    error!("repost-channel parameter is not a channel");
-                    return Err(BidibipError::msg("repost-channel parameter is not a channel"));
-                };
+    async fn validate_config(&self, validator: &mut ConfigValidator) {
+        self.repost_config.read().await.validate(validator);
+    }
 
-                let vote = match command.data.options().find("vote") {
-                    None => { false }
-                    Some(vote) => {
-                        if let ResolvedValue::Boolean(vote) = vote {
-                            vote
-                        } else {
-                            error!("vote option is not a boolean");
-                            return Err(BidibipError::msg("vote option is not a boolean"));
-                        }
-                    }
-                };
+    async fn reload_config(&self, validator: &mut ConfigValidator) -> Result<bool, Error> {
+        let mut config = Config::get().read_module_config::<Repost, RepostConfig>()?;
+        config.validate(validator);
+        if validator.is_valid() {
+            let mut current = self.repost_config.write().await;
+            // Votes are not stored in the json file
+            config.votes = std::mem::take(&mut current.votes);
+            *current = config;
+        }
+        Ok(true)
+    }
 
-                let enabled = match command.data.options().find("enabled") {
-                    None => { true }
-                    Some(enabled) => {
-                        if let ResolvedValue::Boolean(enabled) = enabled {
-                            enabled
-                        } else {
-                            error!("Enable option is not a boolean");
-                            return Err(BidibipError::msg("Enable option is not a boolean"));
-                        }
-                    }
-                };
+    async fn execute_command(&self, ctx: Context, _: &str, command: CommandInteraction) -> Result<(), BidibipError> {
+        let parsed = match command.parse_command::<RepostCommand>(&ctx.http).await {
+            None => { return Ok(()) }
+            Some(parsed) => { parsed }
+        };
+        match parsed {
+            RepostCommand::SetForumLink(SetForumLinkCommand { forum, channel, vote, enabled }) => {
+                if forum.kind != ChannelType::Forum {
+                    command.respond_user_error(&ctx.http, "L'option `forum` doit être un forum").await;
+                    return Ok(());
+                }
+                if channel.kind != ChannelType::Text {
+                    command.respond_user_error(&ctx.http, "L'option `repost-channel` doit être un salon textuel").await;
+                    return Ok(());
+                }
+                let (forum, channel) = (forum.id, channel.id);
 
                 if enabled {
                     let mut repost_config = self.repost_config.write().await;
@@ -275,16 +354,14 @@ impl BidibipModule for Repost {
                     on_fail!(command.create_response(&ctx.http, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().ephemeral(true).content(format!("Forum {} déconnecté du channel {} !", forum.mention(), channel.mention())))).await, "Failed to send confirmation message")?;
                 }
             }
-            "reposte" => {
+            RepostCommand::Reposte(ReposteCommand { message }) => {
                 on_fail!(command.defer_ephemeral(&ctx.http).await, "Failed to defer command interaction")?;
 
                 let thread = assert_some!(on_fail!(command.channel_id.to_channel(&ctx.http).await, "Failed to get thread")?.guild(), "Failed to get guild thread")?;
 
-                let message = &assert_some!(command.data.options().find("message"), "Missing option 'message'")?;
-
-                let reposted_message = if let ResolvedValue::String(message) = message {
+                let reposted_message = {
                     let id = match message.split("/").last() {
-                        None => { message }
+                        None => { message.as_str() }
                         Some(last) => { last }
                     };
                     match u64::from_str(id) {
@@ -298,18 +375,15 @@ impl BidibipModule for Repost {
                             }
                         }
                         Err(_) => {
-                            on_fail_warn!(command.edit_response(&ctx.http, EditInteractionResponse::new().content(format!("L'option message doit être un identifiant de message ou le lien vers le message"))).await, "Failed to edit response");
+                            on_fail_warn!(command.edit_response(&ctx.http, EditInteractionResponse::new().content("L'option message doit être un identifiant de message ou le lien vers le message".to_string())).await, "Failed to edit response");
                             return Ok(());
                         }
                     }
-                } else {
-                    error!("Not a stringValue");
-                    return Err(BidibipError::msg("Not a stringValue"));
                 };
 
                 let forum = match thread.parent_id {
                     None => {
-                        on_fail_warn!(command.edit_response(&ctx.http, EditInteractionResponse::new().content(format!("La commande doit être exécutée depuis un fil qui t'appartient"))).await, "Failed to edit response");
+                        on_fail_warn!(command.edit_response(&ctx.http, EditInteractionResponse::new().content("La commande doit être exécutée depuis un fil qui t'appartient".to_string())).await, "Failed to edit response");
                         return Ok(());
                     }
                     Some(forum) => {
@@ -320,7 +394,7 @@ impl BidibipModule for Repost {
                 let mut config = self.repost_config.write().await;
                 let repost_config = match config.forums.get(&forum.id) {
                     None => {
-                        on_fail_warn!(command.edit_response(&ctx.http, EditInteractionResponse::new().content(format!("La fonctionnalité de reposte n'est pas disponible dans ce contexte"))).await, "Failed to edit response");
+                        on_fail_warn!(command.edit_response(&ctx.http, EditInteractionResponse::new().content("La fonctionnalité de reposte n'est pas disponible dans ce contexte".to_string())).await, "Failed to edit response");
                         return Ok(());
                     }
                     Some(forum_config) => { forum_config.clone() }
@@ -337,28 +411,18 @@ impl BidibipModule for Repost {
                     }
                 }
                 if repost_config.vote_enabled {
-                    on_fail!(self.save_config(&config).await, "Failed to save config")?;
+                    on_fail!(self.save_votes(&config, thread.id), "Failed to save votes")?;
                     on_fail!(self.update_vote_messages(&ctx, thread, &config).await, "Failed to update vote messages")?;
                 }
                 on_fail!(command.edit_response(&ctx.http, EditInteractionResponse::new().content("Message reposté !")).await, "Failed to send confirmation message")?;
             }
-            &_ => {}
         }
         Ok(())
     }
 
     fn fetch_commands(&self, config: &PermissionData) -> Vec<CreateCommandDetailed> {
-        vec![CreateCommandDetailed::new("set-forum-link")
-                 .description("Lie un forum à un channel de repost")
-                 .add_option(CreateCommandOption::new(CommandOptionType::Channel, "forum", "Forum où seront suivis les nouveaux posts").required(true))
-                 .add_option(CreateCommandOption::new(CommandOptionType::Channel, "repost-channel", "Canal où seront repostés les évenements du forum").required(true))
-                 .add_option(CreateCommandOption::new(CommandOptionType::Boolean, "vote", "Active les fonctionnalités de vote").required(true))
-                 .add_option(CreateCommandOption::new(CommandOptionType::Boolean, "enabled", "Active ou désactive le lien").required(true))
-                 .default_member_permissions(config.at_least_admin()),
-             CreateCommandDetailed::new("reposte")
-                 .description("Promeut le message donné dans le salon de repost")
-                 .add_option(CreateCommandOption::new(CommandOptionType::String, "message", "lien du message à promouvoir").required(true))
-                 .default_member_permissions(config.at_least_member())
+        vec![SetForumLinkCommand::create_command().default_member_permissions(config.at_least_admin()),
+             ReposteCommand::create_command().default_member_permissions(config.at_least_member())
         ]
     }
 
@@ -373,45 +437,38 @@ impl BidibipModule for Repost {
     }
 
     async fn interaction_create(&self, ctx: Context, interaction: Interaction) -> Result<(), BidibipError> {
-        match interaction {
-            Interaction::Component(component) => {
-                match component.data.kind {
-                    ComponentInteractionDataKind::Button => {
-                        if let Some(data) = component.data.get_custom_id_data::<Repost>("vote-yes") {
-                            self.user_vote(ctx, ChannelId::new(on_fail!(u64::from_str(data.as_str()), "Payload is not an id")?), component, true).await?;
-                        } else if let Some(data) = component.data.get_custom_id_data::<Repost>("vote-no") {
-                            self.user_vote(ctx, ChannelId::new(on_fail!(u64::from_str(data.as_str()), "Payload is not an id")?), component, false).await?;
-                        } else if let Some(data) = component.data.get_custom_id_data::<Repost>("see-votes") {
-                            let id = ChannelId::new(on_fail!(u64::from_str(data.as_str()), "Payload is not an id")?);
-
-                            let mut config = self.repost_config.write().await;
-                            if let Some(vote_config) = config.votes.get_mut(&id) {
-                                let mut y_str = String::new();
-                                let mut n_str = String::new();
-
-                                for y in &vote_config.yes {
-                                    y_str += format!("{}\n", y.1.full()).as_str();
-                                }
-                                for n in &vote_config.no {
-                                    n_str += format!("{}\n", n.1.full()).as_str();
-                                }
-
-                                on_fail!(component.create_response(&ctx.http,
-                                                         CreateInteractionResponse::Message(
-                                                             CreateInteractionResponseMessage::new()
-                                                                 .ephemeral(true)
-                                                                 .embed(CreateEmbed::new()
-                                                                     .title("Votes actuels")
-                                                                     .description(format!("Nombre de votes : {}", vote_config.yes.len() + vote_config.no.len()))
-                                                                     .field("Pour ✅", y_str.truncate_text(1024), true)
-                                                                     .field("Contre ❌", n_str.truncate_text(1024), true)))).await, "Failed to send interaction response")?;
+        if let Interaction::Component(component) = interaction {
+            if let ComponentInteractionDataKind::Button = component.data.kind {
+                match VoteButton::decode(&component.data.custom_id) {
+                    None => {}
+                    Some(VoteButton::Yes(thread)) => { self.user_vote(ctx, thread, component, true).await?; }
+                    Some(VoteButton::No(thread)) => { self.user_vote(ctx, thread, component, false).await?; }
+                    Some(VoteButton::SeeVotes(id)) => {
+                        let mut config = self.repost_config.write().await;
+                        if let Some(vote_config) = config.votes.get_mut(&id) {
+                            let mut y_str = String::new();
+                            let mut n_str = String::new();
+
+                            for y in &vote_config.yes {
+                                y_str += format!("{}\n", y.1.full()).as_str();
+                            }
+                            for n in &vote_config.no {
+                                n_str += format!("{}\n", n.1.full()).as_str();
                             }
+
+                            on_fail!(component.create_response(&ctx.http,
+                                                     CreateInteractionResponse::Message(
+                                                         CreateInteractionResponseMessage::new()
+                                                             .ephemeral(true)
+                                                             .embed(CreateEmbed::new()
+                                                                 .title("Votes actuels")
+                                                                 .description(format!("Nombre de votes : {}", vote_config.yes.len() + vote_config.no.len()))
+                                                                 .field("Pour ✅", y_str.truncate_text(1024), true)
+                                                                 .field("Contre ❌", n_str.truncate_text(1024), true)))).await, "Failed to send interaction response")?;
                         }
                     }
-                    _ => {}
                 }
             }
-            _ => {}
         }
         Ok(())
     }
@@ -449,7 +506,7 @@ impl BidibipModule for Repost {
                 };
 
                 let initial_message = assert_some!(messages.first(), format!("Failed to get first message in thread {} after {NB_RETRY} attempts", thread.mention()))?;
-                let thread_owner = on_fail!(GuildId::from(Config::get().server_id).member(&ctx.http,assert_some!(thread.owner_id, "Failed to get owner id")?).await, "Failed to get owner member")?;
+                let thread_owner = on_fail!(thread.guild_id.member(&ctx.http,assert_some!(thread.owner_id, "Failed to get owner id")?).await, "Failed to get owner member")?;
                 let forum_name = on_fail!(potential_forum.name(&ctx.http).await, "Failed to get forum name")?;
 
                 if repost_config.vote_enabled {
@@ -467,7 +524,7 @@ impl BidibipModule for Repost {
 
                 for repost_channel in repost_config.repost_channel {
                     let mut last_repost_message = None;
-                    for message in make_repost_message(&initial_message, &thread, &forum_name, &thread_owner) {
+                    for message in make_repost_message(initial_message, &thread, &forum_name, &thread_owner) {
                         last_repost_message = Some(on_fail!(repost_channel.send_message(&ctx.http, message).await, format!("Failed to repost message {} in {}", initial_message.link(), repost_channel.mention()))?);
                     }
 
@@ -478,7 +535,7 @@ impl BidibipModule for Repost {
                 }
 
                 if repost_config.vote_enabled {
-                    on_fail!(self.save_config(&config).await, "Failed to save config")?;
+                    on_fail!(self.save_votes(&config, thread.id), "Failed to save votes")?;
                     on_fail!(self.update_vote_messages(&ctx, thread, &config).await,"Failed to update vote messages")?;
                 }
             }
@@ -491,8 +548,8 @@ impl BidibipModule for Repost {
         if self.repost_config.read().await.votes.contains_key(&channel.id) {
             let mut config = self.repost_config.write().await;
             config.votes.remove(&channel.id);
-            if let Err(err) = self.save_config(&config).await {
-                error!("{}", err);
+            if let Err(err) = self.save_votes(&config, channel.id) {
+                error!("Failed to save votes : {}", err);
             }
         }
         Ok(())
@@ -509,8 +566,21 @@ impl LoadModule<Repost> for Repost {
         "Permet de lier un salon à un forum"
     }
 
+    fn events() -> ModuleEvents {
+        ModuleEvents::CHANNEL_DELETE | ModuleEvents::INTERACTION_CREATE | ModuleEvents::THREAD_CREATE | ModuleEvents::THREAD_DELETE
+    }
+
     async fn load(_: &Arc<BidibipSharedData>) -> Result<Repost, Error> {
-        let welcome_config = Config::get().load_module_config::<Repost, RepostConfig>()?;
-        Ok(Repost { repost_config: RwLock::new(welcome_config) })
+        let mut repost_config = Config::get().load_module_config::<Repost, RepostConfig>()?;
+        let votes_table = Storage::get().table::<Repost, _, _>("votes")?;
+
+        // Move votes from the json config to the storage
+        if !repost_config.votes.is_empty() {
+            votes_table.import(repost_config.votes.drain())?;
+            Config::get().save_module_config::<Repost, RepostConfig>(&repost_config)?;
+        }
+        repost_config.votes = votes_table.entries()?.into_iter().collect();
+
+        Ok(Repost { repost_config: RwLock::new(repost_config), votes_table })
     }
 }
\ No newline at end of file
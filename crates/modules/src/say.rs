@@ -1,19 +1,39 @@
 use std::sync::Arc;
 use anyhow::Error;
-use serenity::all::{CommandInteraction, CommandOptionType, CommandType, Context, CreateCommandOption, ResolvedValue, async_trait};
+use serenity::all::{Attachment, CommandInteraction, Context, async_trait};
 use serde::{Deserialize, Serialize};
 use utils::global_interface::BidibipSharedData;
 use utils::module::{LoadModule, BidibipModule};
+use utils::dispatch::ModuleEvents;
 use utils::error::BidibipError;
-use utils::on_fail;
+use utils::{on_fail, command_options, command_router};
+use utils::slash_command::{CommandParser, SlashCommand};
 use utils::json_to_message::json_to_message;
 use utils::create_command_detailed::CreateCommandDetailed;
 use utils::global_interface::PermissionData;
-use utils::utilities::{CommandHelper, OptionHelper, ResultDebug};
+use utils::utilities::{CommandHelper, ResultDebug};
 
 #[derive(Serialize, Deserialize)]
 pub struct Say {}
 
+command_options! {
+    struct SayCommand {
+        message: Option<String> => ("message", "Que dois-je dire à votre place ?"),
+        file: Option<Attachment> => ("fichier", "Fichier json pour afficher un message formaté"),
+    }
+}
+
+impl SlashCommand for SayCommand {
+    const NAME: &'static str = "say";
+    const DESCRIPTION: &'static str = "Ma parole sera la votre";
+}
+
+command_router! {
+    enum SayCommands {
+        Say(SayCommand),
+    }
+}
+
 #[async_trait]
 impl LoadModule<Say> for Say {
     fn name() -> &'static str {
@@ -24,6 +44,10 @@ impl LoadModule<Say> for Say {
         "Fait parler bidibip"
     }
 
+    fn events() -> ModuleEvents {
+        ModuleEvents::empty()
+    }
+
     async fn load(_: &Arc<BidibipSharedData>) -> Result<Say, Error> {
         Ok(Say {})
     }
@@ -31,37 +55,30 @@ impl LoadModule<Say> for Say {
 
 #[async_trait]
 impl BidibipModule for Say {
-    async fn execute_command(&self, ctx: Context, name: &str, command: CommandInteraction) -> Result<(), BidibipError> {
-        if name == "say" {
-            if let Some(option) = command.data.options().find("message") {
-                if let ResolvedValue::String(str) = option {
-                    command.channel_id.say(&ctx.http, str).await.on_fail("Failed to send message in channel");
-                    command.skip(&ctx.http).await;
-                }
-            } else if let Some(option) = command.data.options().find("fichier") {
-                if let ResolvedValue::Attachment(attachment) = option {
-                    on_fail!(command.defer(&ctx.http).await, "Failed to defer say command")?;
+    async fn execute_command(&self, ctx: Context, _: &str, command: CommandInteraction) -> Result<(), BidibipError> {
+        let SayCommands::Say(say) = match command.parse_command::<SayCommands>(&ctx.http).await {
+            None => { return Ok(()) }
+            Some(parsed) => { parsed }
+        };
+        if let Some(str) = say.message {
+            command.channel_id.say(&ctx.http, str).await.on_fail("Failed to send message in channel");
+            command.skip(&ctx.http).await;
+        } else if let Some(attachment) = say.file {
+            on_fail!(command.defer(&ctx.http).await, "Failed to defer say command")?;
 
-                    let message = on_fail!(String::from_utf8(on_fail!(attachment.download().await, "Failed to download attachment")?), "Our bytes should be valid utf8")?;
-                    let message = on_fail!(json_to_message(message), "Invalid json_to_message")?;
-                    for message in message {
-                        command.channel_id.send_message(&ctx.http, message).await.on_fail("Failed to send message in channel");
-                    }
-                    command.delete_response(&ctx.http).await.on_fail("Failed to delete command interaction");
-                }
-            } else {
-                command.respond_user_error(&ctx.http, "Tu n'as pas précisé ce que je dois annoncer !").await;
+            let message = on_fail!(String::from_utf8(on_fail!(attachment.download().await, "Failed to download attachment")?), "Our bytes should be valid utf8")?;
+            let message = on_fail!(json_to_message(message), "Invalid json_to_message")?;
+            for message in message {
+                command.channel_id.send_message(&ctx.http, message).await.on_fail("Failed to send message in channel");
             }
+            command.delete_response(&ctx.http).await.on_fail("Failed to delete command interaction");
+        } else {
+            command.respond_user_error(&ctx.http, "Tu n'as pas précisé ce que je dois annoncer !").await;
         }
         Ok(())
     }
 
     fn fetch_commands(&self, config: &PermissionData) -> Vec<CreateCommandDetailed> {
-        vec![CreateCommandDetailed::new("say")
-            .default_member_permissions(config.at_least_member())
-            .description("Ma parole sera la votre")
-            .kind(CommandType::ChatInput)
-            .add_option(CreateCommandOption::new(CommandOptionType::String, "message", "Que dois-je dire à votre place ?"))
-            .add_option(CreateCommandOption::new(CommandOptionType::Attachment, "fichier", "Fichier json pour afficher un message formaté"))]
+        vec![SayCommand::create_command().default_member_permissions(config.at_least_member())]
     }
-}
\ No newline at end of file
+}
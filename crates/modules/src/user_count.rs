@@ -3,6 +3,10 @@ use utils::config::Config;
 use utils::error::BidibipError;
 use utils::global_interface::BidibipSharedData;
 use utils::module::{BidibipModule, LoadModule};
+use utils::dispatch::ModuleEvents;
+use utils::scheduler::{JobSchedule, ScheduledJob};
+use utils::on_fail;
+use utils::metrics::Metrics;
 use serde::{Deserialize, Serialize};
 use serenity::all::{ActivityData, Context, GuildId, Http, Member, MembersIter, Ready, User};
 use std::sync::atomic::{AtomicUsize, Ordering};
@@ -10,15 +14,45 @@ use std::sync::Arc;
 use serenity::futures::StreamExt;
 use tracing::info;
 
+// Name of the recurring job recounting the members, in case some events were missed
+const REFRESH_JOB: &str = "refresh";
+// Every hour
+const REFRESH_SCHEDULE: &str = "0 0 * * * *";
+
 #[derive(Serialize, Deserialize)]
 pub struct UserCount {
     user_count: AtomicUsize,
+    #[serde(skip)]
+    shared_data: Option<Arc<BidibipSharedData>>,
 }
 
 impl UserCount {
     fn update(&self, ctx: Context) {
+        let metrics = Metrics::get();
+        metrics.members.set(self.user_count.load(Ordering::SeqCst) as i64);
+        metrics.cached_users.set(ctx.cache.user_count() as i64);
+        metrics.cached_guilds.set(ctx.cache.guild_count() as i64);
+        metrics.cached_channels.set(ctx.cache.guild_channel_count() as i64);
         ctx.set_activity(Some(ActivityData::custom(format!("Nous sommes {} membres", self.user_count.load(Ordering::SeqCst)))));
     }
+
+    async fn recount(&self, ctx: Context) {
+        let mut all_members: Vec<Member> = vec![];
+
+        {
+            let mut members = MembersIter::<Http>::stream(&ctx, Config::get().server_id).boxed();
+            while let Some(member_result) = members.next().await {
+                if let Ok(member) = member_result {
+                    all_members.push(member);
+                }
+            }
+        }
+
+        let count = all_members.len();
+        info!("There is {} users", all_members.len());
+        self.user_count.store(count, Ordering::SeqCst);
+        self.update(ctx);
+    }
 }
 
 #[serenity::async_trait]
@@ -31,41 +65,49 @@ impl LoadModule<UserCount> for UserCount {
         "Compte le nombre de membres et l'affiche dans l'activité de Bidibip"
     }
 
-    async fn load(_: &Arc<BidibipSharedData>) -> Result<UserCount, Error> {
+    fn events() -> ModuleEvents {
+        ModuleEvents::GUILD_MEMBER_ADDITION | ModuleEvents::GUILD_MEMBER_REMOVAL | ModuleEvents::READY
+    }
+
+    async fn load(shared_data: &Arc<BidibipSharedData>) -> Result<UserCount, Error> {
         Ok(Self {
             user_count: AtomicUsize::default(),
+            shared_data: Some(shared_data.clone()),
         })
     }
 }
 #[serenity::async_trait]
 impl BidibipModule for UserCount {
-    async fn guild_member_addition(&self, ctx: Context, _: Member) -> Result<(), BidibipError> {
+    // The activity is shared by every guild : only the members of the main guild are counted
+    async fn guild_member_addition(&self, ctx: Context, new_member: Member) -> Result<(), BidibipError> {
+        if new_member.guild_id != Config::get().server_id {
+            return Ok(());
+        }
         self.user_count.fetch_add(1, Ordering::SeqCst);
         self.update(ctx);
         Ok(())
     }
-    async fn guild_member_removal(&self, ctx: Context, _: GuildId, _: User, _: Option<Member>) -> Result<(), BidibipError> {
+    async fn guild_member_removal(&self, ctx: Context, guild_id: GuildId, _: User, _: Option<Member>) -> Result<(), BidibipError> {
+        if guild_id != Config::get().server_id {
+            return Ok(());
+        }
         self.user_count.fetch_sub(1, Ordering::SeqCst);
         self.update(ctx);
         Ok(())
     }
 
     async fn ready(&self, ctx: Context, _: Ready) -> Result<(), BidibipError> {
-        let mut all_members: Vec<Member> = vec![];
-
-        {
-            let mut members = MembersIter::<Http>::stream(&ctx, Config::get().server_id).boxed();
-            while let Some(member_result) = members.next().await {
-                if let Ok(member) = member_result {
-                    all_members.push(member);
-                }
-            }
+        self.recount(ctx).await;
+        if let Some(shared_data) = &self.shared_data {
+            on_fail!(shared_data.scheduler().schedule_once_named::<Self>(REFRESH_JOB, (), JobSchedule::cron(REFRESH_SCHEDULE)?).await, "Failed to schedule member count refresh")?;
         }
+        Ok(())
+    }
 
-        let count = all_members.len();
-        info!("There is {} users", all_members.len());
-        self.user_count.store(count, Ordering::SeqCst);
-        self.update(ctx);
+    async fn run_job(&self, ctx: Context, job: ScheduledJob) -> Result<(), BidibipError> {
+        if job.name == REFRESH_JOB {
+            self.recount(ctx).await;
+        }
         Ok(())
     }
 }
\ No newline at end of file
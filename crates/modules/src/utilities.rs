@@ -1,25 +1,31 @@
 use std::fs::File;
 use std::{io};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::env::current_exe;
-use std::ops::Deref;
 use std::path::Path;
 use std::process::exit;
 use std::sync::Arc;
 use anyhow::Error;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use serenity::all::{CommandInteraction, CommandOptionType, CommandType, Context, CreateCommandOption, CreateInteractionResponse, CreateInteractionResponseMessage, Ready, ResolvedValue};
+use serde_json::Value;
+use serenity::all::{CommandInteraction, Context, CreateCommandOption, CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage, EditInteractionResponse, GuildId, PartialChannel, Ready, Role, User};
 use tokio::sync::RwLock;
-use tracing::info;
+use tracing::{info, warn};
 use utils::module::{LoadModule, BidibipModule};
+use utils::dispatch::ModuleEvents;
 use utils::global_interface::BidibipSharedData;
 use utils::error::BidibipError;
 use utils::config::Config;
 use utils::global_interface::PermissionData;
 use utils::create_command_detailed::CreateCommandDetailed;
-use utils::{on_fail, assert_some};
-use utils::utilities::{OptionHelper, TruncateText};
+use utils::{on_fail, assert_some, command_options, command_router, command_subcommands};
+use utils::permissions::AllowList;
+use utils::reload::ConfigReload;
+use utils::config_editor::{edit_list, field_paths, get_field, parse_text_value};
+use utils::slash_command::{autocomplete, AutocompleteHelper, CommandParser, SlashCommand};
+use utils::utilities::{CommandHelper, TruncateText, Username};
+use utils::logger::{log_filters, set_log_filter, LogSink, AUDIT_TARGET};
 
 pub struct Utilities {
     shared_data: Arc<BidibipSharedData>,
@@ -29,6 +35,9 @@ pub struct Utilities {
 #[derive(Serialize, Deserialize, Default)]
 struct UtilitiesConfig {
     disabled_modules: HashSet<String>,
+    // Modules disabled on a single guild
+    #[serde(default)]
+    guild_disabled_modules: HashMap<GuildId, HashSet<String>>,
     current_version_release_date: Option<String>,
 }
 
@@ -42,11 +51,238 @@ impl LoadModule<Utilities> for Utilities {
         "Utilitaires de modération"
     }
 
+    fn events() -> ModuleEvents {
+        ModuleEvents::READY
+    }
+
     async fn load(shared_data: &Arc<BidibipSharedData>) -> Result<Utilities, Error> {
         Ok(Utilities { shared_data: shared_data.clone(), utilities_config: RwLock::new(Config::get().load_module_config::<Utilities, UtilitiesConfig>()?) })
     }
 }
 
+command_options! {
+    struct ModulesCommand {}
+}
+
+impl SlashCommand for ModulesCommand {
+    const NAME: &'static str = "modules";
+    const DESCRIPTION: &'static str = "Informations sur les modules";
+}
+
+command_options! {
+    struct UpdateCommand {}
+}
+
+impl SlashCommand for UpdateCommand {
+    const NAME: &'static str = "update";
+    const DESCRIPTION: &'static str = "Redémarre et mets à jour Bidibip";
+}
+
+command_options! {
+    struct SetModuleEnabledCommand {
+        module: String => ("module", "nom du module concerné", autocomplete),
+        enabled: bool => ("activer", "active ou désactive le module"),
+        local: Option<bool> => ("ce-serveur", "n'applique le changement qu'à ce serveur"),
+    }
+}
+
+impl SlashCommand for SetModuleEnabledCommand {
+    const NAME: &'static str = "set-module-enabled";
+    const DESCRIPTION: &'static str = "Active ou désactive un module";
+}
+
+command_options! {
+    struct ConfigReloadCommand {
+        module: String => ("module", "nom du module concerné", autocomplete),
+    }
+}
+
+command_options! {
+    struct ConfigListCommand {
+        module: String => ("module", "nom du module concerné", autocomplete),
+    }
+}
+
+command_options! {
+    struct ConfigGetCommand {
+        module: String => ("module", "nom du module concerné", autocomplete),
+        path: String => ("champ", "chemin du champ (ex: forums.1234.vote)", autocomplete),
+    }
+}
+
+command_options! {
+    struct ConfigSetCommand {
+        module: String => ("module", "nom du module concerné", autocomplete),
+        path: String => ("champ", "chemin du champ (ex: forums.1234.vote)", autocomplete),
+        text: Option<String> => ("valeur", "texte, json ou liste séparée par des virgules"),
+        channel: Option<PartialChannel> => ("salon", "salon (ajouté si le champ est une liste)"),
+        role: Option<Role> => ("role", "rôle (ajouté si le champ est une liste)"),
+        number: Option<f64> => ("nombre", "nombre (ajouté si le champ est une liste)"),
+        boolean: Option<bool> => ("booleen", "vrai ou faux"),
+        remove: Option<bool> => ("retirer", "retire la valeur de la liste au lieu de l'ajouter"),
+    }
+}
+
+command_subcommands! {
+    enum ConfigCommand {
+        Reload(ConfigReloadCommand) => ("reload", "Recharge la configuration d'un module depuis son fichier"),
+        List(ConfigListCommand) => ("list", "Liste les champs de la configuration d'un module"),
+        Get(ConfigGetCommand) => ("get", "Affiche la valeur d'un champ de la configuration d'un module"),
+        Set(ConfigSetCommand) => ("set", "Modifie un champ de la configuration d'un module"),
+    }
+}
+
+impl ConfigSetCommand {
+    /// Build the new value of the field from the provided option
+    fn new_value(&self, current: Option<&Value>) -> Result<Value, Error> {
+        let provided = [self.text.is_some(), self.channel.is_some(), self.role.is_some(), self.number.is_some(), self.boolean.is_some()];
+        if provided.iter().filter(|provided| **provided).count() != 1 {
+            return Err(Error::msg("Une seule valeur doit être renseignée (valeur, salon, role, nombre ou booleen)"));
+        }
+        let remove = self.remove.unwrap_or(false);
+        let is_list = matches!(current, Some(Value::Array(_)));
+
+        let value = if let Some(text) = &self.text {
+            if is_list && !remove {
+                return Ok(parse_text_value(text, current));
+            }
+            parse_text_value(text, None)
+        } else if let Some(channel) = &self.channel {
+            serde_json::to_value(channel.id)?
+        } else if let Some(role) = &self.role {
+            serde_json::to_value(role.id)?
+        } else if let Some(number) = self.number {
+            if number.fract() == 0.0 { Value::from(number as i64) } else { Value::from(number) }
+        } else {
+            Value::from(self.boolean.unwrap_or_default())
+        };
+
+        if is_list {
+            edit_list(current, value, remove)
+        } else if remove {
+            Err(Error::msg("`retirer` ne s'applique qu'aux listes"))
+        } else {
+            Ok(value)
+        }
+    }
+}
+
+impl SlashCommand for ConfigCommand {
+    const NAME: &'static str = "config";
+    const DESCRIPTION: &'static str = "Configuration des modules";
+}
+
+command_options! {
+    struct PermissionsListCommand {}
+}
+
+command_options! {
+    struct PermissionsAddCommand {
+        target: String => ("cible", "commande (/nom) ou action (module::action)", autocomplete),
+        role: Option<Role> => ("role", "rôle autorisé"),
+        user: Option<User> => ("utilisateur", "utilisateur autorisé"),
+    }
+}
+
+command_options! {
+    struct PermissionsRemoveCommand {
+        target: String => ("cible", "commande (/nom) ou action (module::action)", autocomplete),
+        role: Option<Role> => ("role", "rôle retiré"),
+        user: Option<User> => ("utilisateur", "utilisateur retiré"),
+    }
+}
+
+command_options! {
+    struct PermissionsClearCommand {
+        target: String => ("cible", "commande (/nom) ou action (module::action)", autocomplete),
+    }
+}
+
+command_subcommands! {
+    enum PermissionsCommand {
+        List(PermissionsListCommand) => ("list", "Liste les commandes et actions restreintes sur ce serveur"),
+        Add(PermissionsAddCommand) => ("add", "Autorise un rôle ou un utilisateur à utiliser une commande ou une action"),
+        Remove(PermissionsRemoveCommand) => ("remove", "Retire un rôle ou un utilisateur de la liste d'une commande ou d'une action"),
+        Clear(PermissionsClearCommand) => ("clear", "Supprime la restriction d'une commande ou d'une action"),
+    }
+}
+
+impl SlashCommand for PermissionsCommand {
+    const NAME: &'static str = "permissions";
+    const DESCRIPTION: &'static str = "Restreint les commandes et actions à certains rôles ou utilisateurs";
+}
+
+command_options! {
+    struct JobsListCommand {}
+}
+
+command_options! {
+    struct JobsCancelCommand {
+        id: i64 => ("id", "identifiant de la tâche (voir /jobs list)"),
+    }
+}
+
+command_subcommands! {
+    enum JobsCommand {
+        List(JobsListCommand) => ("list", "Liste les tâches planifiées des modules"),
+        Cancel(JobsCancelCommand) => ("cancel", "Annule une tâche planifiée"),
+    }
+}
+
+impl SlashCommand for JobsCommand {
+    const NAME: &'static str = "jobs";
+    const DESCRIPTION: &'static str = "Tâches planifiées des modules";
+}
+
+fn log_sink_choices(mut option: CreateCommandOption) -> CreateCommandOption {
+    for sink in LogSink::ALL {
+        option = option.add_string_choice(sink.name(), sink.name());
+    }
+    option
+}
+
+command_options! {
+    struct LoglevelListCommand {}
+}
+
+command_options! {
+    struct LoglevelSetCommand {
+        sink: String => ("sortie", "sortie des logs", log_sink_choices),
+        filter: String => ("filtre", "directives de filtre (ex : info,modules::warn=debug)"),
+    }
+}
+
+command_options! {
+    struct LoglevelResetCommand {
+        sink: String => ("sortie", "sortie des logs", log_sink_choices),
+    }
+}
+
+command_subcommands! {
+    enum LoglevelCommand {
+        List(LoglevelListCommand) => ("list", "Affiche les filtres des logs"),
+        Set(LoglevelSetCommand) => ("set", "Modifie le filtre d'une sortie des logs jusqu'au prochain redémarrage"),
+        Reset(LoglevelResetCommand) => ("reset", "Remet le filtre d'une sortie des logs à sa valeur de la configuration"),
+    }
+}
+
+impl SlashCommand for LoglevelCommand {
+    const NAME: &'static str = "loglevel";
+    const DESCRIPTION: &'static str = "Filtres des logs";
+}
+
+command_router! {
+    enum UtilitiesCommand {
+        Modules(ModulesCommand),
+        Update(UpdateCommand),
+        SetModuleEnabled(SetModuleEnabledCommand),
+        Config(ConfigCommand),
+        Permissions(PermissionsCommand),
+        Jobs(JobsCommand),
+        Loglevel(LoglevelCommand),
+    }
+}
+
 #[derive(Deserialize)]
 struct AssetData {
     name: String,
@@ -60,6 +296,28 @@ struct ReleaseData {
     assets: Vec<AssetData>,
 }
 
+impl Utilities {
+    /// Replace the filter of a log sink, or restore the one of the config
+    async fn set_log_filter(&self, ctx: &Context, command: &CommandInteraction, sink: &str, filter: Option<String>) -> Result<(), BidibipError> {
+        let sink = match LogSink::from_name(sink) {
+            None => {
+                command.respond_user_error(&ctx.http, format!("La sortie `{}` n'existe pas", sink)).await;
+                return Ok(());
+            }
+            Some(sink) => { sink }
+        };
+        let filter = filter.unwrap_or_else(|| sink.configured_filter().to_string());
+        if let Err(err) = set_log_filter(sink, &filter) {
+            command.respond_user_error(&ctx.http, format!("Impossible d'appliquer le filtre `{}` : {}", filter, err)).await;
+            return Ok(());
+        }
+        info!("Log filter of {} set to '{}' by {}", sink.name(), filter, Username::from_user(&command.user).full());
+        on_fail!(command.create_response(&ctx.http, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().content(
+            format!("Filtre de **{}** : `{}`", sink.name(), filter)).ephemeral(true))).await, "Failed to respond")?;
+        Ok(())
+    }
+}
+
 #[serenity::async_trait]
 impl BidibipModule for Utilities {
     async fn ready(&self, ctx: Context, _: Ready) -> Result<(), BidibipError> {
@@ -72,30 +330,45 @@ impl BidibipModule for Utilities {
         for module in &config.disabled_modules {
             self.shared_data.set_module_enabled(&ctx, module.as_str(), false, false).await;
         }
+        for (guild, modules) in &config.guild_disabled_modules {
+            for module in modules {
+                self.shared_data.set_module_enabled_in_guild(&ctx, *guild, module.as_str(), false, false).await;
+            }
+        }
         Ok(())
     }
 
-    async fn execute_command(&self, ctx: Context, cmd: &str, command: CommandInteraction) -> Result<(), BidibipError> {
-        match cmd {
-            "modules" => {
+    async fn execute_command(&self, ctx: Context, _: &str, command: CommandInteraction) -> Result<(), BidibipError> {
+        let parsed = match command.parse_command::<UtilitiesCommand>(&ctx.http).await {
+            None => { return Ok(()) }
+            Some(parsed) => { parsed }
+        };
+        match parsed {
+            UtilitiesCommand::Modules(_) => {
                 let enabled_modules = self.shared_data.get_enabled_modules().await;
                 let disabled_modules = self.shared_data.get_disabled_modules().await;
-                let mut available_modules = String::new();
-                for module in enabled_modules.deref() {
-                    available_modules += format!(":white_check_mark: `{}` : {}\n", module.name, module.description).as_str();
-                }
-
-                for module in disabled_modules.deref() {
-                    available_modules += format!(":x: `{}` : {}\n", module.name, module.description).as_str();
+                let mut embed = CreateEmbed::new().title(format!("{} / {} modules disponibles", enabled_modules.len(), disabled_modules.len() + enabled_modules.len()));
+                for (module, enabled) in enabled_modules.iter().map(|module| (module, true)).chain(disabled_modules.iter().map(|module| (module, false))) {
+                    let mut description = module.description.clone();
+                    let enabled = enabled && self.shared_data.is_module_enabled(&module.name, command.guild_id).await;
+                    if enabled != self.shared_data.is_module_enabled(&module.name, None).await {
+                        description += " (désactivé sur ce serveur)";
+                    }
+                    if module.error_count() > 0 {
+                        description += format!(" (:warning: {} erreur(s))", module.error_count()).as_str();
+                    }
+                    let events: Vec<String> = module.events.kinds().iter().map(|kind| format!("`{}`", kind)).collect();
+                    description += format!("\n-# événements : {}", if events.is_empty() { "aucun".to_string() } else { events.join(", ") }).as_str();
+                    embed = embed.field(format!("{} {}", if enabled { ":white_check_mark:" } else { ":x:" }, module.name).truncate_text(256), description.truncate_text(1024), false);
                 }
 
                 on_fail!(command.create_response(&ctx.http, CreateInteractionResponse::Message(
                 CreateInteractionResponseMessage::new()
-                    .content(format!("# {} / {} modules disponibles:\n{}", enabled_modules.len(), disabled_modules.len() + enabled_modules.len(), available_modules.truncate_text(1900)))
+                    .embed(embed)
                     .ephemeral(true)
             )).await, "Failed to create response")?;
             }
-            "update" => {
+            UtilitiesCommand::Update(_) => {
                 let client = reqwest::Client::new();
                 let response = on_fail!(client.get("https://api.github.com/repos/Unreal-Engine-FR/Bidibip/releases")
                 .header("User-Agent", "Bidibip-updater")
@@ -160,58 +433,223 @@ impl BidibipModule for Utilities {
                     }
                 }
             }
-            "set-module-enabled" => {
-                let module = assert_some!(command.data.options().find("module"), "missing module option")?;
-                let enabled = assert_some!(command.data.options().find("activer"), "missing activer option")?;
+            UtilitiesCommand::SetModuleEnabled(SetModuleEnabledCommand { module: name, enabled, local }) => {
+                if !self.shared_data.available_modules().await.contains(&name) {
+                    on_fail!(command.create_response(&ctx.http, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().content("Ce module n'existe pas").ephemeral(true))).await, "Failed to respond")?;
+                    return Ok(());
+                }
+                let guild = if local.unwrap_or(false) {
+                    Some(assert_some!(command.guild_id, "Local module change outside of a guild")?)
+                } else {
+                    None
+                };
+                match guild {
+                    None => { self.shared_data.set_module_enabled(&ctx, name.as_str(), enabled, true).await; }
+                    Some(guild) => { self.shared_data.set_module_enabled_in_guild(&ctx, guild, name.as_str(), enabled, true).await; }
+                }
+                on_fail!(command.create_response(&ctx.http, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().content(
+                    format!("Module {} {}{}", name, if enabled { "activé" } else { "désactivé" }, if guild.is_some() { " sur ce serveur" } else { "" })
+                ).ephemeral(true))).await, "Failed to respond")?;
 
-                if let ResolvedValue::String(name) = module {
-                    if let ResolvedValue::Boolean(enabled) = enabled {
-                        if !self.shared_data.available_modules().await.contains(&name.to_string()) {
-                            on_fail!(command.create_response(&ctx.http, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().content("Ce module n'existe pas").ephemeral(true))).await, "Failed to respond")?;
+                let mut config = self.utilities_config.write().await;
+                let disabled_modules = match guild {
+                    None => { &mut config.disabled_modules }
+                    Some(guild) => { config.guild_disabled_modules.entry(guild).or_default() }
+                };
+                if enabled {
+                    disabled_modules.remove(&name);
+                } else {
+                    disabled_modules.insert(name);
+                }
+                on_fail!(Config::get().save_module_config::<Utilities, UtilitiesConfig>(&config), "Failed to save module config")?;
+            }
+            UtilitiesCommand::Config(ConfigCommand::Reload(ConfigReloadCommand { module })) => {
+                on_fail!(command.defer_ephemeral(&ctx.http).await, "Failed to defer response")?;
+                let result = self.shared_data.reload_module_config(&ctx.http, module.as_str()).await;
+                match &result {
+                    ConfigReload::Reloaded => { info!("Config of module {} reloaded by {}", module, Username::from_user(&command.user).full()) }
+                    ConfigReload::UnknownModule => {}
+                    result => { warn!("Failed to reload config of module {} : {}", module, result) }
+                }
+                on_fail!(command.edit_response(&ctx.http, EditInteractionResponse::new().content(format!("{} : {}", module, result).truncate_text(2000))).await, "Failed to respond")?;
+            }
+            UtilitiesCommand::Config(ConfigCommand::List(ConfigListCommand { module })) => {
+                if !self.shared_data.available_modules().await.contains(&module) {
+                    command.respond_user_error(&ctx.http, "Ce module n'existe pas").await;
+                    return Ok(());
+                }
+                let config = match Config::get().read_module_config_value(&module) {
+                    Ok(config) => { config }
+                    Err(err) => {
+                        command.respond_user_error(&ctx.http, format!("Impossible de lire la configuration de {} : {}", module, err)).await;
+                        return Ok(());
+                    }
+                };
+                let mut fields = String::new();
+                for path in field_paths(&config) {
+                    let value = get_field(&config, &path).map(|value| value.to_string()).unwrap_or_default();
+                    fields += format!("`{}` : {}\n", path, value.truncate_text(100)).as_str();
+                }
+                let embed = CreateEmbed::new().title(format!("Configuration de {}", module).truncate_text(256)).description(if fields.is_empty() { "Aucun champ".to_string() } else { fields.truncate_text(4096) });
+                on_fail!(command.create_response(&ctx.http, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().embed(embed).ephemeral(true))).await, "Failed to respond")?;
+            }
+            UtilitiesCommand::Config(ConfigCommand::Get(ConfigGetCommand { module, path })) => {
+                if !self.shared_data.available_modules().await.contains(&module) {
+                    command.respond_user_error(&ctx.http, "Ce module n'existe pas").await;
+                    return Ok(());
+                }
+                let config = match Config::get().read_module_config_value(&module) {
+                    Ok(config) => { config }
+                    Err(err) => {
+                        command.respond_user_error(&ctx.http, format!("Impossible de lire la configuration de {} : {}", module, err)).await;
+                        return Ok(());
+                    }
+                };
+                let value = match get_field(&config, &path) {
+                    None => {
+                        command.respond_user_error(&ctx.http, format!("Le champ `{}` n'existe pas", path)).await;
+                        return Ok(());
+                    }
+                    Some(value) => { format!("{:#}", value) }
+                };
+                on_fail!(command.create_response(&ctx.http, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().content(
+                    format!("`{}.{}` :\n```json\n{}\n```", module, path, value.truncate_text(1900))).ephemeral(true))).await, "Failed to respond")?;
+            }
+            UtilitiesCommand::Config(ConfigCommand::Set(set)) => {
+                if !self.shared_data.available_modules().await.contains(&set.module) {
+                    command.respond_user_error(&ctx.http, "Ce module n'existe pas").await;
+                    return Ok(());
+                }
+                let current = Config::get().read_module_config_value(&set.module).ok().and_then(|config| get_field(&config, &set.path).cloned());
+                let value = match set.new_value(current.as_ref()) {
+                    Ok(value) => { value }
+                    Err(err) => {
+                        command.respond_user_error(&ctx.http, err).await;
+                        return Ok(());
+                    }
+                };
+                on_fail!(command.defer_ephemeral(&ctx.http).await, "Failed to defer response")?;
+                let content = match self.shared_data.set_module_config_field(&ctx.http, &set.module, &set.path, value.clone()).await {
+                    Ok(old_value) => {
+                        let old_value = old_value.map(|value| value.to_string()).unwrap_or(String::from("(none)"));
+                        info!(target: AUDIT_TARGET, "{} changed config {}.{} : {} -> {}", Username::from_user(&command.user).full(), set.module, set.path, old_value, value);
+                        format!("`{}.{}` modifié : {}", set.module, set.path, value)
+                    }
+                    Err(err) => { format!(":boom: **Mince alors !**\n{}", err) }
+                };
+                on_fail!(command.edit_response(&ctx.http, EditInteractionResponse::new().content(content.truncate_text(2000))).await, "Failed to respond")?;
+            }
+            UtilitiesCommand::Permissions(PermissionsCommand::List(_)) => {
+                let guild = assert_some!(command.guild_id, "Permissions command used outside of a guild")?;
+                let mut lists = String::new();
+                for (key, allow_list) in self.shared_data.permission_rules().guild_allow_lists(guild).await {
+                    lists += format!("`{}` : {}\n", key, allow_list).as_str();
+                }
+                let embed = CreateEmbed::new().title("Commandes et actions restreintes").description(if lists.is_empty() { "Aucune restriction".to_string() } else { lists.truncate_text(4096) });
+                on_fail!(command.create_response(&ctx.http, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().embed(embed).ephemeral(true))).await, "Failed to respond")?;
+            }
+            UtilitiesCommand::Permissions(permissions) => {
+                let guild = assert_some!(command.guild_id, "Permissions command used outside of a guild")?;
+                let (target, role, user, remove) = match permissions {
+                    PermissionsCommand::Add(PermissionsAddCommand { target, role, user }) => { (target, role, user, Some(false)) }
+                    PermissionsCommand::Remove(PermissionsRemoveCommand { target, role, user }) => { (target, role, user, Some(true)) }
+                    PermissionsCommand::Clear(PermissionsClearCommand { target }) => { (target, None, None, None) }
+                    PermissionsCommand::List(_) => { return Ok(()); }
+                };
+                if !self.shared_data.permission_keys().await.contains(&target) {
+                    command.respond_user_error(&ctx.http, format!("`{}` n'est ni une commande ni une action connue", target)).await;
+                    return Ok(());
+                }
+                let mut allow_list = self.shared_data.permission_rules().get(guild, &target).await;
+                match remove {
+                    None => { allow_list = AllowList::default(); }
+                    Some(remove) => {
+                        if role.is_none() && user.is_none() {
+                            command.respond_user_error(&ctx.http, "Un rôle ou un utilisateur doit être renseigné").await;
                             return Ok(());
                         }
-                        self.shared_data.set_module_enabled(&ctx, name, enabled, true).await;
-                        on_fail!(command.create_response(&ctx.http, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().content(
-                            if enabled {
-                                format!("Module {} activé", name)
-                            } else {
-                                format!("Module {} désactivé", name)
-                            }
-                        ).ephemeral(true))).await, "Failed to respond")?;
-
-                        let mut config = self.utilities_config.write().await;
-                        if enabled {
-                            config.disabled_modules.remove(&name.to_string());
-                        } else {
-                            config.disabled_modules.insert(name.to_string());
+                        if let Some(role) = &role {
+                            if remove { allow_list.roles.remove(&role.id); } else { allow_list.roles.insert(role.id); }
+                        }
+                        if let Some(user) = &user {
+                            if remove { allow_list.users.remove(&user.id); } else { allow_list.users.insert(user.id); }
                         }
-                        on_fail!(Config::get().save_module_config::<Utilities, UtilitiesConfig>(&config), "Failed to save module config")?;
-                        return Ok(());
                     }
                 }
-
-                on_fail!(command.create_response(&ctx.http, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().content("Invalid parameter").ephemeral(true))).await, "Failed to respond")?;
+                on_fail!(command.defer_ephemeral(&ctx.http).await, "Failed to defer response")?;
+                let synced = on_fail!(self.shared_data.set_allow_list(&ctx, guild, &target, allow_list.clone()).await, "Failed to update allow-list")?;
+                info!(target: AUDIT_TARGET, "{} changed the allow-list of {} : {}", Username::from_user(&command.user).full(), target, allow_list);
+                let mut content = format!("`{}` : {}", target, allow_list);
+                if !synced {
+                    content += "\n-# Les permissions Discord n'ont pas pu être mises à jour, la commande reste visible mais la liste est appliquée par Bidibip.";
+                }
+                on_fail!(command.edit_response(&ctx.http, EditInteractionResponse::new().content(content.truncate_text(2000))).await, "Failed to respond")?;
+            }
+            UtilitiesCommand::Jobs(JobsCommand::List(_)) => {
+                let mut jobs = String::new();
+                for (id, job) in self.shared_data.scheduler().jobs().await {
+                    jobs += format!("`{}` **{}** `{}` : {}, prochaine exécution <t:{}:R>\n", id, job.module, job.name, job.schedule, job.next_run.timestamp()).as_str();
+                }
+                let embed = CreateEmbed::new().title("Tâches planifiées").description(if jobs.is_empty() { "Aucune tâche".to_string() } else { jobs.truncate_text(4096) });
+                on_fail!(command.create_response(&ctx.http, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().embed(embed).ephemeral(true))).await, "Failed to respond")?;
+            }
+            UtilitiesCommand::Jobs(JobsCommand::Cancel(cancel)) => {
+                let job = match u64::try_from(cancel.id) {
+                    Ok(id) => { on_fail!(self.shared_data.scheduler().cancel(id).await, "Failed to cancel job")? }
+                    Err(_) => { None }
+                };
+                match job {
+                    None => { command.respond_user_error(&ctx.http, format!("Il n'y a pas de tâche `{}`", cancel.id)).await; }
+                    Some(job) => {
+                        info!("{} cancelled job {} '{}' of {}", Username::from_user(&command.user).full(), cancel.id, job.name, job.module);
+                        on_fail!(command.create_response(&ctx.http, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().content(
+                            format!("Tâche `{}` **{}** `{}` annulée", cancel.id, job.module, job.name)).ephemeral(true))).await, "Failed to respond")?;
+                    }
+                }
+            }
+            UtilitiesCommand::Loglevel(LoglevelCommand::List(_)) => {
+                let mut filters = String::new();
+                for (sink, directives) in log_filters() {
+                    filters += format!("**{}** : `{}`\n", sink.name(), directives).as_str();
+                }
+                let embed = CreateEmbed::new().title("Filtres des logs").description(if filters.is_empty() { "Aucun filtre".to_string() } else { filters.truncate_text(4096) });
+                on_fail!(command.create_response(&ctx.http, CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().embed(embed).ephemeral(true))).await, "Failed to respond")?;
+            }
+            UtilitiesCommand::Loglevel(LoglevelCommand::Set(LoglevelSetCommand { sink, filter })) => {
+                self.set_log_filter(&ctx, &command, &sink, Some(filter)).await?;
+            }
+            UtilitiesCommand::Loglevel(LoglevelCommand::Reset(LoglevelResetCommand { sink })) => {
+                self.set_log_filter(&ctx, &command, &sink, None).await?;
             }
-            &_ => {}
+        }
+        Ok(())
+    }
+
+    async fn autocomplete(&self, ctx: Context, name: &str, command: CommandInteraction) -> Result<(), BidibipError> {
+        if name == ConfigCommand::NAME && command.focused_option().is_some_and(|(option, _)| option == "champ") {
+            let fields = match command.string_option("module").and_then(|module| Config::get().read_module_config_value(&module).ok()) {
+                None => { vec![] }
+                Some(config) => { field_paths(&config) }
+            };
+            command.suggest(&ctx.http, fields).await;
+        } else if name == PermissionsCommand::NAME {
+            command.suggest(&ctx.http, self.shared_data.permission_keys().await).await;
+        } else if name == SetModuleEnabledCommand::NAME || name == ConfigCommand::NAME {
+            let mut modules: Vec<String> = self.shared_data.available_modules().await.into_iter().collect();
+            modules.sort();
+            command.suggest(&ctx.http, modules).await;
         }
         Ok(())
     }
 
     fn fetch_commands(&self, config: &PermissionData) -> Vec<CreateCommandDetailed> {
-        vec![CreateCommandDetailed::new("modules")
-                 .description("Informations sur les modules")
-                 .kind(CommandType::ChatInput)
-                 .default_member_permissions(config.at_least_admin()),
-             CreateCommandDetailed::new("update")
-                 .description("Redémarre et mets à jour Bidibip")
-                 .kind(CommandType::ChatInput)
-                 .default_member_permissions(config.at_least_admin()),
-             CreateCommandDetailed::new("set-module-enabled")
-                 .description("Active ou désactive un module")
-                 .kind(CommandType::ChatInput)
-                 .add_option(CreateCommandOption::new(CommandOptionType::String, "module", "nom du module concerné").required(true))
-                 .add_option(CreateCommandOption::new(CommandOptionType::Boolean, "activer", "active ou désactive le module").required(true))
-                 .default_member_permissions(config.at_least_admin())
+        vec![ModulesCommand::create_command().default_member_permissions(config.at_least_admin()),
+             UpdateCommand::create_command().default_member_permissions(config.at_least_admin()),
+             SetModuleEnabledCommand::create_command().default_member_permissions(config.at_least_admin()),
+             ConfigCommand::create_command().default_member_permissions(config.at_least_admin()),
+             PermissionsCommand::create_command().default_member_permissions(config.at_least_admin()),
+             JobsCommand::create_command().default_member_permissions(config.at_least_admin()),
+             LoglevelCommand::create_command().default_member_permissions(config.at_least_admin())
         ]
     }
-}
\ No newline at end of file
+}
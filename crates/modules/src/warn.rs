@@ -13,22 +13,63 @@ use tokio::sync::{Mutex, RwLock};
 use tracing::{error, warn};
 
 use utils::module::{LoadModule, BidibipModule};
+use utils::dispatch::ModuleEvents;
 use utils::global_interface::BidibipSharedData;
 use utils::error::BidibipError;
-use utils::config::Config;
-use utils::{on_fail, assert_some};
-use utils::utilities::{OptionHelper, ResultDebug, TruncateText, Username};
+use utils::config::{Config, PerGuild};
+use utils::{on_fail, assert_some, command_options, command_router};
+use utils::slash_command::{autocomplete, AutocompleteHelper, CommandParser, CommandValue, SlashCommand};
+use utils::utilities::{CommandHelper, ResultDebug, TruncateText, Username};
 use utils::create_command_detailed::CreateCommandDetailed;
+use utils::storage::{Storage, Table};
+use utils::validation::ConfigValidator;
+use utils::interaction_utils::CustomId;
 use utils::global_interface::PermissionData;
+use utils::event_bus::{InternalEvent, SanctionApplied};
+use utils::actions::DiscordActions;
+use utils::metrics::Metrics;
 
 pub struct Warn {
     warn_config: RwLock<WarnConfig>,
-    // Key is modal id, value is (user id, action)
-    pending_warn_actions: Mutex<HashMap<String, (User, ActionType)>>,
+    // Key is (guild id, user id)
+    warns: Table<(GuildId, UserId), WarnedUserList>,
+    // Key is modal id, value is (guild id, user id, action)
+    pending_warn_actions: Mutex<HashMap<u64, (GuildId, User, ActionType)>>,
+    shared_data: Arc<BidibipSharedData>,
+}
+
+/// Source of the sanctions applied by a moderator from Discord, read from the audit log
+const AUDIT_LOG_SOURCE: &str = "discord";
+
+#[derive(Serialize, Deserialize)]
+enum WarnId {
+    // Sanction modal, the value is the key of the pending action
+    Modal(u64),
+    // History button of the moderation message
+    History,
+}
+
+impl CustomId for WarnId {
+    type Module = Warn;
+
+    fn action(&self) -> &'static str {
+        match self {
+            WarnId::Modal(_) => { "sanction" }
+            WarnId::History => { "history" }
+        }
+    }
+
+    fn from_legacy(custom_id: &str) -> Option<Self> {
+        if custom_id == "warn_update_message" {
+            Some(WarnId::History)
+        } else {
+            custom_id.strip_prefix("WarnModalId").and_then(|id| id.parse().ok()).map(WarnId::Modal)
+        }
+    }
 }
 
 #[derive(Clone)]
-enum ActionType {
+pub enum ActionType {
     Warn,
     BanVocal,
     ExcludeDuration(i64),
@@ -39,6 +80,69 @@ enum ActionType {
     Ban,
 }
 
+impl ActionType {
+    /// Actions available from commands : (choice label, command value, action)
+    const CHOICES: [(&'static str, &'static str, ActionType); 7] = [
+        ("warn", "warn", ActionType::Warn),
+        ("ban du vocal", "ban du vocal", ActionType::BanVocal),
+        ("exclusion une heure", "exclusion 1h", ActionType::ExcludeOneHour),
+        ("exclusion un jour", "exclusion 1 jour", ActionType::ExcludeOneDay),
+        ("exclusion une semaine", "exclusion une semaine", ActionType::ExcludeOneWeek),
+        ("kick", "kick", ActionType::Kick),
+        ("ban", "ban", ActionType::Ban),
+    ];
+
+    /// Read the action from a command value (also used as context menu command name)
+    fn from_command_value(value: &str) -> Result<ActionType, Error> {
+        if let Some((_, _, action)) = Self::CHOICES.iter().find(|(_, name, _)| *name == value) {
+            return Ok(action.clone());
+        }
+        if value.starts_with("exclusion du serveur pour (") {
+            let mut split = value.split(['(', ')']);
+            split.next();
+            if let Some(duration) = split.next() {
+                return Ok(ActionType::ExcludeDuration(hhmmss_to_seconds(duration)?));
+            }
+            return Err(Error::msg("Unknown exclusion duration"));
+        }
+        Err(Error::msg(format!("Unhandled sanction command action : {}", value)))
+    }
+}
+
+impl CommandValue for ActionType {
+    const OPTION_TYPE: CommandOptionType = CommandOptionType::String;
+
+    fn from_value(value: &ResolvedValue) -> Option<Self> {
+        if let ResolvedValue::String(value) = value { ActionType::from_command_value(value).ok() } else { None }
+    }
+
+    fn configure(mut option: CreateCommandOption) -> CreateCommandOption {
+        for (label, value, _) in &Self::CHOICES {
+            option = option.add_string_choice(*label, *value);
+        }
+        option
+    }
+}
+
+command_options! {
+    struct SanctionCommand {
+        target: UserId => ("cible", "utilisateur à sanctionner"),
+        action: ActionType => ("action", "sanction à appliquer"),
+        reason: Option<String> => ("raison", "raison pré-remplie de la sanction", autocomplete),
+    }
+}
+
+impl SlashCommand for SanctionCommand {
+    const NAME: &'static str = "sanction";
+    const DESCRIPTION: &'static str = "Sanctionne un utilisateur";
+}
+
+command_router! {
+    enum WarnCommand {
+        Sanction(SanctionCommand),
+    }
+}
+
 fn seconds_to_hhmmss(seconds: i64) -> String {
     let time_seconds = seconds % 60;
     let minutes = (seconds / 60) % 60;
@@ -51,11 +155,11 @@ fn seconds_to_hhmmss(seconds: i64) -> String {
 }
 
 fn hhmmss_to_seconds(hhmmss: &str) -> Result<i64, Error> {
-    let mut split = hhmmss.split(' ');
+    let split = hhmmss.split(' ');
 
     let mut seconds = 0;
 
-    while let Some(value) = split.next() {
+    for value in split {
         if value.ends_with("s") {
             seconds += i64::from_str(&value[0..value.len() - 2])?;
         } else if value.ends_with("mn") {
@@ -69,6 +173,22 @@ fn hhmmss_to_seconds(hhmmss: &str) -> Result<i64, Error> {
     Ok(seconds)
 }
 
+impl ActionType {
+    /// Label of the sanction metrics, without the duration of the exclusions
+    fn metric_label(&self) -> &'static str {
+        match self {
+            ActionType::Warn => { "warn" }
+            ActionType::BanVocal => { "ban_vocal" }
+            ActionType::ExcludeDuration(_) => { "exclude_duration" }
+            ActionType::ExcludeOneHour => { "exclude_one_hour" }
+            ActionType::ExcludeOneDay => { "exclude_one_day" }
+            ActionType::ExcludeOneWeek => { "exclude_one_week" }
+            ActionType::Kick => { "kick" }
+            ActionType::Ban => { "ban" }
+        }
+    }
+}
+
 impl Display for ActionType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let str = match self {
@@ -95,28 +215,56 @@ impl LoadModule<Warn> for Warn {
         "Sanctions & historique des remarques"
     }
 
-    async fn load(_: &Arc<BidibipSharedData>) -> Result<Warn, Error> {
-        let module = Self { warn_config: Default::default(), pending_warn_actions: Default::default() };
-        let warn_config = Config::get().load_module_config::<Warn, WarnConfig>()?;
-        if warn_config.moderation_warn_channel == 0 {
-            return Err(Error::msg("Invalid warn channel id"));
-        }
-        if warn_config.ban_vocal == 0 {
-            return Err(Error::msg("Invalid ban-vocal role id"));
+    fn events() -> ModuleEvents {
+        ModuleEvents::GUILD_AUDIT_LOG_ENTRY_CREATE | ModuleEvents::GUILD_MEMBER_ADDITION | ModuleEvents::INTERACTION_CREATE
+    }
+
+    async fn load(shared_data: &Arc<BidibipSharedData>) -> Result<Warn, Error> {
+        let mut warn_config = Config::get().load_module_config::<Warn, WarnConfig>()?;
+
+        let legacy_warns = Storage::get().table::<Warn, UserId, WarnedUserList>("warns")?;
+        // Move warns from the json config to the storage
+        if !warn_config.warns.is_empty() {
+            legacy_warns.import(warn_config.warns.drain())?;
+            Config::get().save_module_config::<Warn, WarnConfig>(&warn_config)?;
         }
-        *module.warn_config.write().await = warn_config;
-        Ok(module)
+        // Warns stored before multi-guild support belong to the main guild
+        let warns = Storage::get().table::<Warn, _, _>("guild_warns")?;
+        let server_id = Config::get().server_id;
+        warns.import(legacy_warns.entries()?.into_iter().map(|(user, list)| ((server_id, user), list)))?;
+        Ok(Self { warn_config: RwLock::new(warn_config), warns, pending_warn_actions: Default::default(), shared_data: shared_data.clone() })
     }
 }
 
-#[derive(Serialize, Deserialize, Default)]
-pub struct WarnConfig {
+/// Settings of the sanctions specific to each guild
+#[derive(Serialize, Deserialize, Default, Clone)]
+struct WarnGuildConfig {
     public_warn_channel: ChannelId,
     moderation_warn_channel: ChannelId,
     #[serde(rename = "ban-vocal")]
     ban_vocal: RoleId,
-    // Key is user id
-    pub warns: HashMap<UserId, WarnedUserList>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct WarnConfig {
+    #[serde(flatten)]
+    guild: PerGuild<WarnGuildConfig>,
+    // Runtime data is persisted in the storage, json values are only read to be imported
+    #[serde(default, skip_serializing)]
+    warns: HashMap<UserId, WarnedUserList>,
+    // Reasons suggested in the sanction command
+    #[serde(default)]
+    reason_templates: Vec<String>,
+}
+
+impl WarnConfig {
+    fn validate(&self, validator: &mut ConfigValidator) {
+        self.guild.validate(validator, |config, validator| {
+            validator.channel("public_warn_channel", config.public_warn_channel);
+            validator.channel("moderation_warn_channel", config.moderation_warn_channel);
+            validator.role("ban-vocal", config.ban_vocal);
+        });
+    }
 }
 
 #[derive(Serialize, Deserialize, Default)]
@@ -147,94 +295,27 @@ pub struct UserWarn {
 #[serenity::async_trait]
 impl BidibipModule for Warn {
     async fn execute_command(&self, ctx: Context, name: &str, command: CommandInteraction) -> Result<(), BidibipError> {
-        let (action, target) =
-            if name == "sanction" {
-                match command.data.options().find("action") {
-                    None => {
-                        error!("Missing action value");
-                        return Err(BidibipError::from(Error::msg("Missing action value")));
-                    }
-                    Some(action) => {
-                        if let ResolvedValue::String(action) = action {
-                            match command.data.options().find("cible") {
-                                None => {
-                                    error!("Missing target value");
-                                    return Ok(());
-                                }
-                                Some(target) => {
-                                    if let ResolvedValue::User(user, _) = target {
-                                        let action = match action {
-                                            "warn" => ActionType::Warn,
-                                            "banvoc" => ActionType::BanVocal,
-                                            "exclusion 1h" => ActionType::ExcludeOneHour,
-                                            "exclusion 1 jour" => ActionType::ExcludeOneDay,
-                                            "exclusion une semaine" => ActionType::ExcludeOneWeek,
-                                            "kick" => ActionType::Kick,
-                                            "ban" => ActionType::Ban,
-                                            value => {
-                                                if value.starts_with("exclusion du serveur pour (") {
-                                                    let mut split = value.split(['(', ')']);
-                                                    split.next();
-                                                    if let Some(duration) = split.next() {
-                                                        ActionType::ExcludeDuration(hhmmss_to_seconds(duration)?)
-                                                    } else {
-                                                        error!("Unknown exclusion duration");
-                                                        return Err(BidibipError::from(Error::msg("Unknown exclusion duration")));
-                                                    }
-                                                } else {
-                                                    error!("Unhandled sanction command action");
-                                                    return Err(BidibipError::from(Error::msg("Unhandled sanction command action")));
-                                                }
-                                            }
-                                        };
-                                        (action, user.id)
-                                    } else {
-                                        error!("Invalid user");
-                                        return Err(BidibipError::from(Error::msg("Invalid user")));
-                                    }
-                                }
-                            }
-                        } else {
-                            error!("Wrong action value");
-                            return Err(BidibipError::from(Error::msg("Wrong action value")));
-                        }
-                    }
-                }
+        let (action, target, reason) =
+            if let Some(WarnCommand::Sanction(SanctionCommand { target, action, reason })) = command.parse_command::<WarnCommand>(&ctx.http).await {
+                (action, target, reason)
+            } else if name == SanctionCommand::NAME {
+                // Invalid command, the user has already been notified
+                return Ok(());
             } else if let Some(target) = command.data.target_id {
-                let action = match name {
-                    "warn" => ActionType::Warn,
-                    "ban du vocal" => ActionType::BanVocal,
-                    "exclusion 1h" => ActionType::ExcludeOneHour,
-                    "exclusion 1 jour" => ActionType::ExcludeOneDay,
-                    "exclusion une semaine" => ActionType::ExcludeOneWeek,
-                    "kick" => ActionType::Kick,
-                    "ban" => ActionType::Ban,
-                    value => {
-                        if value.starts_with("exclusion du serveur pour (") {
-                            let mut split = value.split(['(', ')']);
-                            split.next();
-                            if let Some(duration) = split.next() {
-                                ActionType::ExcludeDuration(hhmmss_to_seconds(duration)?)
-                            } else {
-                                error!("Unknown exclusion duration");
-                                return Err(BidibipError::from(Error::msg("Unknown exclusion duration")));
-                            }
-                        } else {
-                            error!("Unhandled sanction command action");
-                            return Err(BidibipError::from(Error::msg("Unhandled sanction command action")));
-                        }
-                    }
-                };
-                (action, target.to_user_id())
+                (on_fail!(ActionType::from_command_value(name), "Invalid context menu action")?, target.to_user_id(), None)
             } else {
                 error!("Invalid target");
                 return Err(BidibipError::from(Error::msg("Invalid target")));
             };
 
-
+        let guild = assert_some!(command.guild_id, "Warn command used outside of a guild")?;
+        if let Err(err) = self.guild_config(guild).await {
+            command.respond_user_error(&ctx.http, err).await;
+            return Ok(());
+        }
         match target.to_user(&ctx.http).await {
             Ok(user) => {
-                self.open_warn_modal(ctx, user, action, command).await;
+                self.open_warn_modal(ctx, guild, user, action, reason, command).await;
             }
             Err(err) => {
                 error!("Failed to fetch user data : {err}");
@@ -244,6 +325,27 @@ impl BidibipModule for Warn {
         Ok(())
     }
 
+    async fn validate_config(&self, validator: &mut ConfigValidator) {
+        self.warn_config.read().await.validate(validator);
+    }
+
+    async fn reload_config(&self, validator: &mut ConfigValidator) -> Result<bool, Error> {
+        let config = Config::get().read_module_config::<Warn, WarnConfig>()?;
+        config.validate(validator);
+        if validator.is_valid() {
+            *self.warn_config.write().await = config;
+        }
+        Ok(true)
+    }
+
+    async fn autocomplete(&self, ctx: Context, name: &str, command: CommandInteraction) -> Result<(), BidibipError> {
+        if name == SanctionCommand::NAME {
+            let templates = self.warn_config.read().await.reason_templates.clone();
+            command.suggest(&ctx.http, templates).await;
+        }
+        Ok(())
+    }
+
     fn fetch_commands(&self, config: &PermissionData) -> Vec<CreateCommandDetailed> {
         vec![
             CreateCommandDetailed::new("warn").kind(CommandType::User).default_member_permissions(config.at_least_admin()),
@@ -251,24 +353,11 @@ impl BidibipModule for Warn {
             CreateCommandDetailed::new("kick").kind(CommandType::User).default_member_permissions(config.at_least_admin()),
             CreateCommandDetailed::new("exclusion 1h").kind(CommandType::User).default_member_permissions(config.at_least_helper()),
             CreateCommandDetailed::new("ban").kind(CommandType::User).default_member_permissions(config.at_least_admin()),
-            CreateCommandDetailed::new("sanction")
-                .default_member_permissions(config.at_least_admin())
-                .description("Sanctionne un utilisateur")
-                .add_option(CreateCommandOption::new(CommandOptionType::User, "cible", "utilisateur à sanctionner").required(true))
-                .add_option(CreateCommandOption::new(CommandOptionType::String, "action", "sanction à appliquer")
-                    .required(true)
-                    .add_string_choice("warn", "warn")
-                    .add_string_choice("ban du vocal", "ban du vocal")
-                    .add_string_choice("exclusion une heure", "exclusion 1h")
-                    .add_string_choice("exclusion un jour", "exclusion 1 jour")
-                    .add_string_choice("exclusion une semaine", "exclusion une semaine")
-                    .add_string_choice("kick", "kick")
-                    .add_string_choice("ban", "ban")
-                ),
+            SanctionCommand::create_command().default_member_permissions(config.at_least_admin()),
         ]
     }
 
-    async fn guild_audit_log_entry_create(&self, ctx: Context, entry: AuditLogEntry, _: GuildId) -> Result<(), BidibipError> {
+    async fn guild_audit_log_entry_create(&self, ctx: Context, entry: AuditLogEntry, guild: GuildId) -> Result<(), BidibipError> {
         if let Action::Member(member_action) = entry.action {
             if entry.user_id.get() != Config::get().application_id.get() {
                 if let Some(target) = entry.target_id {
@@ -287,10 +376,10 @@ impl BidibipModule for Warn {
                                 action: ActionType::Kick.to_string(),
                                 full_message_link: "".to_string(),
                             };
-                            self.handle_warn_action(&ctx.http, warn_data, false, ActionType::Kick).await?;
+                            self.handle_warn_action(&ctx, guild, warn_data, false, ActionType::Kick, AUDIT_LOG_SOURCE).await?;
                         }
                         MemberAction::Update => {
-                            let member = on_fail!(GuildId::from(Config::get().server_id).member(&ctx.http, to.id).await, "Failed to get member data")?;
+                            let member = on_fail!(guild.member(&ctx.http, to.id).await, "Failed to get member data")?;
 
                             if let Some(end) = member.communication_disabled_until {
                                 let duration = (end.timestamp_millis() - Timestamp::now().timestamp_millis()) / 1000;
@@ -304,7 +393,7 @@ impl BidibipModule for Warn {
                                     action: ActionType::ExcludeDuration(duration).to_string(),
                                     full_message_link: "".to_string(),
                                 };
-                                self.handle_warn_action(&ctx.http, warn_data, false, ActionType::ExcludeDuration(duration)).await?;
+                                self.handle_warn_action(&ctx, guild, warn_data, false, ActionType::ExcludeDuration(duration), AUDIT_LOG_SOURCE).await?;
                             }
                         }
                         MemberAction::BanAdd => {
@@ -318,7 +407,7 @@ impl BidibipModule for Warn {
                                 action: ActionType::Ban.to_string(),
                                 full_message_link: "".to_string(),
                             };
-                            self.handle_warn_action(&ctx.http, warn_data, false, ActionType::Ban).await?;
+                            self.handle_warn_action(&ctx, guild, warn_data, false, ActionType::Ban, AUDIT_LOG_SOURCE).await?;
                         }
                         _ => {}
                     }
@@ -330,9 +419,8 @@ impl BidibipModule for Warn {
 
     /// Detect when a warned user join the server and tell the moderation to stay vigilant
     async fn guild_member_addition(&self, ctx: Context, new_member: Member) -> Result<(), BidibipError> {
-        let warns = self.warn_config.read().await;
-
-        if let Some(data) = warns.warns.get(&new_member.user.id) {
+        let guild = new_member.guild_id;
+        if let Some(data) = self.warns.get(&(guild, new_member.user.id))? {
             if !data.warns.is_empty() {
                 let mut last = String::new();
                 let mut last_date = 0;
@@ -343,7 +431,7 @@ impl BidibipModule for Warn {
                     }
                 }
 
-                if let Err(err) = Config::get().channels.staff_channel.send_message(&ctx.http, CreateMessage::new().content(format!("{} vient de rejoindre le serveur avec {} warn(s) à son actif ! {}", Username::from_user(&new_member.user).full(), data.warns.len(), last))).await {
+                if let Err(err) = Config::get().guild_channels(guild).staff_channel.send_message(&ctx.http, CreateMessage::new().content(format!("{} vient de rejoindre le serveur avec {} warn(s) à son actif ! {}", Username::from_user(&new_member.user).full(), data.warns.len(), last))).await {
                     error!("Failed to send message : {}", err)
                 }
             }
@@ -354,7 +442,12 @@ impl BidibipModule for Warn {
     async fn interaction_create(&self, ctx: Context, interaction: Interaction) -> Result<(), BidibipError> {
         // When user sent a modal response
         if let Interaction::Modal(modal) = interaction {
-            if let Some((target, action)) = self.pending_warn_actions.lock().await.get(&modal.data.custom_id) {
+            let modal_id = match WarnId::decode(&modal.data.custom_id) {
+                Some(WarnId::Modal(modal_id)) => { modal_id }
+                _ => { return Ok(()) }
+            };
+            let pending_action = self.pending_warn_actions.lock().await.get(&modal_id).cloned();
+            if let Some((guild, target, action)) = pending_action {
                 on_fail!(modal.defer(&ctx.http).await, "Failed to close modal interaction")?;
 
                 let mut reason = String::new();
@@ -391,7 +484,7 @@ impl BidibipModule for Warn {
                 let warn_data = UserWarn {
                     date: Utc::now().timestamp() as u64,
                     from: Username::from_user(&modal.user),
-                    to: Username::from_user(target),
+                    to: Username::from_user(&target),
                     link: url,
                     reason,
                     details,
@@ -399,17 +492,18 @@ impl BidibipModule for Warn {
                     full_message_link: "".to_string(),
                 };
 
-                self.handle_warn_action(&ctx.http, warn_data, true, action.clone()).await?;
+                self.handle_warn_action(&ctx, guild, warn_data, true, action, Warn::name()).await?;
 
-                self.pending_warn_actions.lock().await.remove(&modal.data.custom_id);
+                self.pending_warn_actions.lock().await.remove(&modal_id);
             }
         }
         // When the user clicked on the "history" button
         else if let Interaction::Component(component) = interaction {
-            if component.data.custom_id == "warn_update_message" {
-                let config = self.warn_config.write().await;
-
-                for (_, user) in &config.warns {
+            if let Some(WarnId::History) = WarnId::decode(&component.data.custom_id) {
+                for ((guild, _), user) in self.warns.entries()? {
+                    if Some(guild) != component.guild_id {
+                        continue;
+                    }
                     for warn in &user.warns {
                         if warn.full_message_link == component.message.link() {
                             let mut embed = CreateEmbed::new().title(format!("{} warns", user.warns.len()));
@@ -438,30 +532,33 @@ impl Warn {
     /// Open the warn modal to the person who wants to warn a person
     /// user : warned user
     /// action : warn, kick, ban...
-    async fn open_warn_modal(&self, ctx: Context, user: User, action: ActionType, command: CommandInteraction) {
+    async fn open_warn_modal(&self, ctx: Context, guild: GuildId, user: User, action: ActionType, reason: Option<String>, command: CommandInteraction) {
         println!("@TODO : Pre lock pending_warn_actions");
         let mut pending_warn_actions = self.pending_warn_actions.lock().await;
         println!("@TODO : Post lock pending_warn_actions");
 
         // Generate action id
         let mut id = 0;
-        loop {
-            let key = format!("WarnModalId{}", id);
-            if pending_warn_actions.contains_key(&key) {
-                id += 1;
-                continue;
-            }
-            pending_warn_actions.insert(key, (user.clone(), action.clone()));
-            break;
+        while pending_warn_actions.contains_key(&id) {
+            id += 1;
+        }
+        let modal_id = match WarnId::Modal(id).encode() {
+            Ok(modal_id) => { modal_id }
+            Err(err) => { return error!("Failed to create warn modal id : {}", err) }
         };
+        pending_warn_actions.insert(id, (guild, user.clone(), action.clone()));
+
+        let mut reason_input = CreateInputText::new(InputTextStyle::Short, "Raison", "reason")
+            .required(true)
+            .placeholder("Ce message sera transmis à la personne concernée");
+        if let Some(reason) = reason {
+            reason_input = reason_input.value(reason);
+        }
 
         // Send modal widget
         command.create_response(&ctx.http, CreateInteractionResponse::Modal(
-            CreateModal::new(format!("WarnModalId{}", id), format!("{} de {}", action, user.name).truncate_text(45)).components(vec![
-                CreateActionRow::InputText(
-                    CreateInputText::new(InputTextStyle::Short, "Raison", "reason")
-                        .required(true)
-                        .placeholder("Ce message sera transmis à la personne concernée")),
+            CreateModal::new(modal_id, format!("{} de {}", action, user.name).truncate_text(45)).components(vec![
+                CreateActionRow::InputText(reason_input),
                 CreateActionRow::InputText(
                     CreateInputText::new(InputTextStyle::Paragraph, "Autres informations", "other")
                         .required(false)
@@ -473,14 +570,22 @@ impl Warn {
             ]))).await.on_fail("Failed to create interaction modal");
     }
 
-    async fn send_moderation_warn_message(&self, http: &Http, warn_data: &UserWarn) -> Result<Message, Error> {
-        let mut warn_config = self.warn_config.write().await;
+    /// Settings of the given guild. Sanctions are refused on the guilds without their own section.
+    async fn guild_config(&self, guild: GuildId) -> Result<WarnGuildConfig, Error> {
+        match self.warn_config.read().await.guild.get(guild) {
+            None => { Err(Error::msg(format!("Le module {} n'est pas configuré sur le serveur {}", Warn::name(), guild))) }
+            Some(config) => { Ok(config.clone()) }
+        }
+    }
+
+    async fn send_moderation_warn_message(&self, http: &Http, guild: GuildId, warn_data: &UserWarn) -> Result<Message, Error> {
+        let warn_config = self.guild_config(guild).await?;
 
         let mut embed = CreateEmbed::new()
             .title(warn_data.action.clone())
             .description(warn_data.reason.clone());
         {
-            let warn_list = warn_config.warns.entry(warn_data.to.id()).or_default().warns.len();
+            let warn_list = self.warns.get(&(guild, warn_data.to.id()))?.map(|list| list.warns.len()).unwrap_or_default();
             if let Some(details) = &warn_data.details {
                 embed = embed.field("Details", details.truncate_text(1024), false);
             }
@@ -494,68 +599,66 @@ impl Warn {
 
         Ok(warn_config.moderation_warn_channel
             .send_message(http, CreateMessage::new()
-                .content(format!("Sanction de {} par {} {}", warn_data.to.full(), warn_data.from.full(), Config::get().roles.administrator.mention()))
+                .content(format!("Sanction de {} par {} {}", warn_data.to.full(), warn_data.from.full(), Config::get().guild_roles(guild).administrator.mention()))
                 .embed(embed)
                 .components(vec![
                     CreateActionRow::Buttons(vec![
-                        CreateButton::new("warn_update_message")
+                        CreateButton::new(WarnId::History.encode()?)
                             .label("Historique")
                             .style(ButtonStyle::Secondary)
                     ])
                 ])).await?)
     }
 
-    async fn store_new_warn(&self, warn_data: UserWarn) {
-        let mut warn_config = self.warn_config.write().await;
-        let warn_list = &mut warn_config.warns.entry(warn_data.to.id()).or_default().warns;
-        warn_list.push(warn_data.clone());
-        // Update database
-        Config::get().save_module_config::<Self, WarnConfig>(&*warn_config).unwrap();
+    async fn store_new_warn(&self, guild: GuildId, warn_data: UserWarn) {
+        self.warns.update(&(guild, warn_data.to.id()), |list| {
+            list.get_or_insert_with(Default::default).warns.push(warn_data.clone());
+        }).on_fail("Failed to store warn");
     }
 
-    async fn send_warn_public_message(&self, http: &Http, warn_data: &UserWarn, action: &ActionType) -> Result<(), BidibipError> {
-        let warn_config = self.warn_config.read().await;
+    async fn send_warn_public_message(&self, http: &Http, guild: GuildId, warn_data: &UserWarn, action: &ActionType) -> Result<(), BidibipError> {
+        let warn_config = self.guild_config(guild).await?;
         match action {
             ActionType::Ban => {
-                warn_config.public_warn_channel.send_message(http, CreateMessage::new().embed(CreateEmbed::new().title(format!("{} a été banni par {}", warn_data.to.safe_full(), warn_data.from.safe_full())).description(&warn_data.reason.truncate_text(4000)))).await?;
+                warn_config.public_warn_channel.send_message(http, CreateMessage::new().embed(CreateEmbed::new().title(format!("{} a été banni par {}", warn_data.to.safe_full(), warn_data.from.safe_full())).description(warn_data.reason.truncate_text(4000)))).await?;
             }
             ActionType::Kick => {
-                warn_config.public_warn_channel.send_message(http, CreateMessage::new().embed(CreateEmbed::new().title(format!("{} a été kick par {}", warn_data.to.safe_full(), warn_data.from.safe_full())).description(&warn_data.reason.truncate_text(4000)))).await?;
+                warn_config.public_warn_channel.send_message(http, CreateMessage::new().embed(CreateEmbed::new().title(format!("{} a été kick par {}", warn_data.to.safe_full(), warn_data.from.safe_full())).description(warn_data.reason.truncate_text(4000)))).await?;
             }
             ActionType::Warn => {}
             ActionType::BanVocal => {
-                warn_config.public_warn_channel.send_message(http, CreateMessage::new().embed(CreateEmbed::new().title(format!("{} a été exclu du vocal par {}", warn_data.to.safe_full(), warn_data.from.safe_full())).description(&warn_data.reason.truncate_text(4000)))).await?;
+                warn_config.public_warn_channel.send_message(http, CreateMessage::new().embed(CreateEmbed::new().title(format!("{} a été exclu du vocal par {}", warn_data.to.safe_full(), warn_data.from.safe_full())).description(warn_data.reason.truncate_text(4000)))).await?;
             }
             ActionType::ExcludeDuration(time_s) => {
                 warn_config.public_warn_channel.send_message(http, CreateMessage::new().embed(CreateEmbed::new()
                     .title(format!("{} a été exclu par {}", warn_data.to.safe_full(), warn_data.from.safe_full()))
-                    .description(&warn_data.reason.truncate_text(4000))
+                    .description(warn_data.reason.truncate_text(4000))
                     .field("durée", seconds_to_hhmmss(*time_s), true))).await?;
             }
             ActionType::ExcludeOneHour => {
                 warn_config.public_warn_channel.send_message(http, CreateMessage::new().embed(CreateEmbed::new()
                     .title(format!("{} a été exclu par {}", warn_data.to.safe_full(), warn_data.from.safe_full()))
-                    .description(&warn_data.reason.truncate_text(4000))
+                    .description(warn_data.reason.truncate_text(4000))
                     .field("durée", "une heure", true))).await?;
             }
             ActionType::ExcludeOneDay => {
                 warn_config.public_warn_channel.send_message(http, CreateMessage::new().embed(CreateEmbed::new()
                     .title(format!("{} a été exclu par {}", warn_data.to.safe_full(), warn_data.from.safe_full()))
-                    .description(&warn_data.reason.truncate_text(4000))
+                    .description(warn_data.reason.truncate_text(4000))
                     .field("durée", "une journée", true))).await?;
             }
             ActionType::ExcludeOneWeek => {
                 warn_config.public_warn_channel.send_message(http, CreateMessage::new().embed(CreateEmbed::new()
                     .title(format!("{} a été exclu par {}", warn_data.to.safe_full(), warn_data.from.safe_full()))
-                    .description(&warn_data.reason.truncate_text(4000))
+                    .description(warn_data.reason.truncate_text(4000))
                     .field("durée", "une semaine", true))).await?;
             }
         }
         Ok(())
     }
 
-    async fn send_warn_private_message(&self, http: &Http, warn_data: &UserWarn, action: &ActionType) -> Result<(), BidibipError> {
-        let server_name = match Config::get().server_id.to_partial_guild(http).await {
+    async fn send_warn_private_message(&self, http: &Http, guild: GuildId, warn_data: &UserWarn, action: &ActionType) -> Result<(), BidibipError> {
+        let server_name = match guild.to_partial_guild(http).await {
             Ok(guild) => { guild.name }
             Err(err) => {
                 error!("Failed to get server data : {}", err);
@@ -563,7 +666,7 @@ impl Warn {
             }
         };
 
-        match GuildId::from(Config::get().server_id).member(http, warn_data.to.id()).await {
+        match guild.member(http, warn_data.to.id()).await {
             Ok(member) => {
                 match action {
                     ActionType::Ban => {
@@ -600,50 +703,94 @@ impl Warn {
     }
 
     /// Actually kick or ban the person
-    async fn apply_warn(&self, http: &Http, warn_data: &UserWarn, action: &ActionType) -> Result<(), BidibipError> {
-        let mut member = GuildId::from(Config::get().server_id).member(http, warn_data.to.id()).await?;
+    async fn apply_warn(&self, http: &Http, guild: GuildId, warn_data: &UserWarn, action: &ActionType) -> Result<(), BidibipError> {
+        let member = guild.member(http, warn_data.to.id()).await?;
+        let actions = DiscordActions::of::<Warn>(http);
         match action {
             ActionType::Ban => {
-                member.ban_with_reason(http, 0, warn_data.reason.as_str()).await.on_fail("Failed to ban member");
+                actions.ban(guild, &member.user, 0, warn_data.reason.as_str()).await.on_fail("Failed to ban member");
             }
             ActionType::Kick => {
-                if let Err(error) = member.disconnect_from_voice(http).await {
+                if let Err(error) = actions.disconnect_from_voice(guild, &member.user).await {
                     warn!("Failed to disconnect user from voice : {}", error);
                 }
-                member.kick_with_reason(http, warn_data.reason.as_str()).await.on_fail("Failed to kick member");
+                actions.kick(guild, &member.user, warn_data.reason.as_str()).await.on_fail("Failed to kick member");
             }
             ActionType::BanVocal => {
-                member.add_role(http, self.warn_config.read().await.ban_vocal).await?
+                actions.add_role(guild, &member.user, self.guild_config(guild).await?.ban_vocal).await?
             }
             ActionType::ExcludeDuration(time_s) => {
-                member.disable_communication_until_datetime(http, Timestamp::from(Timestamp::now().add(TimeDelta::seconds(*time_s)))).await?
+                actions.timeout(guild, &member.user, Timestamp::from(Timestamp::now().add(TimeDelta::seconds(*time_s)))).await?
             }
             ActionType::ExcludeOneHour => {
-                member.disable_communication_until_datetime(http, Timestamp::from(Timestamp::now().add(TimeDelta::hours(1)))).await?
+                actions.timeout(guild, &member.user, Timestamp::from(Timestamp::now().add(TimeDelta::hours(1)))).await?
             }
             ActionType::ExcludeOneDay => {
-                member.disable_communication_until_datetime(http, Timestamp::from(Timestamp::now().add(TimeDelta::days(1)))).await?
+                actions.timeout(guild, &member.user, Timestamp::from(Timestamp::now().add(TimeDelta::days(1)))).await?
             }
             ActionType::ExcludeOneWeek => {
-                member.disable_communication_until_datetime(http, Timestamp::from(Timestamp::now().add(TimeDelta::weeks(1)))).await?
+                actions.timeout(guild, &member.user, Timestamp::from(Timestamp::now().add(TimeDelta::weeks(1)))).await?
             }
             ActionType::Warn => {}
         }
         Ok(())
     }
 
+    /// Store a sanction applied by another module and tell the moderation, without applying it again.
+    /// The details are added after the name of the source module.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn record_sanction<Source: LoadModule<Source> + BidibipModule>(&self, ctx: &Context, guild: GuildId, moderator: &User, user: &User, action: ActionType, reason: &str, details: Option<String>) -> Result<(), BidibipError> {
+        let source = Source::name();
+        let details = match details {
+            None => { format!("Sanction appliquée par le module {}", source) }
+            Some(details) => { format!("Sanction appliquée par le module {}\n{}", source, details) }
+        };
+        let warn_data = UserWarn {
+            date: Utc::now().timestamp() as u64,
+            from: Username::from_user(moderator),
+            to: Username::from_user(user),
+            link: None,
+            reason: reason.to_string(),
+            details: Some(details),
+            action: action.to_string(),
+            full_message_link: "".to_string(),
+        };
+        self.handle_warn_action(ctx, guild, warn_data, false, action, source).await
+    }
+
     /// Apply warn sanction (store / send messages / kick-ban if required)
-    async fn handle_warn_action(&self, http: &Http, warn_data: UserWarn, affect_user: bool, action: ActionType) -> Result<(), BidibipError> {
+    async fn handle_warn_action(&self, ctx: &Context, guild: GuildId, warn_data: UserWarn, affect_user: bool, action: ActionType, source: &str) -> Result<(), BidibipError> {
+        let http = &ctx.http;
+        let warn_config = on_fail!(self.guild_config(guild).await, "Sanction on a guild without warn config")?;
+
+        // Sanctions of a module in dry run are not applied : only the moderation is told, the member and the history are left untouched
+        if source != AUDIT_LOG_SOURCE && Config::get().dry_run.is_dry_run(source) {
+            if affect_user {
+                on_fail!(self.apply_warn(http, guild, &warn_data, &action).await, "Failed apply warn sentence")?;
+            }
+            on_fail!(warn_config.moderation_warn_channel.say(http, format!("[dry run] Sanction `{}` de {} par {} non appliquée : {}", warn_data.action, warn_data.to.full(), warn_data.from.full(), warn_data.reason.truncate_text(1000))).await, "Failed to send dry run warn notice")?;
+            return Ok(());
+        }
+
         // Send requests
-        let mod_message = self.send_moderation_warn_message(http, &warn_data);
-        let pub_message = self.send_warn_public_message(http, &warn_data, &action);
-        let priv_message = self.send_warn_private_message(http, &warn_data, &action);
-        let apply_warn = if affect_user { Some(self.apply_warn(http, &warn_data, &action)) } else { None };
+        let mod_message = self.send_moderation_warn_message(http, guild, &warn_data);
+        let pub_message = self.send_warn_public_message(http, guild, &warn_data, &action);
+        let priv_message = self.send_warn_private_message(http, guild, &warn_data, &action);
+        let apply_warn = if affect_user { Some(self.apply_warn(http, guild, &warn_data, &action)) } else { None };
 
         let mod_message = on_fail!(mod_message.await, "Failed to send warn moderation message")?;
         let mut data = warn_data.clone();
         data.full_message_link = mod_message.link();
-        self.store_new_warn(data).await;
+        self.store_new_warn(guild, data).await;
+        Metrics::get().sanctions.with_label_values(&[action.metric_label()]).inc();
+        self.shared_data.publish(ctx, InternalEvent::SanctionApplied(SanctionApplied {
+            guild,
+            user: warn_data.to.id(),
+            moderator: warn_data.from.id(),
+            sanction: warn_data.action.clone(),
+            reason: warn_data.reason.clone(),
+            source: source.to_string(),
+        })).await;
 
         on_fail!(pub_message.await, "Failed to send warn public message")?;
         on_fail!(priv_message.await, "Failed to send warn private message")?;
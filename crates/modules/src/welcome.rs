@@ -3,15 +3,18 @@ use anyhow::Error;
 use rand::prelude::IndexedRandom;
 use serde::{Deserialize, Serialize};
 use serenity::all::{ChannelId, Context, CreateMessage, GuildId, Member, Mentionable, User};
+use tokio::sync::RwLock;
 use utils::module::{LoadModule, BidibipModule};
+use utils::dispatch::ModuleEvents;
 use utils::global_interface::BidibipSharedData;
 use utils::error::BidibipError;
 use utils::config::Config;
+use utils::validation::ConfigValidator;
 use utils::on_fail;
 use utils::utilities::TruncateText;
 
 pub struct Welcome {
-    welcome_config: WelcomeConfig,
+    welcome_config: RwLock<WelcomeConfig>,
 }
 
 #[derive(Serialize, Deserialize, Default)]
@@ -33,32 +36,59 @@ impl LoadModule<Welcome> for Welcome {
         "messages de bienvenue et de départ"
     }
 
+    fn events() -> ModuleEvents {
+        ModuleEvents::GUILD_MEMBER_ADDITION | ModuleEvents::GUILD_MEMBER_REMOVAL
+    }
+
     async fn load(_: &Arc<BidibipSharedData>) -> Result<Welcome, Error> {
         let welcome_config = Config::get().load_module_config::<Welcome, WelcomeConfig>()?;
-        Ok(Welcome { welcome_config })
+        Ok(Welcome { welcome_config: RwLock::new(welcome_config) })
+    }
+}
+
+impl WelcomeConfig {
+    fn validate(&self, validator: &mut ConfigValidator) {
+        validator.channel("join_channel", self.join_channel);
+        validator.channel("leave_channel", self.leave_channel);
+        validator.channel("reglement_channel", self.reglement_channel);
     }
 }
 
 #[serenity::async_trait]
 impl BidibipModule for Welcome {
+    async fn validate_config(&self, validator: &mut ConfigValidator) {
+        self.welcome_config.read().await.validate(validator);
+    }
+
+    async fn reload_config(&self, validator: &mut ConfigValidator) -> Result<bool, Error> {
+        let config = Config::get().read_module_config::<Welcome, WelcomeConfig>()?;
+        config.validate(validator);
+        if validator.is_valid() {
+            *self.welcome_config.write().await = config;
+        }
+        Ok(true)
+    }
+
     async fn guild_member_addition(&self, ctx: Context, new_member: Member) -> Result<(), BidibipError> {
-        let mut sentence = match self.welcome_config.welcome_messages.choose(&mut rand::rng()) {
+        let config = self.welcome_config.read().await;
+        let mut sentence = match config.welcome_messages.choose(&mut rand::rng()) {
             None => { String::from("Bienvenue parmi nous {} :wave: !") }
             Some(sentence) => { sentence.clone() }
         };
         sentence += "\n> N'oublies pas de lire le {reglement} pour accéder au serveur.";
-        let sentence = sentence.replace("{user}", new_member.user.mention().to_string().as_str()).replace("{reglement}", self.welcome_config.reglement_channel.mention().to_string().as_str());
-        on_fail!(self.welcome_config.join_channel.send_message(&ctx.http, CreateMessage::new().content(sentence.truncate_text(2000))).await, "Failed to send welcome message")?;
+        let sentence = sentence.replace("{user}", new_member.user.mention().to_string().as_str()).replace("{reglement}", config.reglement_channel.mention().to_string().as_str());
+        on_fail!(config.join_channel.send_message(&ctx.http, CreateMessage::new().content(sentence.truncate_text(2000))).await, "Failed to send welcome message")?;
         Ok(())
     }
 
     async fn guild_member_removal(&self, ctx: Context, _: GuildId, user: User, _: Option<Member>) -> Result<(), BidibipError> {
-        let sentence = match self.welcome_config.leave_messages.choose(&mut rand::rng()) {
+        let config = self.welcome_config.read().await;
+        let sentence = match config.leave_messages.choose(&mut rand::rng()) {
             None => { String::from("{} nous a quitté !") }
             Some(sentence) => { sentence.clone() }
         };
         let sentence = sentence.replace("{user}", user.mention().to_string().as_str());
-        on_fail!(self.welcome_config.leave_channel.send_message(&ctx.http, CreateMessage::new().content(sentence.truncate_text(2000))).await, "Failed to send leave message")?;
+        on_fail!(config.leave_channel.send_message(&ctx.http, CreateMessage::new().content(sentence.truncate_text(2000))).await, "Failed to send leave message")?;
         Ok(())
     }
 }
\ No newline at end of file
@@ -0,0 +1,183 @@
+use serde_json::{json, Value};
+use serenity::all::{ChannelId, Interaction, RoleId};
+use modules::advertising::Advertising;
+use test_support::fixtures::{ChannelBuilder, CommandInteractionBuilder, ComponentInteractionBuilder, MemberBuilder, MessageBuilder, UserBuilder};
+use test_support::{write_module_config, TestBot, GUILD};
+use utils::module::BidibipModule;
+
+const AD_FORUM: ChannelId = ChannelId::new(5000);
+const IN_PROGRESS_CHANNEL: ChannelId = ChannelId::new(5001);
+const REVIEWER_ROLE: RoleId = RoleId::new(5002);
+
+const PREVIEW: &str = "# Voici ton annonce telle qu'elle sera présentée.";
+
+async fn start() -> TestBot {
+    write_module_config::<Advertising>(json!({
+        "tags": {
+            "freelance": 1, "volunteer": 2, "paid": 3, "unpaid": 4, "internship": 5, "fixed_term": 6, "open_ended": 7,
+            "work_study": 8, "worker": 9, "recruiter": 10, "remote": 11, "on_site": 12, "on_site_flex": 13,
+        },
+        "ad_forum": AD_FORUM,
+        "reviewer_roles": [REVIEWER_ROLE],
+        "in_progress_ad_channel": IN_PROGRESS_CHANNEL,
+        "max_ad_per_user": 2,
+    }));
+    TestBot::start().await.expect("Failed to start test bot")
+}
+
+/// Edition thread of an ad. Every message posted by the bot in the thread gets the same id.
+struct EditionThread {
+    thread: ChannelBuilder,
+    bot_message: MessageBuilder,
+}
+
+impl EditionThread {
+    fn mock(bot: &TestBot, author: &UserBuilder) -> Self {
+        let thread = ChannelBuilder::thread(IN_PROGRESS_CHANNEL, &UserBuilder::bidibip(), "Annonce");
+        let bot_message = MessageBuilder::new(thread.channel_id(), &UserBuilder::bidibip(), "Question");
+        let discord = &bot.discord;
+        discord.respond("POST", &format!("/channels/{}/threads", IN_PROGRESS_CHANNEL), thread.json());
+        discord.respond("GET", &format!("/channels/{}", thread.channel_id()), thread.json());
+        discord.respond("POST", &format!("/channels/{}/messages", thread.channel_id()), bot_message.json());
+        let message_path = format!("/channels/{}/messages/{}", thread.channel_id(), bot_message.message_id());
+        discord.respond("GET", &message_path, bot_message.json());
+        discord.respond("PATCH", &message_path, bot_message.json());
+        discord.respond("GET", &format!("/guilds/{}/members/{}", GUILD, author.user_id()), MemberBuilder::new(author).json());
+        Self { thread, bot_message }
+    }
+
+    /// Last message posted by the bot in the thread
+    fn last_post(&self, bot: &TestBot) -> Value {
+        let posts = bot.discord.requests_to("POST", &format!("/channels/{}/messages", self.thread.channel_id()));
+        posts.last().and_then(|request| request.body.clone()).expect("No message was posted in the edition thread")
+    }
+
+    /// The last posted message, as received in a button interaction
+    fn clicked_message(&self, posted: &Value) -> MessageBuilder {
+        self.bot_message.clone().components(posted["components"].clone())
+    }
+}
+
+async fn open_edition_thread(bot: &TestBot, advertising: &Advertising, author: &MemberBuilder) {
+    let command = CommandInteractionBuilder::new("annonce", ChannelId::new(1), author);
+    advertising.execute_command(bot.ctx.clone(), "annonce", command.build()).await.expect("Failed to execute command");
+}
+
+/// Answer the questions of the edition thread (first button, or a text answer) until the preview is printed
+async fn fill_ad(bot: &TestBot, advertising: &Advertising, thread: &EditionThread, author: &UserBuilder, member: &MemberBuilder) -> Vec<String> {
+    let mut questions = vec![];
+    for _ in 0..50 {
+        let posted = thread.last_post(bot);
+        let content = posted["content"].as_str().unwrap_or_default().to_string();
+        if content.starts_with(PREVIEW) {
+            return questions;
+        }
+        questions.push(content.clone());
+        match posted["components"][0]["components"][0]["custom_id"].as_str() {
+            Some(custom_id) if !content.contains("Écris ta réponse") => {
+                let click = ComponentInteractionBuilder::button(custom_id, &thread.clicked_message(&posted), member);
+                advertising.interaction_create(bot.ctx.clone(), Interaction::Component(click.build())).await.expect("Failed to handle button");
+            }
+            _ => {
+                let answer = MessageBuilder::new(thread.thread.channel_id(), author, &format!("Réponse {}", questions.len()));
+                advertising.message(bot.ctx.clone(), answer.build()).await.expect("Failed to handle answer");
+            }
+        }
+        assert_ne!(thread.last_post(bot), posted, "No new question after answering `{}`", content);
+    }
+    panic!("The preview was not printed after {} questions", questions.len())
+}
+
+#[tokio::test]
+async fn command_opens_an_edition_thread() {
+    let bot = start().await;
+    let advertising = bot.load_module::<Advertising>().await.expect("Failed to load advertising");
+    let author = UserBuilder::new("recruiter");
+    let member = MemberBuilder::new(&author);
+    let thread = EditionThread::mock(&bot, &author);
+
+    open_edition_thread(&bot, &advertising, &member).await;
+
+    assert_eq!(bot.discord.requests_to("POST", &format!("/channels/{}/threads", IN_PROGRESS_CHANNEL)).len(), 1);
+    assert_eq!(bot.discord.requests_to("PUT", &format!("/channels/{}/thread-members/{}", thread.thread.channel_id(), author.user_id())).len(), 1);
+    assert!(thread.last_post(&bot)["content"].as_str().unwrap().contains("Donne un titre à ton annonce"));
+}
+
+#[tokio::test]
+async fn text_answer_is_recorded_and_next_question_is_asked() {
+    let bot = start().await;
+    let advertising = bot.load_module::<Advertising>().await.expect("Failed to load advertising");
+    let author = UserBuilder::new("writer");
+    let member = MemberBuilder::new(&author);
+    let thread = EditionThread::mock(&bot, &author);
+    open_edition_thread(&bot, &advertising, &member).await;
+
+    let answer = MessageBuilder::new(thread.thread.channel_id(), &author, "Développeur gameplay");
+    advertising.message(bot.ctx.clone(), answer.build()).await.expect("Failed to handle answer");
+
+    let edits = bot.discord.requests_to("PATCH", &format!("/channels/{}/messages/{}", thread.thread.channel_id(), thread.bot_message.message_id()));
+    assert!(edits[0].body.as_ref().unwrap()["content"].as_str().unwrap().contains("`Développeur gameplay`"), "The question should display the answer");
+    assert_eq!(bot.discord.requests_to("DELETE", &format!("/channels/{}/messages/{}", thread.thread.channel_id(), answer.message_id())).len(), 1, "The answer should be deleted");
+    assert!(thread.last_post(&bot)["content"].as_str().unwrap().contains("Décris ton annonce"));
+
+    // Messages sent outside of the edition thread are ignored
+    bot.discord.clear_requests();
+    advertising.message(bot.ctx.clone(), MessageBuilder::new(ChannelId::new(1), &author, "Hors sujet").build()).await.expect("Failed to handle message");
+    assert!(bot.discord.requests().is_empty());
+}
+
+#[tokio::test]
+async fn completed_steps_print_the_preview() {
+    let bot = start().await;
+    let advertising = bot.load_module::<Advertising>().await.expect("Failed to load advertising");
+    let author = UserBuilder::new("candidate");
+    let member = MemberBuilder::new(&author);
+    let thread = EditionThread::mock(&bot, &author);
+    open_edition_thread(&bot, &advertising, &member).await;
+
+    let questions = fill_ad(&bot, &advertising, &thread, &author, &member).await;
+    assert!(questions.iter().any(|question| question.contains("Quel type de contrat recherches-tu ?")));
+    assert!(questions.iter().any(|question| question.contains("Comment peut-on te contacter ?")));
+
+    let preview = thread.last_post(&bot);
+    assert_eq!(preview["embeds"][0]["title"], "Réponse 1");
+    assert_eq!(preview["embeds"].as_array().unwrap().last().unwrap()["title"], "Contact");
+    let publish = preview["components"][0]["components"][0].clone();
+    assert_eq!(publish["label"], "Publier");
+}
+
+#[tokio::test]
+async fn reviewer_publishes_the_ad() {
+    let bot = start().await;
+    let advertising = bot.load_module::<Advertising>().await.expect("Failed to load advertising");
+    let author = UserBuilder::new("publisher");
+    let member = MemberBuilder::new(&author);
+    let thread = EditionThread::mock(&bot, &author);
+    open_edition_thread(&bot, &advertising, &member).await;
+    fill_ad(&bot, &advertising, &thread, &author, &member).await;
+
+    // Author submits the ad
+    let preview = thread.last_post(&bot);
+    let publish = preview["components"][0]["components"][0]["custom_id"].as_str().unwrap().to_string();
+    let click = ComponentInteractionBuilder::button(&publish, &thread.clicked_message(&preview), &member);
+    advertising.interaction_create(bot.ctx.clone(), Interaction::Component(click.build())).await.expect("Failed to submit ad");
+    let review = bot.discord.requests_to("PATCH", &format!("/channels/{}/messages/{}", thread.thread.channel_id(), thread.bot_message.message_id())).pop().and_then(|request| request.body).unwrap();
+    let validate = review["components"][0]["components"][0]["custom_id"].as_str().unwrap().to_string();
+
+    // Reviewer validates it
+    let reviewer_user = UserBuilder::new("reviewer");
+    let reviewer = MemberBuilder::new(&reviewer_user).role(REVIEWER_ROLE);
+    let post = ChannelBuilder::thread(AD_FORUM, &UserBuilder::bidibip(), "Annonce publiée");
+    let post_message = MessageBuilder::new(post.channel_id(), &UserBuilder::bidibip(), "");
+    bot.discord.respond("GET", &format!("/guilds/{}/members/{}", GUILD, reviewer_user.user_id()), reviewer.json());
+    bot.discord.respond("GET", &format!("/users/{}", author.user_id()), author.json());
+    bot.discord.respond("POST", &format!("/channels/{}/threads", AD_FORUM), post.json());
+    bot.discord.respond("GET", &format!("/channels/{}/messages", post.channel_id()), json!([post_message.json()]));
+    bot.discord.respond("DELETE", &format!("/channels/{}", thread.thread.channel_id()), thread.thread.json());
+    let click = ComponentInteractionBuilder::button(&validate, &thread.clicked_message(&review), &reviewer);
+    advertising.interaction_create(bot.ctx.clone(), Interaction::Component(click.build())).await.expect("Failed to validate ad");
+
+    let forum_post = bot.discord.requests_to("POST", &format!("/channels/{}/threads", AD_FORUM)).pop().and_then(|request| request.body).expect("The ad should be posted in the forum");
+    assert!(forum_post["name"].as_str().unwrap().ends_with("Réponse 1"));
+    assert_eq!(bot.discord.requests_to("DELETE", &format!("/channels/{}", thread.thread.channel_id())).len(), 1, "The edition thread should be deleted");
+}
@@ -0,0 +1,82 @@
+mod common;
+
+use serenity::all::{ChannelId, Interaction, RoleId};
+use modules::anti_spam::AntiSpam;
+use common::{anti_spam_config, button, spam};
+use test_support::fixtures::{next_id, ComponentInteractionBuilder, MemberBuilder, MessageBuilder, UserBuilder};
+use test_support::{write_module_config, TestBot, GUILD};
+use utils::module::BidibipModule;
+
+const MUTE_ROLE: RoleId = RoleId::new(2000);
+const MODERATION_CHANNEL: ChannelId = ChannelId::new(2001);
+
+async fn start() -> TestBot {
+    write_module_config::<AntiSpam>(anti_spam_config(MUTE_ROLE, MODERATION_CHANNEL));
+    TestBot::start().await.expect("Failed to start test bot")
+}
+
+#[tokio::test]
+async fn repeated_message_mutes_the_spammer() {
+    let bot = start().await;
+    let anti_spam = bot.load_module::<AntiSpam>().await.expect("Failed to load anti spam");
+    let spammer = MemberBuilder::new(&UserBuilder::new("spammer"));
+    let user_id = spammer.build().user.id;
+
+    spam(&bot, anti_spam.as_ref(), &spammer, MODERATION_CHANNEL).await;
+
+    assert_eq!(bot.discord.requests_to("PUT", &format!("/guilds/{}/members/{}/roles/{}", GUILD, user_id, MUTE_ROLE)).len(), 1);
+    let deleted = bot.discord.requests().into_iter().filter(|request| request.method == "DELETE" && request.path.starts_with("/channels/")).count();
+    assert_eq!(deleted, 2, "The previous copies of the message should be deleted");
+}
+
+#[tokio::test]
+async fn same_channel_is_not_spam() {
+    let bot = start().await;
+    let anti_spam = bot.load_module::<AntiSpam>().await.expect("Failed to load anti spam");
+    let author = UserBuilder::new("chatty");
+    let channel = ChannelId::new(next_id());
+
+    for _ in 0..4 {
+        anti_spam.message(bot.ctx.clone(), MessageBuilder::new(channel, &author, "up").build()).await.expect("Failed to handle message");
+    }
+    assert!(bot.discord.requests().is_empty(), "No request should be sent for messages repeated in the same channel");
+}
+
+#[tokio::test]
+async fn kick_button_kicks_the_spammer() {
+    let bot = start().await;
+    let anti_spam = bot.load_module::<AntiSpam>().await.expect("Failed to load anti spam");
+    let spammer = MemberBuilder::new(&UserBuilder::new("spammer"));
+    let user_id = spammer.build().user.id;
+    let moderation_message = spam(&bot, anti_spam.as_ref(), &spammer, MODERATION_CHANNEL).await;
+    bot.discord.clear_requests();
+
+    let moderator = MemberBuilder::new(&UserBuilder::new("moderator"));
+    let click = ComponentInteractionBuilder::button(&button(&moderation_message, 0), &moderation_message, &moderator);
+    anti_spam.interaction_create(bot.ctx.clone(), Interaction::Component(click.build())).await.expect("Failed to handle kick");
+
+    assert_eq!(bot.discord.requests_to("DELETE", &format!("/guilds/{}/members/{}", GUILD, user_id)).len(), 1, "The spammer should be kicked");
+    assert_eq!(bot.discord.requests_to("DELETE", &format!("/channels/{}/messages/{}", MODERATION_CHANNEL, moderation_message.message_id())).len(), 1);
+
+    // The moderation message is forgotten once handled
+    bot.discord.clear_requests();
+    anti_spam.interaction_create(bot.ctx.clone(), Interaction::Component(click.build())).await.expect("Failed to handle kick");
+    assert!(bot.discord.requests().is_empty());
+}
+
+#[tokio::test]
+async fn pardon_button_removes_the_mute() {
+    let bot = start().await;
+    let anti_spam = bot.load_module::<AntiSpam>().await.expect("Failed to load anti spam");
+    let spammer = MemberBuilder::new(&UserBuilder::new("spammer"));
+    let user_id = spammer.build().user.id;
+    let moderation_message = spam(&bot, anti_spam.as_ref(), &spammer, MODERATION_CHANNEL).await;
+    bot.discord.clear_requests();
+
+    let moderator = MemberBuilder::new(&UserBuilder::new("moderator"));
+    let click = ComponentInteractionBuilder::button(&button(&moderation_message, 1), &moderation_message, &moderator);
+    anti_spam.interaction_create(bot.ctx.clone(), Interaction::Component(click.build())).await.expect("Failed to handle pardon");
+
+    assert_eq!(bot.discord.requests_to("DELETE", &format!("/guilds/{}/members/{}/roles/{}", GUILD, user_id, MUTE_ROLE)).len(), 1, "The mute role should be removed");
+    assert!(bot.discord.requests_to("DELETE", &format!("/guilds/{}/members/{}", GUILD, user_id)).is_empty(), "The spammer should not be kicked");
+}
@@ -0,0 +1,51 @@
+//! Anti spam scenarios shared by the module tests
+// Each test binary only uses some of the helpers
+#![allow(dead_code)]
+
+use serde_json::{json, Value};
+use serenity::all::{ChannelId, RoleId};
+use modules::anti_spam::AntiSpam;
+use test_support::fixtures::{next_id, MemberBuilder, MessageBuilder, UserBuilder};
+use test_support::TestBot;
+use utils::module::BidibipModule;
+
+/// Anti spam config detecting a message sent in 3 channels
+pub fn anti_spam_config(mute_role: RoleId, moderation_channel: ChannelId) -> Value {
+    json!({
+        "min_occurrences": 2,
+        "max_delay_ms": 60000,
+        "mute_role": mute_role,
+        "moderation_channel": moderation_channel,
+    })
+}
+
+/// Send the same message in 3 channels of the guild of the spammer
+pub async fn send_spam(bot: &TestBot, anti_spam: &AntiSpam, spammer: &MemberBuilder) {
+    let member = spammer.build();
+    bot.discord.respond("GET", &format!("/guilds/{}/members/{}", member.guild_id, member.user.id), spammer.json());
+
+    let author = UserBuilder::new("spammer").id(member.user.id);
+    for _ in 0..3 {
+        let message = MessageBuilder::new(ChannelId::new(next_id()), &author, "Free nitro : https://scam.example").guild(member.guild_id);
+        bot.discord.respond("GET", &format!("/channels/{}/messages/{}", message.build().channel_id, message.message_id()), message.json());
+        anti_spam.message(bot.ctx.clone(), message.build()).await.expect("Failed to handle message");
+    }
+}
+
+/// Spam, and return the moderation message posted by the bot
+pub async fn spam(bot: &TestBot, anti_spam: &AntiSpam, spammer: &MemberBuilder, moderation_channel: ChannelId) -> MessageBuilder {
+    let moderation_message = MessageBuilder::new(moderation_channel, &UserBuilder::bidibip(), "Spam potentiel");
+    bot.discord.respond("POST", &format!("/channels/{}/messages", moderation_channel), moderation_message.json());
+
+    send_spam(bot, anti_spam, spammer).await;
+
+    let posted = bot.discord.requests_to("POST", &format!("/channels/{}/messages", moderation_channel));
+    assert_eq!(posted.len(), 1, "Exactly one moderation message should be sent");
+    let body = posted[0].body.clone().expect("Moderation message without body");
+    moderation_message.components(body["components"].clone())
+}
+
+/// Custom id of the kick (0) or pardon (1) button of the moderation message
+pub fn button(moderation_message: &MessageBuilder, index: usize) -> String {
+    moderation_message.json()["components"][0]["components"][index]["custom_id"].as_str().expect("Missing button").to_string()
+}
@@ -0,0 +1,55 @@
+use serde_json::json;
+use serenity::all::{ChannelId, RoleId};
+use modules::anti_spam::AntiSpam;
+use modules::utilities::Utilities;
+use test_support::fixtures::{interaction_callback_path, next_id, CommandInteractionBuilder, MemberBuilder, UserBuilder};
+use test_support::{write_module_config, TestBot};
+use utils::config::Config;
+use utils::module::{BidibipModule, LoadModule};
+
+async fn config(bot: &TestBot, utilities: &Utilities, command: CommandInteractionBuilder) -> String {
+    let command = command.build();
+    let callback = interaction_callback_path(command.id, &command.token);
+    utilities.execute_command(bot.ctx.clone(), "config", command).await.expect("Failed to execute command");
+    let response = bot.discord.requests_to("POST", &callback).pop().and_then(|request| request.body).expect("The command should be answered");
+    response["data"]["content"].as_str().unwrap_or_default().to_string()
+}
+
+#[tokio::test]
+async fn config_can_only_be_read_for_known_modules() {
+    let bot = TestBot::start().await.expect("Failed to start test bot");
+    let utilities = bot.load_module::<Utilities>().await.expect("Failed to load utilities");
+    let outside = Config::get().module_config_directory.parent().expect("Missing parent directory").join("secret_config.json");
+    std::fs::write(&outside, r#"{"token": "do not read"}"#).unwrap();
+    let admin = MemberBuilder::new(&UserBuilder::new("admin"));
+    let command = |subcommand: &str| CommandInteractionBuilder::new("config", ChannelId::new(next_id()), &admin).subcommand(subcommand).string_option("module", "../secret");
+
+    for response in [
+        config(&bot, &utilities, command("list")).await,
+        config(&bot, &utilities, command("get").string_option("champ", "token")).await,
+        config(&bot, &utilities, command("set").string_option("champ", "token").string_option("valeur", "\"changed\"")).await,
+    ] {
+        assert!(response.contains("Ce module n'existe pas"), "Unexpected response {}", response);
+        assert!(!response.contains("do not read"));
+    }
+    assert_eq!(std::fs::read_to_string(&outside).unwrap(), r#"{"token": "do not read"}"#);
+}
+
+#[tokio::test]
+async fn rejected_config_change_only_restores_the_edited_field() {
+    write_module_config::<AntiSpam>(json!({
+        "min_occurrences": 2,
+        "max_delay_ms": 60000,
+        "mute_role": RoleId::new(next_id()),
+        "moderation_channel": ChannelId::new(next_id()),
+    }));
+    let bot = TestBot::start().await.expect("Failed to start test bot");
+    bot.load_module::<AntiSpam>().await.expect("Failed to load anti spam");
+
+    let result = bot.shared_data.set_module_config_field(&bot.ctx.http, AntiSpam::name(), "min_occurrences", json!("deux")).await;
+
+    assert!(result.is_err(), "The invalid value should be rejected");
+    let config = Config::get().read_module_config_value(AntiSpam::name()).unwrap();
+    assert_eq!(config["min_occurrences"], 2);
+    assert_eq!(config["max_delay_ms"], 60000);
+}
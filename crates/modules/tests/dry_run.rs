@@ -0,0 +1,117 @@
+mod common;
+
+use serde_json::json;
+use serenity::all::{ChannelId, Interaction, RoleId};
+use modules::anti_spam::AntiSpam;
+use modules::warn::{ActionType, Warn};
+use common::{anti_spam_config, button, spam};
+use test_support::fixtures::{interaction_callback_path, private_channel_json, ComponentInteractionBuilder, MemberBuilder, MessageBuilder, UserBuilder};
+use test_support::{init_environment_with, write_module_config, TestBot, GUILD, STAFF_CHANNEL};
+use utils::config::Config;
+use utils::module::BidibipModule;
+
+const MUTE_ROLE: RoleId = RoleId::new(6000);
+const MODERATION_CHANNEL: ChannelId = ChannelId::new(6001);
+const PUBLIC_WARN_CHANNEL: ChannelId = ChannelId::new(6002);
+const MODERATION_WARN_CHANNEL: ChannelId = ChannelId::new(6003);
+
+async fn start() -> TestBot {
+    init_environment_with(json!({"dry_run": {"modules": ["anti-spam"]}}));
+    write_module_config::<AntiSpam>(anti_spam_config(MUTE_ROLE, MODERATION_CHANNEL));
+    write_module_config::<Warn>(json!({
+        "public_warn_channel": PUBLIC_WARN_CHANNEL,
+        "moderation_warn_channel": MODERATION_WARN_CHANNEL,
+        "ban-vocal": 6004,
+    }));
+    TestBot::start().await.expect("Failed to start test bot")
+}
+
+#[tokio::test]
+async fn only_listed_modules_are_in_dry_run() {
+    start().await;
+    assert!(Config::get().dry_run.is_dry_run("anti-spam"));
+    assert!(!Config::get().dry_run.is_dry_run("warn"));
+}
+
+#[tokio::test]
+async fn spammer_is_not_muted_in_dry_run() {
+    let bot = start().await;
+    let anti_spam = bot.load_module::<AntiSpam>().await.expect("Failed to load anti spam");
+    let spammer = MemberBuilder::new(&UserBuilder::new("spammer"));
+
+    spam(&bot, anti_spam.as_ref(), &spammer, MODERATION_CHANNEL).await;
+
+    // The moderation is still told, but nothing is applied
+    assert_eq!(bot.discord.requests_to("POST", &format!("/channels/{}/messages", MODERATION_CHANNEL)).len(), 1);
+    assert!(bot.discord.requests().iter().all(|request| request.method != "PUT" && request.method != "DELETE"), "No destructive request should be sent");
+}
+
+#[tokio::test]
+async fn spammer_is_not_kicked_in_dry_run() {
+    let bot = start().await;
+    let anti_spam = bot.load_module::<AntiSpam>().await.expect("Failed to load anti spam");
+    let spammer = MemberBuilder::new(&UserBuilder::new("spammer"));
+    let moderation_message = spam(&bot, anti_spam.as_ref(), &spammer, MODERATION_CHANNEL).await;
+    let kick = button(&moderation_message, 0);
+
+    let click = ComponentInteractionBuilder::button(&kick, &moderation_message, &MemberBuilder::new(&UserBuilder::new("moderator")));
+    anti_spam.interaction_create(bot.ctx.clone(), Interaction::Component(click.build())).await.expect("Failed to handle kick");
+
+    assert!(bot.discord.requests_to("DELETE", &format!("/guilds/{}/members/{}", GUILD, spammer.build().user.id)).is_empty(), "The spammer should not be kicked");
+    // The bot still cleans its own moderation message
+    assert_eq!(bot.discord.requests_to("DELETE", &format!("/channels/{}/messages/{}", MODERATION_CHANNEL, moderation_message.message_id())).len(), 1);
+}
+
+/// Requests that would tell the sanction to the member or to everyone
+fn assert_nothing_public(bot: &TestBot) {
+    assert!(bot.discord.requests_to("POST", &format!("/channels/{}/messages", PUBLIC_WARN_CHANNEL)).is_empty(), "No public warn message should be sent");
+    assert!(bot.discord.requests_to("POST", "/users/@me/channels").is_empty(), "No private message should be sent");
+}
+
+#[tokio::test]
+async fn dry_run_kick_is_not_announced_nor_recorded() {
+    let bot = start().await;
+    let warn = bot.load_module::<Warn>().await.expect("Failed to load warn");
+    let anti_spam = bot.load_module::<AntiSpam>().await.expect("Failed to load anti spam");
+    let spammer = MemberBuilder::new(&UserBuilder::new("spammer"));
+    let user = UserBuilder::new("spammer").id(spammer.build().user.id);
+    bot.discord.respond("POST", "/users/@me/channels", private_channel_json(&user));
+    let moderation_message = spam(&bot, anti_spam.as_ref(), &spammer, MODERATION_CHANNEL).await;
+    let kick = button(&moderation_message, 0);
+
+    let click = ComponentInteractionBuilder::button(&kick, &moderation_message, &MemberBuilder::new(&UserBuilder::new("moderator"))).build();
+    let callback = interaction_callback_path(click.id, &click.token);
+    anti_spam.interaction_create(bot.ctx.clone(), Interaction::Component(click)).await.expect("Failed to handle kick");
+
+    let response = bot.discord.requests_to("POST", &callback);
+    assert!(response[0].body.as_ref().unwrap()["data"]["content"].as_str().unwrap().starts_with("[dry run]"), "The moderation should be told the kick was not applied");
+    assert_nothing_public(&bot);
+    assert!(bot.discord.requests_to("POST", &format!("/channels/{}/messages", MODERATION_WARN_CHANNEL)).is_empty());
+
+    // The kick is not in the warn history
+    bot.discord.clear_requests();
+    warn.guild_member_addition(bot.ctx.clone(), spammer.build()).await.expect("Failed to handle new member");
+    assert!(bot.discord.requests_to("POST", &format!("/channels/{}/messages", STAFF_CHANNEL)).is_empty());
+}
+
+#[tokio::test]
+async fn dry_run_sanction_is_only_told_to_the_moderation() {
+    let bot = start().await;
+    let warn = bot.load_module::<Warn>().await.expect("Failed to load warn");
+    let user = UserBuilder::new("troll");
+    let member = MemberBuilder::new(&user);
+    bot.discord.respond("GET", &format!("/guilds/{}/members/{}", GUILD, user.user_id()), member.json());
+    bot.discord.respond("POST", "/users/@me/channels", private_channel_json(&user));
+    bot.discord.respond("POST", &format!("/channels/{}/messages", MODERATION_WARN_CHANNEL), MessageBuilder::new(MODERATION_WARN_CHANNEL, &UserBuilder::bidibip(), "[dry run]").json());
+
+    warn.record_sanction::<AntiSpam>(&bot.ctx, GUILD, &UserBuilder::new("moderator").build(), &user.build(), ActionType::Kick, "Spam détecté", None).await.expect("Failed to record sanction");
+
+    let notices = bot.discord.requests_to("POST", &format!("/channels/{}/messages", MODERATION_WARN_CHANNEL));
+    assert_eq!(notices.len(), 1);
+    assert!(notices[0].body.as_ref().unwrap()["content"].as_str().unwrap().starts_with("[dry run]"));
+    assert_nothing_public(&bot);
+
+    bot.discord.clear_requests();
+    warn.guild_member_addition(bot.ctx.clone(), member.build()).await.expect("Failed to handle new member");
+    assert!(bot.discord.requests_to("POST", &format!("/channels/{}/messages", STAFF_CHANNEL)).is_empty(), "The sanction should not be in the warn history");
+}
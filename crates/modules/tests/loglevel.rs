@@ -0,0 +1,35 @@
+use serenity::all::ChannelId;
+use modules::utilities::Utilities;
+use test_support::fixtures::{interaction_callback_path, next_id, CommandInteractionBuilder, MemberBuilder, UserBuilder};
+use test_support::{init_environment, TestBot};
+use utils::logger::init_logger;
+use utils::module::BidibipModule;
+
+async fn loglevel(bot: &TestBot, utilities: &Utilities, command: CommandInteractionBuilder) -> String {
+    let command = command.build();
+    let callback = interaction_callback_path(command.id, &command.token);
+    utilities.execute_command(bot.ctx.clone(), "loglevel", command).await.expect("Failed to execute command");
+    let response = bot.discord.requests_to("POST", &callback).pop().and_then(|request| request.body).expect("The command should be answered");
+    response["data"]["content"].as_str().unwrap_or_default().to_string()
+}
+
+/// The logger is global : every step runs in the same test
+#[tokio::test]
+async fn loglevel_sets_and_resets_the_filters() {
+    init_environment();
+    init_logger();
+    let bot = TestBot::start().await.expect("Failed to start test bot");
+    let utilities = bot.load_module::<Utilities>().await.expect("Failed to load utilities");
+    let admin = MemberBuilder::new(&UserBuilder::new("admin"));
+    let command = || CommandInteractionBuilder::new("loglevel", ChannelId::new(next_id()), &admin);
+
+    let response = loglevel(&bot, &utilities, command().subcommand("set").string_option("sortie", "discord").string_option("filtre", "warn,modules::warn=debug")).await;
+    assert_eq!(response, "Filtre de **discord** : `warn,modules::warn=debug`");
+
+    // Invalid directives are refused
+    let response = loglevel(&bot, &utilities, command().subcommand("set").string_option("sortie", "stdout").string_option("filtre", "modules=verbose")).await;
+    assert!(response.contains("Impossible d'appliquer le filtre"), "Unexpected response {}", response);
+
+    let response = loglevel(&bot, &utilities, command().subcommand("reset").string_option("sortie", "discord")).await;
+    assert_eq!(response, "Filtre de **discord** : `info,log=off`");
+}
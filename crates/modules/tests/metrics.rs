@@ -0,0 +1,91 @@
+mod common;
+
+use chrono::Utc;
+use serde_json::json;
+use serenity::all::{ChannelId, RoleId};
+use modules::anti_spam::AntiSpam;
+use modules::user_count::UserCount;
+use modules::warn::{ActionType, Warn};
+use common::{anti_spam_config, send_spam};
+use test_support::http::http_get;
+use test_support::fixtures::{next_id, private_channel_json, MemberBuilder, MessageBuilder, UserBuilder};
+use test_support::{write_module_config, TestBot, GUILD};
+use utils::http_server::{start_server, HttpConfig};
+use utils::metrics::Metrics;
+use utils::module::{BidibipModule, LoadModule};
+use utils::scheduler::{JobSchedule, ScheduledJob};
+
+const PUBLIC_WARN_CHANNEL: ChannelId = ChannelId::new(8000);
+const MODERATION_WARN_CHANNEL: ChannelId = ChannelId::new(8001);
+const ANTI_SPAM_CHANNEL: ChannelId = ChannelId::new(8002);
+const MUTE_ROLE: RoleId = RoleId::new(8003);
+
+#[tokio::test]
+async fn metrics_are_exposed_over_http() {
+    let bot = TestBot::start().await.expect("Failed to start test bot");
+    let config = HttpConfig { enabled: true, address: "127.0.0.1:0".to_string(), ..Default::default() };
+    let address = start_server(&config, bot.shared_data.clone()).await.expect("Failed to start HTTP listener");
+    Metrics::get().commands.with_label_values(&["sanction"]).inc();
+
+    let (status, body) = http_get(address, "/metrics").await;
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    assert!(body.contains("bidibip_commands_total{command=\"sanction\"}"));
+    assert!(body.contains("# TYPE bidibip_members gauge"));
+
+    assert_eq!(http_get(address, "/unknown").await.0, "HTTP/1.1 404 Not Found");
+}
+
+#[tokio::test]
+async fn metrics_can_be_disabled() {
+    let bot = TestBot::start().await.expect("Failed to start test bot");
+    let config = HttpConfig { enabled: true, address: "127.0.0.1:0".to_string(), metrics: false, ..Default::default() };
+    let address = start_server(&config, bot.shared_data.clone()).await.expect("Failed to start HTTP listener");
+
+    assert_eq!(http_get(address, "/metrics").await.0, "HTTP/1.1 404 Not Found");
+}
+
+#[tokio::test]
+async fn sanctions_and_spam_detections_are_counted() {
+    write_module_config::<Warn>(json!({
+        "public_warn_channel": PUBLIC_WARN_CHANNEL,
+        "moderation_warn_channel": MODERATION_WARN_CHANNEL,
+        "ban-vocal": RoleId::new(next_id()),
+    }));
+    write_module_config::<AntiSpam>(anti_spam_config(MUTE_ROLE, ANTI_SPAM_CHANNEL));
+    let bot = TestBot::start().await.expect("Failed to start test bot");
+    let warn = bot.load_module::<Warn>().await.expect("Failed to load warn");
+    let anti_spam = bot.load_module::<AntiSpam>().await.expect("Failed to load anti spam");
+    let bidibip = UserBuilder::bidibip();
+    for channel in [PUBLIC_WARN_CHANNEL, MODERATION_WARN_CHANNEL, ANTI_SPAM_CHANNEL] {
+        bot.discord.respond("POST", &format!("/channels/{}/messages", channel), MessageBuilder::new(channel, &bidibip, "Sanction").json());
+    }
+    let user = UserBuilder::new("spammer");
+    let dm_channel = private_channel_json(&user);
+    bot.discord.respond("GET", &format!("/guilds/{}/members/{}", GUILD, user.user_id()), MemberBuilder::new(&user).json());
+    bot.discord.respond("POST", "/users/@me/channels", dm_channel.clone());
+    bot.discord.respond("POST", &format!("/channels/{}/messages", dm_channel["id"].as_str().unwrap()), MessageBuilder::new(ChannelId::new(next_id()), &bidibip, "DM").private().json());
+
+    let kicks = Metrics::get().sanctions.with_label_values(&["kick"]).get();
+    warn.record_sanction::<AntiSpam>(&bot.ctx, GUILD, &UserBuilder::new("moderator").build(), &user.build(), ActionType::Kick, "Spam", None).await.expect("Failed to record sanction");
+    assert_eq!(Metrics::get().sanctions.with_label_values(&["kick"]).get(), kicks + 1);
+
+    let detections = Metrics::get().spam_detections.get();
+    send_spam(&bot, anti_spam.as_ref(), &MemberBuilder::new(&user)).await;
+    assert_eq!(Metrics::get().spam_detections.get(), detections + 1);
+}
+
+#[tokio::test]
+async fn member_count_is_exposed() {
+    let bot = TestBot::start().await.expect("Failed to start test bot");
+    let user_count = bot.load_module::<UserCount>().await.expect("Failed to load user count");
+    let members: Vec<_> = ["first", "second", "third"].into_iter().map(|name| MemberBuilder::new(&UserBuilder::new(name)).json()).collect();
+    bot.discord.respond("GET", &format!("/guilds/{}/members", GUILD), json!(members));
+
+    // The refresh job counts the members of the mocked guild
+    let refresh = ScheduledJob { module: UserCount::name().to_string(), name: "refresh".to_string(), payload: json!(null), schedule: JobSchedule::cron("0 0 * * * *").unwrap(), next_run: Utc::now() };
+    user_count.run_job(bot.ctx.clone(), refresh).await.expect("Failed to run refresh job");
+    assert_eq!(Metrics::get().members.get(), members.len() as i64);
+
+    user_count.guild_member_addition(bot.ctx.clone(), MemberBuilder::new(&UserBuilder::new("member")).build()).await.expect("Failed to handle new member");
+    assert_eq!(Metrics::get().members.get(), members.len() as i64 + 1);
+}
@@ -0,0 +1,54 @@
+mod common;
+
+use serde_json::json;
+use serenity::all::{ChannelId, GuildId, RoleId};
+use modules::anti_spam::AntiSpam;
+use common::{send_spam, spam};
+use test_support::fixtures::{MemberBuilder, UserBuilder};
+use test_support::{init_environment_with, write_module_config, TestBot, LOG_CHANNEL, STAFF_CHANNEL};
+
+const CONFIGURED_GUILD: GuildId = GuildId::new(3000);
+const UNCONFIGURED_GUILD: GuildId = GuildId::new(3001);
+const MUTE_ROLE: RoleId = RoleId::new(3002);
+const MODERATION_CHANNEL: ChannelId = ChannelId::new(3003);
+const OTHER_MUTE_ROLE: RoleId = RoleId::new(3004);
+const OTHER_MODERATION_CHANNEL: ChannelId = ChannelId::new(3005);
+
+async fn start() -> TestBot {
+    let guild = json!({
+        "channels": {"log_channel": LOG_CHANNEL, "staff_channel": STAFF_CHANNEL},
+        "roles": {"support": 1, "member": 2, "helper": 3, "administrator": 4, "mute": 5},
+    });
+    init_environment_with(json!({"guilds": {CONFIGURED_GUILD.to_string(): guild, UNCONFIGURED_GUILD.to_string(): guild}}));
+    write_module_config::<AntiSpam>(json!({
+        "min_occurrences": 2,
+        "max_delay_ms": 60000,
+        "mute_role": MUTE_ROLE,
+        "moderation_channel": MODERATION_CHANNEL,
+        "guilds": {CONFIGURED_GUILD.to_string(): {"mute_role": OTHER_MUTE_ROLE, "moderation_channel": OTHER_MODERATION_CHANNEL}},
+    }));
+    TestBot::start().await.expect("Failed to start test bot")
+}
+
+#[tokio::test]
+async fn guilds_use_their_own_section() {
+    let bot = start().await;
+    let anti_spam = bot.load_module::<AntiSpam>().await.expect("Failed to load anti spam");
+    let spammer = MemberBuilder::new(&UserBuilder::new("spammer")).guild(CONFIGURED_GUILD);
+
+    spam(&bot, anti_spam.as_ref(), &spammer, OTHER_MODERATION_CHANNEL).await;
+
+    let user_id = spammer.build().user.id;
+    assert_eq!(bot.discord.requests_to("PUT", &format!("/guilds/{}/members/{}/roles/{}", CONFIGURED_GUILD, user_id, OTHER_MUTE_ROLE)).len(), 1, "The mute role of the guild should be used");
+    assert!(bot.discord.requests_to("POST", &format!("/channels/{}/messages", MODERATION_CHANNEL)).is_empty(), "The main guild should not be notified");
+}
+
+#[tokio::test]
+async fn guilds_without_section_are_ignored() {
+    let bot = start().await;
+    let anti_spam = bot.load_module::<AntiSpam>().await.expect("Failed to load anti spam");
+    send_spam(&bot, anti_spam.as_ref(), &MemberBuilder::new(&UserBuilder::new("spammer")).guild(UNCONFIGURED_GUILD)).await;
+
+    let requests = bot.discord.requests().into_iter().filter(|request| request.method != "GET").count();
+    assert_eq!(requests, 0, "The main guild config should not be used on other guilds");
+}
@@ -0,0 +1,135 @@
+use serde_json::{json, Value};
+use serenity::all::{ChannelId, Interaction};
+use modules::repost::Repost;
+use test_support::fixtures::{interaction_callback_path, ChannelBuilder, ComponentInteractionBuilder, MemberBuilder, MessageBuilder, UserBuilder};
+use test_support::{write_module_config, TestBot, GUILD};
+use utils::module::BidibipModule;
+
+const FORUM: ChannelId = ChannelId::new(4000);
+const REPOST_CHANNEL: ChannelId = ChannelId::new(4001);
+
+async fn start() -> TestBot {
+    write_module_config::<Repost>(json!({
+        "forums": {FORUM.to_string(): {"repost_channel": [REPOST_CHANNEL], "vote_enabled": true}},
+    }));
+    let bot = TestBot::start().await.expect("Failed to start test bot");
+    bot.discord.respond("GET", &format!("/channels/{}", FORUM), ChannelBuilder::forum("showcase").id(FORUM).json());
+    bot
+}
+
+/// A new forum post, and the messages posted by the bot when it is reposted
+struct Post {
+    thread: ChannelBuilder,
+    vote_message: MessageBuilder,
+    repost_message: MessageBuilder,
+}
+
+fn mock_post(bot: &TestBot, author: &UserBuilder) -> Post {
+    let thread = ChannelBuilder::thread(FORUM, author, "Mon jeu");
+    let first_message = MessageBuilder::new(thread.channel_id(), author, "Regardez mon jeu");
+    let bidibip = UserBuilder::bidibip();
+    let vote_message = MessageBuilder::new(thread.channel_id(), &bidibip, "Vote en réagissant au post !");
+    let repost_message = MessageBuilder::new(REPOST_CHANNEL, &bidibip, "Nouveau post");
+
+    let discord = &bot.discord;
+    discord.respond("GET", &format!("/channels/{}", thread.channel_id()), thread.json());
+    discord.respond("PATCH", &format!("/channels/{}", thread.channel_id()), thread.json());
+    discord.respond("GET", &format!("/channels/{}/messages", thread.channel_id()), json!([first_message.json()]));
+    discord.respond("GET", &format!("/guilds/{}/members/{}", GUILD, author.user_id()), MemberBuilder::new(author).json());
+    discord.respond("POST", &format!("/channels/{}/messages", thread.channel_id()), vote_message.json());
+    discord.respond("POST", &format!("/channels/{}/messages", REPOST_CHANNEL), repost_message.json());
+    for message in [&vote_message, &repost_message] {
+        let path = format!("/channels/{}/messages/{}", message.build().channel_id, message.message_id());
+        discord.respond("GET", &path, message.json());
+        discord.respond("PATCH", &path, message.json());
+    }
+    Post { thread, vote_message, repost_message }
+}
+
+/// Labels of the buttons of the last edition of the message
+fn edited_buttons(bot: &TestBot, message: &MessageBuilder) -> Vec<Value> {
+    let edits = bot.discord.requests_to("PATCH", &format!("/channels/{}/messages/{}", message.build().channel_id, message.message_id()));
+    let edit = edits.last().and_then(|request| request.body.clone()).expect("The message should be edited");
+    edit["components"][0]["components"].as_array().cloned().unwrap_or_default()
+}
+
+#[tokio::test]
+async fn new_post_is_reposted_with_votes() {
+    let bot = start().await;
+    let repost = bot.load_module::<Repost>().await.expect("Failed to load repost");
+    let post = mock_post(&bot, &UserBuilder::new("author"));
+
+    repost.thread_create(bot.ctx.clone(), post.thread.build()).await.expect("Failed to handle new thread");
+
+    let reposts = bot.discord.requests_to("POST", &format!("/channels/{}/messages", REPOST_CHANNEL));
+    assert_eq!(reposts.len(), 1);
+    let repost_body = reposts[0].body.clone().unwrap();
+    assert!(repost_body["content"].as_str().unwrap().starts_with("Nouveau post dans showcase"));
+    assert_eq!(repost_body["embeds"][0]["title"], "Mon jeu");
+    assert_eq!(repost_body["embeds"][0]["description"], "Regardez mon jeu");
+
+    let buttons = edited_buttons(&bot, &post.vote_message);
+    assert_eq!(buttons[0]["label"], "Pour ✅ 0");
+    assert_eq!(buttons[1]["label"], "Contre ❌ 0");
+    // Reposts also get a link to the post
+    assert_eq!(edited_buttons(&bot, &post.repost_message)[0]["label"], "Viens donc voir !");
+
+    let rename = bot.discord.wait_for("PATCH", &format!("/channels/{}", post.thread.channel_id())).await.expect("The thread should be renamed");
+    assert_eq!(rename.body.unwrap()["name"], "[❌0-0] Mon jeu");
+}
+
+#[tokio::test]
+async fn vote_updates_the_counters() {
+    let bot = start().await;
+    let repost = bot.load_module::<Repost>().await.expect("Failed to load repost");
+    let post = mock_post(&bot, &UserBuilder::new("author"));
+    repost.thread_create(bot.ctx.clone(), post.thread.build()).await.expect("Failed to handle new thread");
+    let yes = edited_buttons(&bot, &post.vote_message)[0]["custom_id"].as_str().unwrap().to_string();
+    bot.discord.clear_requests();
+
+    let voter = MemberBuilder::new(&UserBuilder::new("voter"));
+    let vote_message = post.vote_message.clone().components(json!([{"type": 1, "components": []}]));
+    let click = ComponentInteractionBuilder::button(&yes, &vote_message, &voter).build();
+    let callback = interaction_callback_path(click.id, &click.token);
+    repost.interaction_create(bot.ctx.clone(), Interaction::Component(click)).await.expect("Failed to handle vote");
+
+    assert_eq!(edited_buttons(&bot, &post.vote_message)[0]["label"], "Pour ✅ 1");
+    let response = bot.discord.requests_to("POST", &callback).pop().and_then(|request| request.body).expect("The vote should be answered");
+    assert_eq!(response["data"]["content"], "Ton vote a bien été pris en compte !");
+
+    // Voting again removes the vote
+    let click = ComponentInteractionBuilder::button(&yes, &vote_message, &voter).build();
+    repost.interaction_create(bot.ctx.clone(), Interaction::Component(click)).await.expect("Failed to handle vote");
+    assert_eq!(edited_buttons(&bot, &post.vote_message)[0]["label"], "Pour ✅ 0");
+}
+
+#[tokio::test]
+async fn archived_post_refuses_votes() {
+    let bot = start().await;
+    let repost = bot.load_module::<Repost>().await.expect("Failed to load repost");
+    let post = mock_post(&bot, &UserBuilder::new("author"));
+    repost.thread_create(bot.ctx.clone(), post.thread.build()).await.expect("Failed to handle new thread");
+    let no = edited_buttons(&bot, &post.vote_message)[1]["custom_id"].as_str().unwrap().to_string();
+
+    // The thread is archived before the vote
+    let archived = post.thread.clone().archived();
+    bot.discord.respond_once("GET", &format!("/channels/{}", post.thread.channel_id()), 200, archived.json());
+    let click = ComponentInteractionBuilder::button(&no, &post.vote_message, &MemberBuilder::new(&UserBuilder::new("late voter"))).build();
+    let callback = interaction_callback_path(click.id, &click.token);
+    bot.discord.clear_requests();
+    repost.interaction_create(bot.ctx.clone(), Interaction::Component(click)).await.expect("Failed to handle vote");
+
+    let response = bot.discord.requests_to("POST", &callback).pop().and_then(|request| request.body).expect("The vote should be answered");
+    assert_eq!(response["data"]["content"], "Ce thread a été archivé. tu ne peux plus voter.");
+    assert!(bot.discord.requests_to("PATCH", &format!("/channels/{}/messages/{}", post.thread.channel_id(), post.vote_message.message_id())).is_empty());
+}
+
+#[tokio::test]
+async fn thread_outside_of_linked_forums_is_ignored() {
+    let bot = start().await;
+    let repost = bot.load_module::<Repost>().await.expect("Failed to load repost");
+    let thread = ChannelBuilder::thread(ChannelBuilder::forum("other").channel_id(), &UserBuilder::new("author"), "Hors sujet");
+
+    repost.thread_create(bot.ctx.clone(), thread.build()).await.expect("Failed to handle new thread");
+    assert!(bot.discord.requests().is_empty());
+}
@@ -0,0 +1,34 @@
+use std::time::Duration;
+use serenity::all::GuildId;
+use modules::user_count::UserCount;
+use test_support::fixtures::{MemberBuilder, UserBuilder};
+use test_support::TestBot;
+use utils::module::BidibipModule;
+
+/// Activity texts sent to the gateway, waiting for the expected count of updates
+async fn activities(bot: &TestBot, count: usize) -> Vec<String> {
+    for _ in 0..100 {
+        let updates = bot.gateway.presence_updates();
+        if updates.len() >= count {
+            return updates.iter().map(|update| update["activities"][0]["state"].as_str().unwrap_or_default().to_string()).collect();
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("Expected {} presence updates, got {:?}", count, bot.gateway.presence_updates())
+}
+
+#[tokio::test]
+async fn member_count_is_displayed_in_the_activity() {
+    let bot = TestBot::start().await.expect("Failed to start test bot");
+    let user_count = bot.load_module::<UserCount>().await.expect("Failed to load user count");
+
+    let first = MemberBuilder::new(&UserBuilder::new("first"));
+    let second = MemberBuilder::new(&UserBuilder::new("second"));
+    user_count.guild_member_addition(bot.ctx.clone(), first.build()).await.expect("Failed to handle new member");
+    user_count.guild_member_addition(bot.ctx.clone(), second.build()).await.expect("Failed to handle new member");
+    // Only the members of the main guild are counted
+    user_count.guild_member_addition(bot.ctx.clone(), MemberBuilder::new(&UserBuilder::new("elsewhere")).guild(GuildId::new(9999)).build()).await.expect("Failed to handle new member");
+    user_count.guild_member_removal(bot.ctx.clone(), first.build().guild_id, first.build().user, None).await.expect("Failed to handle member removal");
+
+    assert_eq!(activities(&bot, 3).await, vec!["Nous sommes 1 membres", "Nous sommes 2 membres", "Nous sommes 1 membres"]);
+}
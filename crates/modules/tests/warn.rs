@@ -0,0 +1,130 @@
+mod common;
+
+use serde_json::json;
+use serenity::all::{ChannelId, Interaction, RoleId};
+use modules::anti_spam::AntiSpam;
+use modules::warn::{ActionType, Warn};
+use common::{anti_spam_config, button, spam};
+use test_support::fixtures::{interaction_callback_path, next_id, private_channel_json, CommandInteractionBuilder, ComponentInteractionBuilder, MemberBuilder, MessageBuilder, UserBuilder};
+use test_support::{write_module_config, TestBot, GUILD, STAFF_CHANNEL};
+use utils::module::BidibipModule;
+
+const PUBLIC_WARN_CHANNEL: ChannelId = ChannelId::new(3000);
+const MODERATION_WARN_CHANNEL: ChannelId = ChannelId::new(3001);
+const BAN_VOCAL_ROLE: RoleId = RoleId::new(3002);
+const ANTI_SPAM_CHANNEL: ChannelId = ChannelId::new(3003);
+const MUTE_ROLE: RoleId = RoleId::new(3004);
+
+async fn start() -> TestBot {
+    write_module_config::<Warn>(json!({
+        "public_warn_channel": PUBLIC_WARN_CHANNEL,
+        "moderation_warn_channel": MODERATION_WARN_CHANNEL,
+        "ban-vocal": BAN_VOCAL_ROLE,
+        "reason_templates": ["Spam", "Insultes"],
+    }));
+    write_module_config::<AntiSpam>(anti_spam_config(MUTE_ROLE, ANTI_SPAM_CHANNEL));
+    let bot = TestBot::start().await.expect("Failed to start test bot");
+    let bidibip = UserBuilder::bidibip();
+    bot.discord.respond("POST", &format!("/channels/{}/messages", MODERATION_WARN_CHANNEL), MessageBuilder::new(MODERATION_WARN_CHANNEL, &bidibip, "Sanction").json());
+    bot.discord.respond("POST", &format!("/channels/{}/messages", PUBLIC_WARN_CHANNEL), MessageBuilder::new(PUBLIC_WARN_CHANNEL, &bidibip, "Sanction").json());
+    bot
+}
+
+/// Let the warned member receive the private message
+fn mock_member(bot: &TestBot, member: &MemberBuilder) {
+    let user = UserBuilder::new("warned").id(member.build().user.id);
+    let dm_channel = private_channel_json(&user);
+    bot.discord.respond("GET", &format!("/guilds/{}/members/{}", GUILD, user.user_id()), member.json());
+    bot.discord.respond("POST", "/users/@me/channels", dm_channel.clone());
+    bot.discord.respond("POST", &format!("/channels/{}/messages", dm_channel["id"].as_str().unwrap()), MessageBuilder::new(ChannelId::new(next_id()), &UserBuilder::bidibip(), "DM").private().json());
+}
+
+#[tokio::test]
+async fn recorded_sanction_is_announced() {
+    let bot = start().await;
+    let warn = bot.load_module::<Warn>().await.expect("Failed to load warn");
+    let moderator = UserBuilder::new("moderator");
+    let user = UserBuilder::new("troll");
+    mock_member(&bot, &MemberBuilder::new(&user));
+
+    warn.record_sanction::<AntiSpam>(&bot.ctx, GUILD, &moderator.build(), &user.build(), ActionType::Kick, "Spam détecté", None).await.expect("Failed to record sanction");
+
+    let moderation = bot.discord.requests_to("POST", &format!("/channels/{}/messages", MODERATION_WARN_CHANNEL));
+    assert_eq!(moderation.len(), 1);
+    let embed = &moderation[0].body.as_ref().unwrap()["embeds"][0];
+    assert_eq!(embed["description"], "Spam détecté");
+    assert_eq!(embed["fields"][0]["value"], "Sanction appliquée par le module anti-spam");
+
+    let public = bot.discord.requests_to("POST", &format!("/channels/{}/messages", PUBLIC_WARN_CHANNEL));
+    assert_eq!(public.len(), 1);
+    assert!(public[0].body.as_ref().unwrap()["embeds"][0]["title"].as_str().unwrap().contains("a été kick par"));
+
+    // Recorded sanctions were already applied by the source module
+    assert!(bot.discord.requests_to("DELETE", &format!("/guilds/{}/members/{}", GUILD, user.user_id())).is_empty());
+}
+
+#[tokio::test]
+async fn warned_member_joining_alerts_the_staff() {
+    let bot = start().await;
+    let warn = bot.load_module::<Warn>().await.expect("Failed to load warn");
+    let user = UserBuilder::new("returning");
+    let member = MemberBuilder::new(&user);
+    mock_member(&bot, &member);
+    warn.record_sanction::<AntiSpam>(&bot.ctx, GUILD, &UserBuilder::new("moderator").build(), &user.build(), ActionType::Warn, "Insultes", None).await.expect("Failed to record sanction");
+
+    warn.guild_member_addition(bot.ctx.clone(), member.build()).await.expect("Failed to handle new member");
+    let alerts = bot.discord.requests_to("POST", &format!("/channels/{}/messages", STAFF_CHANNEL));
+    assert_eq!(alerts.len(), 1);
+    assert!(alerts[0].body.as_ref().unwrap()["content"].as_str().unwrap().contains("avec 1 warn(s) à son actif"));
+
+    // Members without warns join silently
+    bot.discord.clear_requests();
+    warn.guild_member_addition(bot.ctx.clone(), MemberBuilder::new(&UserBuilder::new("newcomer")).build()).await.expect("Failed to handle new member");
+    assert!(bot.discord.requests().is_empty());
+}
+
+#[tokio::test]
+async fn sanction_command_opens_the_modal() {
+    let bot = start().await;
+    let warn = bot.load_module::<Warn>().await.expect("Failed to load warn");
+    let target = MemberBuilder::new(&UserBuilder::new("target"));
+    bot.discord.respond("GET", &format!("/users/{}", target.build().user.id), target.json()["user"].clone());
+
+    let moderator = MemberBuilder::new(&UserBuilder::new("moderator"));
+    let command = CommandInteractionBuilder::new("sanction", ChannelId::new(next_id()), &moderator)
+        .user_option("cible", &target)
+        .string_option("action", "kick")
+        .string_option("raison", "Spam");
+    let command = command.build();
+    let callback = interaction_callback_path(command.id, &command.token);
+    warn.execute_command(bot.ctx.clone(), "sanction", command).await.expect("Failed to execute command");
+
+    let responses = bot.discord.requests_to("POST", &callback);
+    let response = responses.first().and_then(|request| request.body.clone()).expect("The command should be answered with a modal");
+    assert_eq!(response["type"], 9);
+    assert_eq!(response["data"]["title"], "kick de target");
+    assert_eq!(response["data"]["components"][0]["components"][0]["value"], "Spam");
+}
+
+#[tokio::test]
+async fn anti_spam_kick_is_recorded_in_the_history() {
+    let bot = start().await;
+    let warn = bot.load_module::<Warn>().await.expect("Failed to load warn");
+    let anti_spam = bot.load_module::<AntiSpam>().await.expect("Failed to load anti spam");
+    let spammer = UserBuilder::new("spammer");
+    let member = MemberBuilder::new(&spammer);
+    mock_member(&bot, &member);
+    let moderation_message = spam(&bot, anti_spam.as_ref(), &member, ANTI_SPAM_CHANNEL).await;
+
+    let click = ComponentInteractionBuilder::button(&button(&moderation_message, 0), &moderation_message, &MemberBuilder::new(&UserBuilder::new("moderator")));
+    anti_spam.interaction_create(bot.ctx.clone(), Interaction::Component(click.build())).await.expect("Failed to handle kick");
+
+    let announces = bot.discord.requests_to("POST", &format!("/channels/{}/messages", MODERATION_WARN_CHANNEL));
+    assert_eq!(announces.len(), 1, "The kick should be announced by the warn module");
+    let details = announces[0].body.as_ref().unwrap()["embeds"][0]["fields"][0]["value"].as_str().unwrap().to_string();
+    assert!(details.contains("Free nitro : https://scam.example"), "The spammed message should be recorded : {details}");
+    assert_eq!(details.matches("<#").count(), 3, "The spammed channels should be recorded : {details}");
+    bot.discord.clear_requests();
+    warn.guild_member_addition(bot.ctx.clone(), member.build()).await.expect("Failed to handle new member");
+    assert_eq!(bot.discord.requests_to("POST", &format!("/channels/{}/messages", STAFF_CHANNEL)).len(), 1, "The kick should be in the warn history");
+}
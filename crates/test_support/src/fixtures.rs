@@ -0,0 +1,373 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Map, Value};
+use serenity::all::{ChannelId, CommandInteraction, CommandOptionType, ComponentInteraction, GuildChannel, GuildId, InteractionId, Member, Message, MessageId, RoleId, User, UserId};
+use crate::{APPLICATION, GUILD};
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1_000_000);
+
+/// A new snowflake, so the tests sharing the storage don't use the same users or messages
+pub fn next_id() -> u64 {
+    NEXT_ID.fetch_add(1, Ordering::SeqCst)
+}
+
+/// Serenity models can't be built directly : fixtures are written as Discord json and deserialized
+fn deserialize<T: DeserializeOwned>(value: &Value) -> T {
+    match serde_json::from_value(value.clone()) {
+        Ok(model) => { model }
+        Err(err) => { panic!("Invalid fixture {} : {}", value, err) }
+    }
+}
+
+const TIMESTAMP: &str = "2025-01-01T00:00:00.000000+00:00";
+
+#[derive(Clone)]
+pub struct UserBuilder {
+    value: Value,
+}
+
+impl UserBuilder {
+    pub fn new(name: &str) -> Self {
+        Self { value: json!({"id": next_id().to_string(), "username": name, "discriminator": "0", "global_name": null, "avatar": null, "bot": false}) }
+    }
+
+    /// The bot user
+    pub fn bidibip() -> Self {
+        Self::new("Bidibip").id(UserId::new(APPLICATION.get())).bot()
+    }
+
+    pub fn id(mut self, id: UserId) -> Self {
+        self.value["id"] = json!(id.to_string());
+        self
+    }
+
+    pub fn bot(mut self) -> Self {
+        self.value["bot"] = json!(true);
+        self
+    }
+
+    pub fn user_id(&self) -> UserId {
+        UserId::new(self.value["id"].as_str().and_then(|id| id.parse().ok()).expect("Invalid user fixture id"))
+    }
+
+    pub fn json(&self) -> Value {
+        self.value.clone()
+    }
+
+    pub fn build(&self) -> User {
+        deserialize(&self.value)
+    }
+}
+
+#[derive(Clone)]
+pub struct MemberBuilder {
+    value: Value,
+}
+
+impl MemberBuilder {
+    pub fn new(user: &UserBuilder) -> Self {
+        Self { value: json!({"guild_id": GUILD.to_string(), "user": user.json(), "roles": [], "nick": null, "joined_at": TIMESTAMP, "deaf": false, "mute": false, "flags": 0}) }
+    }
+
+    pub fn guild(mut self, guild: GuildId) -> Self {
+        self.value["guild_id"] = json!(guild.to_string());
+        self
+    }
+
+    pub fn role(mut self, role: RoleId) -> Self {
+        self.value["roles"].as_array_mut().expect("Invalid member fixture").push(json!(role.to_string()));
+        self
+    }
+
+    /// Member as sent in interactions, with its computed permissions
+    fn interaction_json(&self) -> Value {
+        let mut value = self.value.clone();
+        value["permissions"] = json!("0");
+        value
+    }
+
+    pub fn json(&self) -> Value {
+        self.value.clone()
+    }
+
+    pub fn build(&self) -> Member {
+        deserialize(&self.value)
+    }
+}
+
+#[derive(Clone)]
+pub struct MessageBuilder {
+    value: Value,
+}
+
+impl MessageBuilder {
+    pub fn new(channel: ChannelId, author: &UserBuilder, content: &str) -> Self {
+        Self {
+            value: json!({
+                "id": next_id().to_string(),
+                "channel_id": channel.to_string(),
+                "guild_id": GUILD.to_string(),
+                "author": author.json(),
+                "content": content,
+                "timestamp": TIMESTAMP,
+                "edited_timestamp": null,
+                "tts": false,
+                "mention_everyone": false,
+                "mentions": [],
+                "mention_roles": [],
+                "attachments": [],
+                "embeds": [],
+                "components": [],
+                "pinned": false,
+                "type": 0,
+            })
+        }
+    }
+
+    pub fn id(mut self, id: MessageId) -> Self {
+        self.value["id"] = json!(id.to_string());
+        self
+    }
+
+    /// Message sent outside of a guild
+    pub fn private(mut self) -> Self {
+        self.value.as_object_mut().expect("Invalid message fixture").remove("guild_id");
+        self
+    }
+
+    pub fn guild(mut self, guild: GuildId) -> Self {
+        self.value["guild_id"] = json!(guild.to_string());
+        self
+    }
+
+    /// Raw Discord message components (action rows)
+    pub fn components(mut self, components: Value) -> Self {
+        self.value["components"] = components;
+        self
+    }
+
+    pub fn message_id(&self) -> MessageId {
+        MessageId::new(self.value["id"].as_str().and_then(|id| id.parse().ok()).expect("Invalid message fixture id"))
+    }
+
+    pub fn json(&self) -> Value {
+        self.value.clone()
+    }
+
+    pub fn build(&self) -> Message {
+        deserialize(&self.value)
+    }
+}
+
+#[derive(Clone)]
+pub struct ChannelBuilder {
+    value: Value,
+}
+
+impl ChannelBuilder {
+    fn new(kind: u8, name: &str) -> Self {
+        Self { value: json!({"id": next_id().to_string(), "type": kind, "guild_id": GUILD.to_string(), "name": name, "position": 0, "permission_overwrites": [], "nsfw": false, "flags": 0}) }
+    }
+
+    pub fn text(name: &str) -> Self {
+        Self::new(0, name)
+    }
+
+    pub fn forum(name: &str) -> Self {
+        Self::new(15, name)
+    }
+
+    /// Public thread (or forum post) created by the owner
+    pub fn thread(parent: ChannelId, owner: &UserBuilder, name: &str) -> Self {
+        let mut builder = Self::new(11, name);
+        builder.value["parent_id"] = json!(parent.to_string());
+        builder.value["owner_id"] = owner.json()["id"].clone();
+        builder.value["thread_metadata"] = json!({"archived": false, "auto_archive_duration": 1440, "archive_timestamp": TIMESTAMP, "locked": false});
+        builder
+    }
+
+    pub fn archived(mut self) -> Self {
+        self.value["thread_metadata"]["archived"] = json!(true);
+        self
+    }
+
+    pub fn id(mut self, id: ChannelId) -> Self {
+        self.value["id"] = json!(id.to_string());
+        self
+    }
+
+    pub fn channel_id(&self) -> ChannelId {
+        ChannelId::new(self.value["id"].as_str().and_then(|id| id.parse().ok()).expect("Invalid channel fixture id"))
+    }
+
+    pub fn json(&self) -> Value {
+        self.value.clone()
+    }
+
+    pub fn build(&self) -> GuildChannel {
+        deserialize(&self.value)
+    }
+}
+
+/// Direct message channel with the user, as returned by `POST /users/@me/channels`
+pub fn private_channel_json(user: &UserBuilder) -> Value {
+    json!({"id": next_id().to_string(), "type": 1, "recipients": [user.json()], "last_message_id": null})
+}
+
+/// Fields shared by every interaction
+fn interaction_json(kind: u8, channel: ChannelId, member: &MemberBuilder, data: Value) -> Value {
+    let member = member.interaction_json();
+    json!({
+        "id": next_id().to_string(),
+        "application_id": APPLICATION.to_string(),
+        "type": kind,
+        "data": data,
+        "guild_id": member["guild_id"],
+        "channel_id": channel.to_string(),
+        "user": member["user"],
+        "member": member,
+        "token": format!("interaction-token-{}", next_id()),
+        "version": 1,
+        "locale": "fr",
+        "guild_locale": "fr",
+        "app_permissions": "0",
+        "entitlements": [],
+        "attachment_size_limit": 8388608,
+    })
+}
+
+#[derive(Clone)]
+pub struct CommandInteractionBuilder {
+    channel: ChannelId,
+    member: MemberBuilder,
+    name: String,
+    kind: u8,
+    options: Vec<Value>,
+    subcommand: Option<String>,
+    resolved: Map<String, Value>,
+    target: Option<String>,
+}
+
+impl CommandInteractionBuilder {
+    /// Slash command
+    pub fn new(name: &str, channel: ChannelId, member: &MemberBuilder) -> Self {
+        Self { channel, member: member.clone(), name: name.to_string(), kind: 1, options: vec![], subcommand: None, resolved: Map::new(), target: None }
+    }
+
+    /// Context menu command on a user
+    pub fn user_context(name: &str, channel: ChannelId, member: &MemberBuilder, target: &MemberBuilder) -> Self {
+        let mut builder = Self::new(name, channel, member);
+        builder.kind = 2;
+        let target_user = target.json()["user"].clone();
+        builder.target = target_user["id"].as_str().map(|id| id.to_string());
+        builder.resolve_user(target_user, target.interaction_json());
+        builder
+    }
+
+    /// Options are added to this subcommand
+    pub fn subcommand(mut self, name: &str) -> Self {
+        self.subcommand = Some(name.to_string());
+        self
+    }
+
+    pub fn string_option(self, name: &str, value: &str) -> Self {
+        self.option(name, CommandOptionType::String, json!(value))
+    }
+
+    pub fn integer_option(self, name: &str, value: i64) -> Self {
+        self.option(name, CommandOptionType::Integer, json!(value))
+    }
+
+    pub fn bool_option(self, name: &str, value: bool) -> Self {
+        self.option(name, CommandOptionType::Boolean, json!(value))
+    }
+
+    pub fn user_option(mut self, name: &str, member: &MemberBuilder) -> Self {
+        let user = member.json()["user"].clone();
+        let id = user["id"].clone();
+        self.resolve_user(user, member.interaction_json());
+        self.option(name, CommandOptionType::User, id)
+    }
+
+    /// Option currently filled by the user, for autocomplete interactions
+    pub fn focused_option(mut self, name: &str, value: &str) -> Self {
+        self.options.push(json!({"name": name, "type": u8::from(CommandOptionType::String), "value": value, "focused": true}));
+        self
+    }
+
+    fn option(mut self, name: &str, kind: CommandOptionType, value: Value) -> Self {
+        self.options.push(json!({"name": name, "type": u8::from(kind), "value": value}));
+        self
+    }
+
+    fn resolve_user(&mut self, mut user: Value, mut member: Value) {
+        let id = user["id"].as_str().unwrap_or_default().to_string();
+        if let Some(member) = member.as_object_mut() {
+            member.remove("user");
+            member.remove("guild_id");
+        }
+        let users = self.resolved.entry("users").or_insert(json!({}));
+        users[&id] = user.take();
+        let members = self.resolved.entry("members").or_insert(json!({}));
+        members[&id] = member;
+    }
+
+    pub fn json(&self) -> Value {
+        let options = match &self.subcommand {
+            None => { self.options.clone() }
+            Some(subcommand) => { vec![json!({"name": subcommand, "type": u8::from(CommandOptionType::SubCommand), "options": self.options})] }
+        };
+        let mut data = json!({"id": next_id().to_string(), "name": self.name, "type": self.kind, "options": options, "resolved": self.resolved});
+        if let Some(target) = &self.target {
+            data["target_id"] = json!(target);
+        }
+        interaction_json(2, self.channel, &self.member, data)
+    }
+
+    pub fn build(&self) -> CommandInteraction {
+        deserialize(&self.json())
+    }
+
+    /// Same command, as an autocomplete request
+    pub fn build_autocomplete(&self) -> CommandInteraction {
+        let mut value = self.json();
+        value["type"] = json!(4);
+        deserialize(&value)
+    }
+}
+
+#[derive(Clone)]
+pub struct ComponentInteractionBuilder {
+    value: Value,
+}
+
+impl ComponentInteractionBuilder {
+    /// Click on a button of the given message
+    pub fn button(custom_id: &str, message: &MessageBuilder, member: &MemberBuilder) -> Self {
+        let message = message.json();
+        let channel = ChannelId::new(message["channel_id"].as_str().and_then(|id| id.parse().ok()).expect("Invalid message fixture channel"));
+        let mut value = interaction_json(3, channel, member, json!({"custom_id": custom_id, "component_type": 2}));
+        value["message"] = message;
+        Self { value }
+    }
+
+    /// Selection in a string select menu of the given message
+    pub fn select(custom_id: &str, values: &[&str], message: &MessageBuilder, member: &MemberBuilder) -> Self {
+        let mut builder = Self::button(custom_id, message, member);
+        builder.value["data"] = json!({"custom_id": custom_id, "component_type": 3, "values": values});
+        builder
+    }
+
+    pub fn json(&self) -> Value {
+        self.value.clone()
+    }
+
+    pub fn build(&self) -> ComponentInteraction {
+        deserialize(&self.value)
+    }
+}
+
+/// Route of the response to an interaction, `POST /interactions/{id}/{token}/callback`
+pub fn interaction_callback_path(id: InteractionId, token: &str) -> String {
+    format!("/interactions/{}/{}/callback", id, token)
+}
@@ -0,0 +1,68 @@
+use std::sync::{Arc, Mutex};
+use anyhow::Error;
+use serde_json::{json, Value};
+use serenity::futures::{SinkExt, StreamExt};
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::Message;
+
+/// Gateway opcodes used by the fake gateway
+const OP_HEARTBEAT: u64 = 1;
+const OP_PRESENCE_UPDATE: u64 = 3;
+const OP_HELLO: u64 = 10;
+const OP_HEARTBEAT_ACK: u64 = 11;
+
+/// Local websocket server standing for the Discord gateway.
+/// It only says hello and acknowledges the heartbeats, so the shard stays connected, and records what the bot sends.
+#[derive(Clone)]
+pub struct FakeGateway {
+    url: String,
+    frames: Arc<Mutex<Vec<Value>>>,
+}
+
+impl FakeGateway {
+    pub async fn start() -> Result<Self, Error> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let gateway = Self { url: format!("ws://{}", listener.local_addr()?), frames: Default::default() };
+        let frames = gateway.frames.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let frames = frames.clone();
+                tokio::spawn(async move {
+                    let mut socket = match tokio_tungstenite::accept_async(stream).await {
+                        Ok(socket) => { socket }
+                        Err(err) => { return eprintln!("Fake gateway handshake failed : {}", err) }
+                    };
+                    if socket.send(Message::text(json!({"op": OP_HELLO, "d": {"heartbeat_interval": 45000}}).to_string())).await.is_err() {
+                        return;
+                    }
+                    while let Some(Ok(message)) = socket.next().await {
+                        let frame: Value = match message {
+                            Message::Text(text) => { serde_json::from_str(&text).unwrap_or(Value::String(text)) }
+                            Message::Close(_) => { break; }
+                            _ => { continue; }
+                        };
+                        if frame["op"] == OP_HEARTBEAT && socket.send(Message::text(json!({"op": OP_HEARTBEAT_ACK}).to_string())).await.is_err() {
+                            break;
+                        }
+                        frames.lock().unwrap().push(frame);
+                    }
+                });
+            }
+        });
+        Ok(gateway)
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Every payload sent by the bot (identify, heartbeats, presence updates...)
+    pub fn frames(&self) -> Vec<Value> {
+        self.frames.lock().unwrap().clone()
+    }
+
+    /// Activities set with Context::set_activity, in order
+    pub fn presence_updates(&self) -> Vec<Value> {
+        self.frames().into_iter().filter(|frame| frame["op"] == OP_PRESENCE_UPDATE).map(|frame| frame["d"].clone()).collect()
+    }
+}
@@ -0,0 +1,222 @@
+use std::net::SocketAddr;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use anyhow::Error;
+use serde_json::Value;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Prefix of the Discord API routes, removed from the recorded paths
+const API_PREFIX: &str = "/api/v10";
+
+/// A request received by the mock server
+#[derive(Clone, Debug)]
+pub struct RecordedRequest {
+    pub method: String,
+    // Path without the api prefix and the query (ex: `/channels/1/messages`)
+    pub path: String,
+    pub query: Option<String>,
+    // Json body, or json payload of a multipart body (messages with attachments, forum posts)
+    pub body: Option<Value>,
+    pub raw_body: String,
+}
+
+/// A canned response. The last added route is matched first, so a test can override a route for a few requests.
+struct Route {
+    method: String,
+    path: String,
+    status: u16,
+    body: Value,
+    // Remaining uses of the route (None for unlimited)
+    remaining: Option<usize>,
+}
+
+/// Local HTTP server standing for the Discord API.
+/// Requests without a matching route get `204 No Content` for DELETE, PUT and interaction responses, and `404 Unknown` otherwise.
+/// Like Discord, a second response to the same interaction fails with the "already acknowledged" error.
+#[derive(Clone)]
+pub struct MockDiscord {
+    url: String,
+    routes: Arc<Mutex<Vec<Route>>>,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+    // Callback paths of the interactions that were answered
+    acknowledged: Arc<Mutex<HashSet<String>>>,
+}
+
+impl MockDiscord {
+    pub async fn start() -> Result<Self, Error> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let server = Self { url: format!("http://{}", listener.local_addr()?), routes: Default::default(), requests: Default::default(), acknowledged: Default::default() };
+        let handler = server.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let handler = handler.clone();
+                tokio::spawn(async move {
+                    if let Err(err) = handler.serve(stream).await {
+                        eprintln!("Mock discord connection failed : {}", err);
+                    }
+                });
+            }
+        });
+        Ok(server)
+    }
+
+    /// Address given to serenity as proxy
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Answer every request to this route with the given json
+    pub fn respond(&self, method: &str, path: &str, body: Value) {
+        self.add_route(method, path, 200, body, None);
+    }
+
+    /// Answer the next request to this route with the given status and json
+    pub fn respond_once(&self, method: &str, path: &str, status: u16, body: Value) {
+        self.add_route(method, path, status, body, Some(1));
+    }
+
+    /// Answer every request to this route with an error
+    pub fn respond_error(&self, method: &str, path: &str, status: u16) {
+        self.add_route(method, path, status, serde_json::json!({"message": "Mock error", "code": 0}), None);
+    }
+
+    fn add_route(&self, method: &str, path: &str, status: u16, body: Value, remaining: Option<usize>) {
+        self.routes.lock().unwrap().push(Route { method: method.to_uppercase(), path: path.to_string(), status, body, remaining });
+    }
+
+    /// Every request received since the start
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Requests received on the given route
+    pub fn requests_to(&self, method: &str, path: &str) -> Vec<RecordedRequest> {
+        self.requests().into_iter().filter(|request| request.method.eq_ignore_ascii_case(method) && request.path == path).collect()
+    }
+
+    /// Wait for a request sent from a spawned task. Returns None after one second.
+    pub async fn wait_for(&self, method: &str, path: &str) -> Option<RecordedRequest> {
+        for _ in 0..100 {
+            if let Some(request) = self.requests_to(method, path).pop() {
+                return Some(request);
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        None
+    }
+
+    pub fn clear_requests(&self) {
+        self.requests.lock().unwrap().clear();
+    }
+
+    /// Serve the requests of a connection until it is closed (reqwest keeps the connections alive)
+    async fn serve(&self, mut stream: TcpStream) -> Result<(), Error> {
+        let mut buffer = Vec::new();
+        loop {
+            let (request, consumed) = loop {
+                if let Some(parsed) = parse_request(&buffer)? {
+                    break parsed;
+                }
+                let mut chunk = [0u8; 4096];
+                let read = stream.read(&mut chunk).await?;
+                if read == 0 {
+                    return Ok(());
+                }
+                buffer.extend_from_slice(&chunk[..read]);
+            };
+            buffer.drain(..consumed);
+
+            let (status, body) = self.response(&request);
+            self.requests.lock().unwrap().push(request);
+            let body = match &body {
+                None => { String::new() }
+                Some(body) => { body.to_string() }
+            };
+            let response = format!("HTTP/1.1 {} {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}", status, reason(status), body.len(), body);
+            stream.write_all(response.as_bytes()).await?;
+        }
+    }
+
+    fn response(&self, request: &RecordedRequest) -> (u16, Option<Value>) {
+        let mut routes = self.routes.lock().unwrap();
+        if let Some(index) = routes.iter().rposition(|route| route.method == request.method && route.path == request.path && route.remaining != Some(0)) {
+            let route = &mut routes[index];
+            if let Some(remaining) = &mut route.remaining {
+                *remaining -= 1;
+            }
+            return (route.status, Some(route.body.clone()));
+        }
+        match request.method.as_str() {
+            "DELETE" | "PUT" => { (204, None) }
+            "POST" if request.path.starts_with("/interactions/") && request.path.ends_with("/callback") => {
+                if self.acknowledged.lock().unwrap().insert(request.path.clone()) {
+                    (204, None)
+                } else {
+                    (400, Some(serde_json::json!({"message": "Interaction has already been acknowledged.", "code": 40060})))
+                }
+            }
+            _ => { (404, Some(serde_json::json!({"message": format!("No mock route for {} {}", request.method, request.path), "code": 0}))) }
+        }
+    }
+}
+
+/// Read a full request from the buffer. Returns None if more data is needed.
+fn parse_request(buffer: &[u8]) -> Result<Option<(RecordedRequest, usize)>, Error> {
+    let mut headers = [httparse::EMPTY_HEADER; 64];
+    let mut request = httparse::Request::new(&mut headers);
+    let header_length = match request.parse(buffer)? {
+        httparse::Status::Partial => { return Ok(None) }
+        httparse::Status::Complete(length) => { length }
+    };
+    let content_length = request.headers.iter()
+        .find(|header| header.name.eq_ignore_ascii_case("content-length"))
+        .and_then(|header| std::str::from_utf8(header.value).ok()?.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+    if buffer.len() < header_length + content_length {
+        return Ok(None);
+    }
+
+    let target = request.path.unwrap_or("/");
+    let (path, query) = match target.split_once('?') {
+        None => { (target, None) }
+        Some((path, query)) => { (path, Some(query.to_string())) }
+    };
+    let raw_body = String::from_utf8_lossy(&buffer[header_length..header_length + content_length]).to_string();
+    Ok(Some((RecordedRequest {
+        method: request.method.unwrap_or("GET").to_uppercase(),
+        path: path.strip_prefix(API_PREFIX).unwrap_or(path).to_string(),
+        query,
+        body: serde_json::from_str(&raw_body).ok().or_else(|| multipart_payload(&raw_body)),
+        raw_body,
+    }, header_length + content_length)))
+}
+
+/// Read the `payload_json` part of a multipart body
+fn multipart_payload(raw_body: &str) -> Option<Value> {
+    let (_, part) = raw_body.split_once("name=\"payload_json\"")?;
+    let (_, content) = part.split_once("\r\n\r\n")?;
+    let end = content.find("\r\n--").unwrap_or(content.len());
+    serde_json::from_str(&content[..end]).ok()
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => { "OK" }
+        204 => { "No Content" }
+        400 => { "Bad Request" }
+        403 => { "Forbidden" }
+        404 => { "Not Found" }
+        _ => { "Mock" }
+    }
+}
+
+/// Send a GET request to the HTTP listener of the bot (health checks, metrics), and return the status line and the body
+pub async fn http_get(address: SocketAddr, path: &str) -> (String, String) {
+    let mut stream = TcpStream::connect(address).await.expect("Failed to connect to the HTTP listener");
+    stream.write_all(format!("GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n").as_bytes()).await.expect("Failed to send HTTP request");
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.expect("Failed to read HTTP response");
+    let (head, body) = response.split_once("\r\n\r\n").expect("Invalid HTTP response");
+    (head.lines().next().unwrap_or_default().to_string(), body.to_string())
+}
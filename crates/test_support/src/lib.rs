@@ -0,0 +1,149 @@
+//! Offline harness to test the modules without a Discord server : a mock HTTP api, a fake gateway,
+//! a Context connected to both, and fixture builders for the Discord models.
+
+pub mod http;
+pub mod gateway;
+pub mod fixtures;
+
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+use anyhow::Error;
+use serde_json::{json, Value};
+use serenity::all::{ApplicationId, Cache, ChannelId, Context, GatewayIntents, GuildId, HttpBuilder, RoleId, Shard, ShardId, ShardInfo, ShardManager, ShardManagerOptions, ShardMessenger, ShardRunner, ShardRunnerOptions};
+use serenity::prelude::TypeMap;
+use tokio::sync::{Mutex, RwLock};
+use utils::config::Config;
+use utils::global_interface::{BidibipSharedData, GlobalInterface};
+use utils::logger::DiscordLogConnector;
+use utils::module::{BidibipModule, LoadModule};
+use utils::storage::Storage;
+use crate::gateway::FakeGateway;
+use crate::http::MockDiscord;
+
+/// Ids of the test config
+pub const GUILD: GuildId = GuildId::new(1000);
+// The bot user has the same id as the application
+pub const APPLICATION: ApplicationId = ApplicationId::new(1001);
+pub const LOG_CHANNEL: ChannelId = ChannelId::new(1002);
+pub const STAFF_CHANNEL: ChannelId = ChannelId::new(1003);
+pub const SUPPORT_ROLE: RoleId = RoleId::new(1010);
+pub const MEMBER_ROLE: RoleId = RoleId::new(1011);
+pub const HELPER_ROLE: RoleId = RoleId::new(1012);
+pub const ADMINISTRATOR_ROLE: RoleId = RoleId::new(1013);
+pub const MUTE_ROLE: RoleId = RoleId::new(1014);
+
+const TOKEN: &str = "test-token";
+
+static ENVIRONMENT: OnceLock<PathBuf> = OnceLock::new();
+
+/// Write the test config in a temporary directory and initialize the global config and storage.
+/// They are shared by the tests of a binary : tests should use their own users and channels.
+pub fn init_environment() -> &'static PathBuf {
+    init_environment_with(json!({}))
+}
+
+/// Same as init_environment, with some fields of the main config replaced.
+/// The environment is shared by a whole test binary : only the first call is applied.
+pub fn init_environment_with(overrides: Value) -> &'static PathBuf {
+    ENVIRONMENT.get_or_init(|| {
+        let directory = std::env::temp_dir().join(format!("bidibip-tests-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).expect("Failed to create test directory");
+        let mut config = json!({
+            "token": TOKEN,
+            "server_id": GUILD,
+            "application_id": APPLICATION,
+            "log_directory": directory.join("logs"),
+            "module_config_directory": directory.join("modules"),
+            "disabled_modules": [],
+            "channels": {"log_channel": LOG_CHANNEL, "staff_channel": STAFF_CHANNEL},
+            "roles": {"support": SUPPORT_ROLE, "member": MEMBER_ROLE, "helper": HELPER_ROLE, "administrator": ADMINISTRATOR_ROLE, "mute": MUTE_ROLE},
+            "cache_message_size": 100,
+        });
+        if let (Some(config), Some(overrides)) = (config.as_object_mut(), overrides.as_object()) {
+            config.extend(overrides.clone());
+        }
+        let config_path = directory.join("config.json");
+        std::fs::write(&config_path, config.to_string()).expect("Failed to write test config");
+        Config::init(config_path).expect("Failed to load test config");
+        Storage::init(directory.join("modules").join("bidibip.sqlite")).expect("Failed to open test storage");
+        directory
+    })
+}
+
+/// Replace the config file of a module, before loading it
+pub fn write_module_config<Module: LoadModule<Module> + BidibipModule>(config: Value) {
+    init_environment();
+    Config::get().write_module_config_value(Module::name(), &config).expect("Failed to write module config");
+}
+
+/// A bot connected to a mock Discord : give `ctx` to the module hooks, then check the requests they sent
+pub struct TestBot {
+    pub discord: MockDiscord,
+    pub gateway: FakeGateway,
+    pub ctx: Context,
+    // Event handler of the bot, to test the dispatch of the events to the modules
+    pub interface: GlobalInterface,
+    pub shared_data: Arc<BidibipSharedData>,
+    _shard_manager: Arc<ShardManager>,
+}
+
+impl TestBot {
+    pub async fn start() -> Result<Self, Error> {
+        init_environment();
+        let discord = MockDiscord::start().await?;
+        let gateway = FakeGateway::start().await?;
+
+        let http = Arc::new(HttpBuilder::new(TOKEN).proxy(discord.url()).ratelimiter_disabled(true).application_id(APPLICATION).build());
+        let data = Arc::new(RwLock::new(TypeMap::new()));
+        let cache = Arc::new(Cache::new());
+        let ws_url = Arc::new(Mutex::new(gateway.url().to_string()));
+        let (shard_manager, _) = ShardManager::new(ShardManagerOptions {
+            data: data.clone(),
+            event_handlers: vec![],
+            raw_event_handlers: vec![],
+            framework: Arc::new(OnceLock::new()),
+            shard_index: 0,
+            shard_init: 0,
+            shard_total: 1,
+            ws_url: ws_url.clone(),
+            cache: cache.clone(),
+            http: http.clone(),
+            intents: GatewayIntents::all(),
+            presence: None,
+        });
+
+        // The runner forwards the gateway commands of the context (set_activity...) to the fake gateway
+        let shard = Shard::new(ws_url, TOKEN, ShardInfo { id: ShardId(0), total: 1 }, GatewayIntents::all(), None).await?;
+        let mut runner = ShardRunner::new(ShardRunnerOptions {
+            data: data.clone(),
+            event_handlers: vec![],
+            raw_event_handlers: vec![],
+            framework: None,
+            manager: shard_manager.clone(),
+            shard,
+            cache: cache.clone(),
+            http: http.clone(),
+        });
+        let ctx = Context { data, shard: ShardMessenger::new(&runner), shard_id: ShardId(0), http, cache };
+        tokio::spawn(async move {
+            if let Err(err) = runner.run().await {
+                eprintln!("Test shard runner stopped : {}", err);
+            }
+        });
+
+        let interface = GlobalInterface::new(Arc::new(DiscordLogConnector::default())).await?;
+        let shared_data = interface.shared_data().clone();
+        Ok(Self { discord, gateway, ctx, interface, shared_data, _shard_manager: shard_manager })
+    }
+
+    /// Load a module with its current config and register it, so the other modules can use its services
+    pub async fn load_module<Module: 'static + LoadModule<Module> + BidibipModule>(&self) -> Result<Arc<Module>, Error> {
+        let module = Module::load(&self.shared_data).await?;
+        self.shared_data.register_module(module).await;
+        match self.shared_data.service::<Module>(None).await {
+            None => { Err(Error::msg(format!("Module {} is disabled", Module::name()))) }
+            Some(module) => { Ok(module) }
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use serenity::all::{EditMember, GuildId, Http, Message, RoleId, Timestamp, User};
+use serenity::Error;
+use tracing::info;
+use crate::config::Config;
+use crate::module::{BidibipModule, LoadModule};
+use crate::utilities::{TruncateText, Username};
+
+/// Destructive Discord calls of a module : kicks, bans, role changes, message deletes and timeouts.
+/// When the module is in dry run (see `dry_run` in the config), the calls are logged instead of being sent to Discord.
+pub struct DiscordActions<'a> {
+    http: &'a Http,
+    module: &'static str,
+    dry_run: bool,
+}
+
+impl<'a> DiscordActions<'a> {
+    pub fn of<Module: LoadModule<Module> + BidibipModule>(http: &'a Http) -> Self {
+        Self { http, module: Module::name(), dry_run: Config::get().dry_run.is_dry_run(Module::name()) }
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// Returns true if the call should be skipped
+    fn intercept(&self, guild: GuildId, action: String) -> bool {
+        if self.dry_run {
+            info!("[dry run] Module {} would {} (guild {})", self.module, action, guild);
+        }
+        self.dry_run
+    }
+
+    pub async fn kick(&self, guild: GuildId, user: &User, reason: &str) -> Result<(), Error> {
+        if self.intercept(guild, format!("kick {} : {}", Username::from_user(user).full(), reason)) {
+            return Ok(());
+        }
+        guild.kick_with_reason(self.http, user.id, reason).await
+    }
+
+    pub async fn ban(&self, guild: GuildId, user: &User, delete_message_days: u8, reason: &str) -> Result<(), Error> {
+        if self.intercept(guild, format!("ban {} and delete {} day(s) of messages : {}", Username::from_user(user).full(), delete_message_days, reason)) {
+            return Ok(());
+        }
+        guild.ban_with_reason(self.http, user.id, delete_message_days, reason).await
+    }
+
+    pub async fn add_role(&self, guild: GuildId, user: &User, role: RoleId) -> Result<(), Error> {
+        if self.intercept(guild, format!("give role {} to {}", role, Username::from_user(user).full())) {
+            return Ok(());
+        }
+        self.http.add_member_role(guild, user.id, role, None).await
+    }
+
+    pub async fn remove_role(&self, guild: GuildId, user: &User, role: RoleId) -> Result<(), Error> {
+        if self.intercept(guild, format!("remove role {} from {}", role, Username::from_user(user).full())) {
+            return Ok(());
+        }
+        self.http.remove_member_role(guild, user.id, role, None).await
+    }
+
+    /// Prevent the member from talking until the given date
+    pub async fn timeout(&self, guild: GuildId, user: &User, until: Timestamp) -> Result<(), Error> {
+        if self.intercept(guild, format!("time out {} until {}", Username::from_user(user).full(), until)) {
+            return Ok(());
+        }
+        guild.edit_member(self.http, user.id, EditMember::new().disable_communication_until_datetime(until)).await.map(|_| ())
+    }
+
+    pub async fn disconnect_from_voice(&self, guild: GuildId, user: &User) -> Result<(), Error> {
+        if self.intercept(guild, format!("disconnect {} from voice", Username::from_user(user).full())) {
+            return Ok(());
+        }
+        guild.disconnect_member(self.http, user.id).await.map(|_| ())
+    }
+
+    pub async fn delete_message(&self, guild: GuildId, message: &Message) -> Result<(), Error> {
+        if self.intercept(guild, format!("delete message {} of {} : `{}`", message.link(), Username::from_user(&message.author).full(), message.content.truncate_text(200))) {
+            return Ok(());
+        }
+        message.delete(self.http).await
+    }
+}
@@ -1,16 +1,25 @@
+use std::collections::HashMap;
+use std::env;
+use std::fmt::{Debug, Display, Formatter};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::{OnceLock, RwLock};
+use std::sync::atomic::{AtomicU64, Ordering};
+use chrono::Utc;
 use anyhow::Error;
 use serde::{Deserialize, Serialize};
 use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
 use serenity::all::{ApplicationId, ChannelId, GuildId, RoleId};
 use tracing::warn;
 use crate::assert_some;
 use crate::interaction_utils::make_custom_id;
 use crate::module::{BidibipModule, LoadModule};
+use crate::validation::ConfigValidator;
+use crate::http_server::HttpConfig;
+use crate::persistence::{create_backup, list_backups, load_json, save_file, write_atomic, BackupConfig};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Roles {
     pub support: RoleId,
     pub member: RoleId,
@@ -19,12 +28,165 @@ pub struct Roles {
     pub mute: RoleId,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Channels {
     pub log_channel: ChannelId, // Where everything is printed
     pub staff_channel: ChannelId, // The channel I should use to tell something important to the moderator team
 }
 
+/// Settings of an additional guild handled by the bot
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct GuildConfig {
+    pub channels: Channels,
+    pub roles: Roles,
+    /// Modules that are not enabled on this guild
+    #[serde(default)]
+    pub disabled_modules: Vec<String>,
+}
+
+/// Guild specific part of a module config (channels, roles...). The section of the main guild is at the root
+/// of the module config, the other guilds have their own section in `guilds`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct PerGuild<T> {
+    #[serde(flatten)]
+    main: T,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    guilds: HashMap<GuildId, T>,
+}
+
+impl<T> PerGuild<T> {
+    /// Section of the given guild. Other guilds than the main one don't use the main section :
+    /// None is returned if they don't have their own section.
+    pub fn get(&self, guild: GuildId) -> Option<&T> {
+        if guild == Config::get().server_id {
+            Some(&self.main)
+        } else {
+            self.guilds.get(&guild)
+        }
+    }
+
+    /// Sections of every configured guild, the main guild first
+    pub fn iter(&self) -> impl Iterator<Item=(GuildId, &T)> {
+        std::iter::once((Config::get().server_id, &self.main)).chain(self.guilds.iter().map(|(guild, section)| (*guild, section)))
+    }
+
+    /// Check the section of each guild against the channels and roles of this guild
+    pub fn validate(&self, validator: &mut ConfigValidator, validate: impl Fn(&T, &mut ConfigValidator)) {
+        let guilds = Config::get().guild_ids();
+        for (guild, section) in self.iter() {
+            validator.check(guilds.contains(&guild), format!("`guilds.{}` : ce serveur n'est pas géré par le bot", guild));
+            validator.guild_section(guild, |validator| validate(section, validator));
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct DispatchConfig {
+    /// Maximum duration of a module event handler before it is cancelled
+    pub module_timeout_ms: u64,
+    /// Number of consecutive failures (errors, timeouts or panics) before a module is automatically disabled
+    pub max_consecutive_failures: usize,
+}
+
+impl Default for DispatchConfig {
+    fn default() -> Self {
+        Self {
+            module_timeout_ms: 60000,
+            max_consecutive_failures: 5,
+        }
+    }
+}
+
+/// Destructive Discord actions (kicks, bans, role changes, message deletes, timeouts) are only logged in dry run
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct DryRunConfig {
+    /// Dry run for every module
+    pub enabled: bool,
+    /// Modules in dry run when it is not globally enabled
+    pub modules: Vec<String>,
+}
+
+impl DryRunConfig {
+    pub fn is_dry_run(&self, module: &str) -> bool {
+        self.enabled || self.modules.iter().any(|name| name == module)
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.enabled || !self.modules.is_empty()
+    }
+}
+
+/// Rotation and retention of the log files of log_directory
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct LogRotationConfig {
+    /// Start new log files every day
+    pub daily: bool,
+    /// Start a new log file when the current one exceeds this size (0 = no limit)
+    pub max_file_size_mb: u64,
+    /// Compress the rotated log files with gzip
+    pub compress: bool,
+    /// Delete the rotated log files older than this (0 = keep them)
+    pub max_age_days: u64,
+    /// Delete the oldest rotated log files when they exceed this total size, for each log file (0 = no limit)
+    pub max_total_size_mb: u64,
+}
+
+impl Default for LogRotationConfig {
+    fn default() -> Self {
+        Self {
+            daily: true,
+            max_file_size_mb: 50,
+            compress: true,
+            max_age_days: 30,
+            max_total_size_mb: 500,
+        }
+    }
+}
+
+/// Filters of the log sinks, with EnvFilter directives (ex: "info,modules::warn=debug")
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct LoggingConfig {
+    pub stdout: String,
+    pub file: String,
+    pub error_file: String,
+    pub discord: String,
+    /// Discord logs of these targets (ex: "modules::warn") are sent to a dedicated channel instead of the log channel
+    pub channels: HashMap<String, ChannelId>,
+    /// Minimal level of the Discord logs pinging the support role ("off" to never ping)
+    pub ping_level: String,
+    /// Minimal delay between two pings in the same channel
+    pub ping_cooldown_s: u64,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            stdout: "info".to_string(),
+            file: "info".to_string(),
+            error_file: "warn".to_string(),
+            discord: "info,log=off".to_string(),
+            channels: Default::default(),
+            ping_level: "error".to_string(),
+            ping_cooldown_s: 300,
+        }
+    }
+}
+
+impl LoggingConfig {
+    /// Channel dedicated to the logs of this target, using the most specific configured target
+    pub fn channel_for(&self, target: &str) -> Option<ChannelId> {
+        self.channels.iter()
+            .filter(|(prefix, _)| target == prefix.as_str() || target.starts_with(format!("{prefix}::").as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, channel)| *channel)
+    }
+}
+
+/// Ids allocated by the previous button id allocator (buttons.json)
 #[derive(Serialize, Deserialize, Debug, Default)]
 struct ButtonIds {
     max: u64,
@@ -48,40 +210,135 @@ impl From<u64> for ButtonId {
     }
 }
 
-impl ButtonId {
-    pub fn new() -> Result<Self, Error> {
-        let mut buttons = Config::get().buttons.1.write().unwrap();
-
-        let id = if let Some(old) = buttons.free.pop() {
-            old
-        } else {
-            buttons.max += 1;
-            buttons.max
-        };
+static LAST_BUTTON_ID: AtomicU64 = AtomicU64::new(0);
 
-        fs::write(Config::get().buttons.0.clone(), serde_json::to_string(&*buttons)?)?;
-
-        Ok(Self {
-            id
-        })
+impl ButtonId {
+    /// Ids are made from the current time, so they are unique without storing any allocation.
+    /// The lower bits leave room for several ids per millisecond.
+    pub fn new() -> Self {
+        let now = (Utc::now().timestamp_millis() as u64) << 10;
+        let next = |last: u64| (last + 1).max(now);
+        let last = LAST_BUTTON_ID.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |last| Some(next(last))).unwrap_or_default();
+        Self { id: next(last) }
     }
 
     pub fn custom_id<T: BidibipModule + LoadModule<T>>(&self) -> String {
         make_custom_id::<T>(self.id.to_string().as_str(), "")
     }
 
+    /// Custom id carrying the action name, so the action can be restricted with an allow-list
+    pub fn action_custom_id<T: BidibipModule + LoadModule<T>>(&self, action: &str) -> String {
+        make_custom_id::<T>(action, self.id)
+    }
+
     pub fn raw(&self) -> u64 {
         self.id
     }
+}
+
+/// Retire the file of the previous button id allocator, and return the number of ids it had allocated.
+/// The file is renamed and kept for reference, new ids can't collide with the old ones.
+pub fn retire_legacy_button_ids() -> Result<Option<u64>, Error> {
+    let path = &Config::get().legacy_button_ids;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let buttons: ButtonIds = load_json(path)?;
+    fs::rename(path, path.with_extension("json.old"))?;
+    Ok(Some(buttons.max.saturating_sub(buttons.free.len() as u64)))
+}
 
-    pub fn free(&mut self) -> Result<(), Error> {
-        let mut buttons = Config::get().buttons.1.write().unwrap();
+/// A value that should never be printed (the Discord token). Debug and Display are redacted.
+#[derive(Serialize, Clone, Default)]
+#[serde(transparent)]
+pub struct Secret(String);
 
-        buttons.free.push(self.id);
-        self.id = 0;
+impl Secret {
+    pub fn expose(&self) -> &str {
+        self.0.as_str()
+    }
+}
 
-        fs::write(Config::get().buttons.0.clone(), serde_json::to_string(&*buttons)?)?;
-        Ok(())
+impl From<&str> for Secret {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // A missing value is allowed, it can be provided by the environment or the secrets file
+        Ok(Self(Option::<String>::deserialize(deserializer)?.unwrap_or_default()))
+    }
+}
+
+impl Debug for Secret {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("[redacted]")
+    }
+}
+
+impl Display for Secret {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("[redacted]")
+    }
+}
+
+/// Prefix of the environment variables overriding the config. Nested fields are separated with a double underscore
+/// (ex : BIDIBIP_CHANNELS__LOG_CHANNEL)
+const ENV_PREFIX: &str = "BIDIBIP_";
+/// Path of the secrets file (json object layered over the config file, default is secrets.json next to the config file)
+const SECRETS_FILE_ENV: &str = "BIDIBIP_SECRETS_FILE";
+
+/// Recursively replace the values of `target` with the ones of `overrides`
+fn merge_json(target: &mut Value, overrides: Value) {
+    match (target, overrides) {
+        (Value::Object(target), Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                merge_json(target.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (target, overrides) => { *target = overrides; }
+    }
+}
+
+/// Build a json object from the BIDIBIP_* environment variables
+fn env_overrides(vars: impl Iterator<Item=(String, String)>) -> Value {
+    let mut overrides = Value::Object(Map::new());
+    for (key, raw) in vars {
+        let path = match key.strip_prefix(ENV_PREFIX) {
+            // Empty variables are ignored (unset values in docker compose)
+            Some(path) if key != SECRETS_FILE_ENV && !path.is_empty() && !raw.is_empty() => { path.to_lowercase() }
+            _ => { continue; }
+        };
+        // Numbers, booleans and arrays are written as json, anything else is a string
+        let mut value = serde_json::from_str::<Value>(&raw).unwrap_or(Value::String(raw));
+        for field in path.split("__").collect::<Vec<_>>().into_iter().rev() {
+            let mut object = Map::new();
+            object.insert(field.to_string(), value);
+            value = Value::Object(object);
+        }
+        merge_json(&mut overrides, value);
+    }
+    overrides
+}
+
+/// Sections renamed since they were introduced : the values written with the old name (in the files or the
+/// environment variables) are moved under the new one, the new name wins when both are set.
+const RENAMED_SECTIONS: [(&str, &str); 1] = [("metrics", "http")];
+
+fn rename_legacy_sections(config: &mut Value) {
+    let config = match config {
+        Value::Object(config) => { config }
+        _ => { return; }
+    };
+    for (legacy, name) in RENAMED_SECTIONS {
+        if let Some(mut section) = config.remove(legacy) {
+            if let Some(current) = config.remove(name) {
+                merge_json(&mut section, current);
+            }
+            config.insert(name.to_string(), section);
+        }
     }
 }
 
@@ -89,28 +346,45 @@ static GLOBAL_CONFIG: OnceLock<Config> = OnceLock::new();
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Config {
-    pub token: String,
+    pub token: Secret,
     pub server_id: GuildId,
     pub application_id: ApplicationId,
     pub log_directory: PathBuf,
-    pub button_id_config: PathBuf,
+    #[serde(default)]
+    pub log_rotation: LogRotationConfig,
     pub module_config_directory: PathBuf,
     pub disabled_modules: Vec<String>,
     pub channels: Channels,
     pub roles: Roles,
     pub cache_message_size: usize,
+    #[serde(default)]
+    pub dispatch: DispatchConfig,
+    #[serde(default)]
+    pub backups: BackupConfig,
+    #[serde(default)]
+    pub dry_run: DryRunConfig,
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    #[serde(default)]
+    pub http: HttpConfig,
+    /// Other guilds handled by the bot. The main guild is server_id, configured with channels and roles.
+    #[serde(default)]
+    pub guilds: HashMap<GuildId, GuildConfig>,
+    #[serde(skip_serializing, skip_deserializing)]
+    legacy_button_ids: PathBuf,
+    /// Last known content of each module config file (loaded or written by the bot)
     #[serde(skip_serializing, skip_deserializing)]
-    buttons: (PathBuf, RwLock<ButtonIds>),
+    module_config_contents: RwLock<HashMap<PathBuf, String>>,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
-            token: "PLEASE FILL APP TOKEN FIRST".to_string(),
+            token: Secret::from("PLEASE FILL APP TOKEN FIRST"),
             server_id: GuildId::default(),
             application_id: ApplicationId::default(),
             log_directory: PathBuf::from("saved/logs"),
-            button_id_config: PathBuf::from("button/buttons.json"),
+            log_rotation: Default::default(),
             module_config_directory: PathBuf::from("saved/config"),
             disabled_modules: vec![],
             channels: Channels {
@@ -125,43 +399,102 @@ impl Default for Config {
                 mute: RoleId::default(),
             },
             cache_message_size: 10000,
-            buttons: Default::default(),
+            dispatch: Default::default(),
+            backups: Default::default(),
+            dry_run: Default::default(),
+            logging: Default::default(),
+            http: Default::default(),
+            guilds: Default::default(),
+            legacy_button_ids: Default::default(),
+            module_config_contents: Default::default(),
         }
     }
 }
 
 impl Config {
+    /// Load the config file, then apply the secrets file and the BIDIBIP_* environment variables over it
     pub fn init(path: PathBuf) -> Result<(), Error> {
         if path.exists() {
-            let mut config: Config = serde_json::from_str(&fs::read_to_string(&path)?)?;
-
-            assert_ne!(config.application_id, 0, "Invalid application id in config");
-            assert_ne!(config.server_id, 0, "Invalid server id in config");
-            assert_ne!(config.roles.support, 0, "Invalid helper role id in config");
-            assert_ne!(config.roles.member, 0, "Invalid member role id in config");
-            assert_ne!(config.roles.helper, 0, "Invalid helper role id in config");
-            assert_ne!(config.roles.administrator, 0, "Invalid administrator role id in config");
-            assert_ne!(config.roles.mute, 0, "Invalid helper role id in config");
-            assert_ne!(config.channels.staff_channel, 0, "Invalid staff channel id in config");
-            assert_ne!(config.channels.log_channel, 0, "Invalid staff channel id in config");
+            let mut value: Value = load_json(&path)?;
 
-            let parent = assert_some!(path.parent(), "Failed to get parent path")?;
-            let buttons_path = Path::join(parent, "buttons.json");
-            if !buttons_path.exists() {
-                fs::write(buttons_path.clone(), serde_json::to_string_pretty(&ButtonIds::default())?)?;
+            let secrets_file = match env::var(SECRETS_FILE_ENV) {
+                Ok(secrets_file) => { Some(PathBuf::from(secrets_file)) }
+                Err(_) => {
+                    let default = assert_some!(path.parent(), "Failed to get parent path")?.join("secrets.json");
+                    if default.exists() { Some(default) } else { None }
+                }
             };
-            config.buttons = (buttons_path.to_path_buf(), serde_json::from_str(&fs::read_to_string(&buttons_path)?)?);
+            if let Some(secrets_file) = secrets_file {
+                let secrets: Value = serde_json::from_str(&fs::read_to_string(&secrets_file).map_err(|err| Error::msg(format!("Failed to read secrets file {} : {}", secrets_file.display(), err)))?)?;
+                merge_json(&mut value, secrets);
+            }
+            merge_json(&mut value, env_overrides(env::vars()));
+            rename_legacy_sections(&mut value);
+
+            let mut config: Config = serde_json::from_value(value)?;
+
+            // Other ids are checked once connected (see Config::validate)
+            if config.application_id == 0 {
+                return Err(Error::msg("Invalid application id in config"));
+            }
+            if config.server_id == 0 {
+                return Err(Error::msg("Invalid server id in config"));
+            }
+
+            let parent = assert_some!(path.parent(), "Failed to get parent path")?;
+            config.legacy_button_ids = Path::join(parent, "buttons.json");
 
             #[allow(unused)]
             GLOBAL_CONFIG.set(config);
 
             Ok(())
         } else {
-            fs::write(path.clone(), serde_json::to_string_pretty(&Config::default())?)?;
+            write_atomic(&path, serde_json::to_string_pretty(&Config::default())?.as_bytes())?;
             Err(Error::msg(format!("Created a new config file at {}. Please fill in information first", path.to_str().unwrap())))
         }
     }
 
+    /// Check the channels and roles configured for the given guild
+    pub fn validate(&self, guild: GuildId, validator: &mut ConfigValidator) {
+        let channels = self.guild_channels(guild);
+        validator.channel("channels.log_channel", channels.log_channel);
+        validator.channel("channels.staff_channel", channels.staff_channel);
+        let roles = self.guild_roles(guild);
+        validator.role("roles.support", roles.support);
+        validator.role("roles.member", roles.member);
+        validator.role("roles.helper", roles.helper);
+        validator.role("roles.administrator", roles.administrator);
+        validator.role("roles.mute", roles.mute);
+    }
+
+    /// Every guild handled by the bot, main guild first
+    pub fn guild_ids(&self) -> Vec<GuildId> {
+        let mut guilds: Vec<GuildId> = self.guilds.keys().filter(|guild| **guild != self.server_id).cloned().collect();
+        guilds.sort();
+        guilds.insert(0, self.server_id);
+        guilds
+    }
+
+    pub fn is_handled_guild(&self, guild: GuildId) -> bool {
+        guild == self.server_id || self.guilds.contains_key(&guild)
+    }
+
+    /// Channels of the given guild (the main guild channels are used for unknown guilds)
+    pub fn guild_channels(&self, guild: GuildId) -> &Channels {
+        match self.guilds.get(&guild) {
+            Some(config) if guild != self.server_id => { &config.channels }
+            _ => { &self.channels }
+        }
+    }
+
+    /// Roles of the given guild (the main guild roles are used for unknown guilds)
+    pub fn guild_roles(&self, guild: GuildId) -> &Roles {
+        match self.guilds.get(&guild) {
+            Some(config) if guild != self.server_id => { &config.roles }
+            _ => { &self.roles }
+        }
+    }
+
     pub fn get() -> &'static Self {
         match GLOBAL_CONFIG.get() {
             None => { panic!("Global config have not been initialized using GLOBAL_CONFIG::init(path)") }
@@ -169,25 +502,187 @@ impl Config {
         }
     }
 
+    pub fn module_config_path<Module: LoadModule<Module> + BidibipModule>(&self) -> PathBuf {
+        self.module_config_file(Module::name())
+    }
+
+    fn module_config_file(&self, module: &str) -> PathBuf {
+        self.module_config_directory.join(format!("{}_config.json", module))
+    }
+
+    /// Raw content of a module config file (used by the config editor)
+    pub fn read_module_config_value(&self, module: &str) -> Result<Value, Error> {
+        Ok(serde_json::from_str(&fs::read_to_string(self.module_config_file(module))?)?)
+    }
+
+    /// Replace the content of a module config file. The module config should be reloaded after that.
+    pub fn write_module_config_value(&self, module: &str, value: &Value) -> Result<(), Error> {
+        let config_file = self.module_config_file(module);
+        let content = serde_json::to_string_pretty(value)?;
+        save_file(&config_file, content.as_bytes(), &self.backups)?;
+        self.set_module_config_content(&config_file, content);
+        Ok(())
+    }
+
     pub fn load_module_config<Module: LoadModule<Module> + BidibipModule, Config: Serialize + DeserializeOwned + Default>(&self) -> Result<Config, Error> {
         fs::create_dir_all(&self.module_config_directory)?;
 
-        let config_file = self.module_config_directory.join(format!("{}_config.json", Module::name()));
+        let config_file = self.module_config_path::<Module>();
 
-        if !fs::exists(&config_file)? {
+        if !fs::exists(&config_file)? && list_backups(&config_file)?.is_empty() {
             // Create log files and channels
-            fs::write(&config_file, serde_json::to_string_pretty(&Config::default())?)?;
+            write_atomic(&config_file, versioned_json::<Module, Config>(&Config::default())?.as_bytes())?;
             warn!("Initialized config file for module {} to {config_file:?}", Module::name());
         }
 
-        Ok(serde_json::from_str(&fs::read_to_string(&config_file)?)?)
+        if let Ok(content) = fs::read_to_string(&config_file) {
+            self.set_module_config_content(&config_file, content);
+        }
+        load_json(&config_file)
+    }
+
+    /// Read the module config file again. Unlike load_module_config(), an invalid file is an error instead of falling back to a backup.
+    pub fn read_module_config<Module: LoadModule<Module> + BidibipModule, Config: DeserializeOwned>(&self) -> Result<Config, Error> {
+        let config_file = self.module_config_path::<Module>();
+        let content = fs::read_to_string(&config_file)?;
+        let config = serde_json::from_str(&content)?;
+        self.set_module_config_content(&config_file, content);
+        Ok(config)
     }
 
     pub fn save_module_config<Module: LoadModule<Module> + BidibipModule, Config: Serialize>(&self, config: &Config) -> Result<(), Error> {
         fs::create_dir_all(&self.module_config_directory)?;
 
-        let config_file = self.module_config_directory.join(format!("{}_config.json", Module::name()));
-        fs::write(&config_file, serde_json::to_string_pretty(config)?)?;
+        let config_file = self.module_config_path::<Module>();
+        let content = versioned_json::<Module, Config>(config)?;
+        save_file(&config_file, content.as_bytes(), &self.backups)?;
+        self.set_module_config_content(&config_file, content);
+        Ok(())
+    }
+
+    /// Check if the file content differs from the last version loaded or written by the bot
+    pub fn module_config_changed(&self, path: &Path) -> bool {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => { content }
+            Err(_) => { return false; }
+        };
+        match self.module_config_contents.read() {
+            Ok(contents) => { contents.get(path) != Some(&content) }
+            Err(_) => { true }
+        }
+    }
+
+    fn set_module_config_content(&self, path: &Path, content: String) {
+        if let Ok(mut contents) = self.module_config_contents.write() {
+            contents.insert(path.to_path_buf(), content);
+        }
+    }
+}
+
+const SCHEMA_VERSION_KEY: &str = "schema_version";
+
+/// Serialize a module config, tagged with the current schema version of the module
+fn versioned_json<Module: LoadModule<Module> + BidibipModule, Config: Serialize>(config: &Config) -> Result<String, Error> {
+    let mut value = serde_json::to_value(config)?;
+    if let Value::Object(map) = &mut value {
+        map.insert(SCHEMA_VERSION_KEY.to_string(), Value::from(Module::migrations().len()));
+    }
+    Ok(serde_json::to_string_pretty(&value)?)
+}
+
+/// Upgrade step of a module config. The step registered at index N upgrades the config from version N to N + 1.
+pub struct ConfigMigration {
+    pub description: &'static str,
+    pub upgrade: fn(&mut Map<String, Value>) -> Result<(), Error>,
+}
+
+impl ConfigMigration {
+    pub fn new(description: &'static str, upgrade: fn(&mut Map<String, Value>) -> Result<(), Error>) -> Self {
+        Self { description, upgrade }
+    }
+}
+
+/// Ordered config migrations of every module
+#[derive(Default)]
+pub struct MigrationRegistry {
+    modules: Vec<(&'static str, Vec<ConfigMigration>)>,
+}
+
+impl MigrationRegistry {
+    pub fn register<Module: LoadModule<Module> + BidibipModule>(&mut self) {
+        self.modules.push((Module::name(), Module::migrations()));
+    }
+
+    /// Upgrade the module config files to their latest version. A backup of each file is written before it is modified.
+    pub fn run(&self) -> MigrationReport {
+        let mut report = MigrationReport::default();
+        for (module, migrations) in &self.modules {
+            let config_file = Config::get().module_config_directory.join(format!("{}_config.json", module));
+            if !config_file.exists() {
+                continue;
+            }
+            match Self::migrate_file(&config_file, migrations) {
+                Ok(Some((from, backup))) => {
+                    report.entries.push(format!("{} : {} -> {} (sauvegarde : {})", module, from, migrations.len(), backup.display()));
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    report.failed = true;
+                    report.entries.push(format!("{} : échec de la migration : {}", module, err));
+                }
+            }
+        }
+        report
+    }
+
+    /// Returns the initial version and the backup path if the file was upgraded
+    fn migrate_file(config_file: &Path, migrations: &[ConfigMigration]) -> Result<Option<(usize, PathBuf)>, Error> {
+        let mut value: Value = load_json(config_file)?;
+        let map = assert_some!(value.as_object_mut(), "Config is not a json object")?;
+        let version = match map.get(SCHEMA_VERSION_KEY) {
+            None => { 0 }
+            Some(version) => { assert_some!(version.as_u64(), "Invalid schema version")? as usize }
+        };
+        if version > migrations.len() {
+            return Err(Error::msg(format!("Config version {} is newer than the supported version {}", version, migrations.len())));
+        }
+        if version == migrations.len() {
+            return Ok(None);
+        }
+
+        let backup = create_backup(config_file, &Config::get().backups)?;
+        for (index, migration) in migrations.iter().enumerate().skip(version) {
+            if let Err(err) = (migration.upgrade)(map) {
+                return Err(Error::msg(format!("step {} -> {} ({}) : {}", index, index + 1, migration.description, err)));
+            }
+        }
+        map.insert(SCHEMA_VERSION_KEY.to_string(), Value::from(migrations.len()));
+        write_atomic(config_file, serde_json::to_string_pretty(&value)?.as_bytes())?;
+        Ok(Some((version, backup)))
+    }
+}
+
+#[derive(Default)]
+pub struct MigrationReport {
+    entries: Vec<String>,
+    failed: bool,
+}
+
+impl MigrationReport {
+    pub fn failed(&self) -> bool {
+        self.failed
+    }
+}
+
+impl Display for MigrationReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.entries.is_empty() {
+            return f.write_str("Aucune migration de configuration en attente");
+        }
+        write!(f, "Migrations de configuration :")?;
+        for entry in &self.entries {
+            write!(f, "\n- {}", entry)?;
+        }
         Ok(())
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,129 @@
+use anyhow::Error;
+use serde_json::{Map, Value};
+
+/// Fields that can't be edited from Discord
+const RESERVED_FIELDS: [&str; 1] = ["schema_version"];
+
+/// Get the value at the given dot separated path (ex: `forums.1234.vote`)
+pub fn get_field<'a>(config: &'a Value, path: &str) -> Option<&'a Value> {
+    let mut value = config;
+    for field in path.split('.') {
+        value = match value {
+            Value::Object(map) => { map.get(field)? }
+            Value::Array(array) => { array.get(field.parse::<usize>().ok()?)? }
+            _ => { return None }
+        };
+    }
+    Some(value)
+}
+
+/// Replace the value at the given path, and return the previous one.
+/// The parent of the field should exist, new keys can only be added to objects.
+pub fn set_field(config: &mut Value, path: &str, new_value: Value) -> Result<Option<Value>, Error> {
+    if RESERVED_FIELDS.contains(&path) {
+        return Err(Error::msg(format!("Le champ `{}` ne peut pas être modifié", path)));
+    }
+    let (parent, field) = match path.rsplit_once('.') {
+        None => { (config, path) }
+        Some((parent, field)) => {
+            match get_field_mut(config, parent) {
+                None => { return Err(Error::msg(format!("Le champ `{}` n'existe pas", parent))) }
+                Some(value) => { (value, field) }
+            }
+        }
+    };
+    match parent {
+        Value::Object(map) => { Ok(map.insert(field.to_string(), new_value)) }
+        Value::Array(array) => {
+            match field.parse::<usize>().ok().and_then(|index| array.get_mut(index)) {
+                None => { Err(Error::msg(format!("L'index `{}` n'existe pas", field))) }
+                Some(value) => { Ok(Some(std::mem::replace(value, new_value))) }
+            }
+        }
+        _ => { Err(Error::msg(format!("Le champ `{}` ne contient pas de sous-champs", path))) }
+    }
+}
+
+/// Remove the field at the given path from its parent object, and return its value
+pub fn remove_field(config: &mut Value, path: &str) -> Option<Value> {
+    let (parent, field) = match path.rsplit_once('.') {
+        None => { (config, path) }
+        Some((parent, field)) => { (get_field_mut(config, parent)?, field) }
+    };
+    match parent {
+        Value::Object(map) => { map.remove(field) }
+        _ => { None }
+    }
+}
+
+fn get_field_mut<'a>(config: &'a mut Value, path: &str) -> Option<&'a mut Value> {
+    let mut value = config;
+    for field in path.split('.') {
+        value = match value {
+            Value::Object(map) => { map.get_mut(field)? }
+            Value::Array(array) => { array.get_mut(field.parse::<usize>().ok()?)? }
+            _ => { return None }
+        };
+    }
+    Some(value)
+}
+
+/// Paths of every editable field, sorted. Lists are considered as a single field.
+pub fn field_paths(config: &Value) -> Vec<String> {
+    let mut paths = vec![];
+    if let Value::Object(map) = config {
+        collect_paths(map, "", &mut paths);
+    }
+    paths.retain(|path| !RESERVED_FIELDS.contains(&path.as_str()));
+    paths.sort();
+    paths
+}
+
+fn collect_paths(map: &Map<String, Value>, prefix: &str, paths: &mut Vec<String>) {
+    for (key, value) in map {
+        let path = format!("{}{}", prefix, key);
+        match value {
+            Value::Object(children) if !children.is_empty() => { collect_paths(children, format!("{}.", path).as_str(), paths) }
+            _ => { paths.push(path) }
+        }
+    }
+}
+
+/// Read a value written by a user : json values are kept as is, mentions are converted to ids and anything else is a string.
+/// If the current value is a list, comma separated values are read as a list.
+pub fn parse_text_value(text: &str, current: Option<&Value>) -> Value {
+    let text = text.trim();
+    if let Some(Value::Array(_)) = current {
+        if !text.starts_with('[') {
+            return Value::Array(text.split(',').map(str::trim).filter(|item| !item.is_empty()).map(parse_single_value).collect());
+        }
+    }
+    parse_single_value(text)
+}
+
+fn parse_single_value(text: &str) -> Value {
+    // Channel (<#id>), role (<@&id>) or user (<@id>) mentions
+    if let Some(mention) = text.strip_prefix('<').and_then(|text| text.strip_suffix('>')) {
+        let id = mention.trim_start_matches(['#', '@', '&', '!']);
+        if !id.is_empty() && id.chars().all(|c| c.is_ascii_digit()) {
+            return Value::String(id.to_string());
+        }
+    }
+    serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.to_string()))
+}
+
+/// Add a value to a list field (nothing is done if it's already in the list), or remove it
+pub fn edit_list(current: Option<&Value>, value: Value, remove: bool) -> Result<Value, Error> {
+    let mut list = match current {
+        Some(Value::Array(list)) => { list.clone() }
+        _ => { return Err(Error::msg("Ce champ n'est pas une liste")) }
+    };
+    // Ids can be stored as numbers or strings
+    let same = |item: &Value| { item == &value || item.to_string().trim_matches('"') == value.to_string().trim_matches('"') };
+    if remove {
+        list.retain(|item| !same(item));
+    } else if !list.iter().any(same) {
+        list.push(value);
+    }
+    Ok(Value::Array(list))
+}
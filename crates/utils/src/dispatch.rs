@@ -0,0 +1,284 @@
+use std::fmt::{Display, Formatter};
+use bitflags::bitflags;
+use serenity::all::{ChannelId, CreateInteractionResponse, CreateInteractionResponseFollowup, CreateInteractionResponseMessage, GuildId, Http, HttpError, Interaction, UserId};
+use tokio::task::JoinError;
+use tracing::debug;
+use crate::error::BidibipError;
+
+/// Kind of event forwarded to the modules
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    ChannelCreate,
+    ChannelDelete,
+    GuildAuditLogEntryCreate,
+    GuildBanAddition,
+    GuildBanRemoval,
+    GuildMemberAddition,
+    GuildMemberRemoval,
+    GuildMemberUpdate,
+    Message,
+    MessageDelete,
+    MessageDeleteBulk,
+    MessageUpdate,
+    Ready,
+    InteractionCreate,
+    Command,
+    Autocomplete,
+    ThreadCreate,
+    ThreadDelete,
+    // A job of the scheduler (see BidibipModule::run_job)
+    Job,
+}
+
+bitflags! {
+    /// Set of events a module wants to receive
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub struct ModuleEvents: u32 {
+        const CHANNEL_CREATE = 1 << 0;
+        const CHANNEL_DELETE = 1 << 1;
+        const GUILD_AUDIT_LOG_ENTRY_CREATE = 1 << 2;
+        const GUILD_BAN_ADDITION = 1 << 3;
+        const GUILD_BAN_REMOVAL = 1 << 4;
+        const GUILD_MEMBER_ADDITION = 1 << 5;
+        const GUILD_MEMBER_REMOVAL = 1 << 6;
+        const GUILD_MEMBER_UPDATE = 1 << 7;
+        const MESSAGE = 1 << 8;
+        const MESSAGE_DELETE = 1 << 9;
+        const MESSAGE_DELETE_BULK = 1 << 10;
+        const MESSAGE_UPDATE = 1 << 11;
+        const READY = 1 << 12;
+        const INTERACTION_CREATE = 1 << 13;
+        const THREAD_CREATE = 1 << 14;
+        const THREAD_DELETE = 1 << 15;
+    }
+}
+
+impl ModuleEvents {
+    /// Event kinds contained in this set
+    pub fn kinds(&self) -> Vec<EventKind> {
+        EventKind::HOOKS.into_iter().filter(|kind| self.contains(kind.flag())).collect()
+    }
+}
+
+impl EventKind {
+    /// Every event kind that is forwarded to a BidibipModule hook
+    pub const HOOKS: [EventKind; 16] = [
+        EventKind::ChannelCreate,
+        EventKind::ChannelDelete,
+        EventKind::GuildAuditLogEntryCreate,
+        EventKind::GuildBanAddition,
+        EventKind::GuildBanRemoval,
+        EventKind::GuildMemberAddition,
+        EventKind::GuildMemberRemoval,
+        EventKind::GuildMemberUpdate,
+        EventKind::Message,
+        EventKind::MessageDelete,
+        EventKind::MessageDeleteBulk,
+        EventKind::MessageUpdate,
+        EventKind::Ready,
+        EventKind::InteractionCreate,
+        EventKind::ThreadCreate,
+        EventKind::ThreadDelete,
+    ];
+
+    /// Subscription flag of this event. Commands and autocompletions are routed by name and don't need any subscription.
+    pub fn flag(&self) -> ModuleEvents {
+        match self {
+            EventKind::ChannelCreate => { ModuleEvents::CHANNEL_CREATE }
+            EventKind::ChannelDelete => { ModuleEvents::CHANNEL_DELETE }
+            EventKind::GuildAuditLogEntryCreate => { ModuleEvents::GUILD_AUDIT_LOG_ENTRY_CREATE }
+            EventKind::GuildBanAddition => { ModuleEvents::GUILD_BAN_ADDITION }
+            EventKind::GuildBanRemoval => { ModuleEvents::GUILD_BAN_REMOVAL }
+            EventKind::GuildMemberAddition => { ModuleEvents::GUILD_MEMBER_ADDITION }
+            EventKind::GuildMemberRemoval => { ModuleEvents::GUILD_MEMBER_REMOVAL }
+            EventKind::GuildMemberUpdate => { ModuleEvents::GUILD_MEMBER_UPDATE }
+            EventKind::Message => { ModuleEvents::MESSAGE }
+            EventKind::MessageDelete => { ModuleEvents::MESSAGE_DELETE }
+            EventKind::MessageDeleteBulk => { ModuleEvents::MESSAGE_DELETE_BULK }
+            EventKind::MessageUpdate => { ModuleEvents::MESSAGE_UPDATE }
+            EventKind::Ready => { ModuleEvents::READY }
+            EventKind::InteractionCreate => { ModuleEvents::INTERACTION_CREATE }
+            EventKind::Command => { ModuleEvents::empty() }
+            EventKind::Autocomplete => { ModuleEvents::empty() }
+            EventKind::ThreadCreate => { ModuleEvents::THREAD_CREATE }
+            EventKind::ThreadDelete => { ModuleEvents::THREAD_DELETE }
+            EventKind::Job => { ModuleEvents::empty() }
+        }
+    }
+}
+
+impl Display for EventKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            EventKind::ChannelCreate => { "channel_create" }
+            EventKind::ChannelDelete => { "channel_delete" }
+            EventKind::GuildAuditLogEntryCreate => { "guild_audit_log_entry_create" }
+            EventKind::GuildBanAddition => { "guild_ban_addition" }
+            EventKind::GuildBanRemoval => { "guild_ban_removal" }
+            EventKind::GuildMemberAddition => { "guild_member_addition" }
+            EventKind::GuildMemberRemoval => { "guild_member_removal" }
+            EventKind::GuildMemberUpdate => { "guild_member_update" }
+            EventKind::Message => { "message" }
+            EventKind::MessageDelete => { "message_delete" }
+            EventKind::MessageDeleteBulk => { "message_delete_bulk" }
+            EventKind::MessageUpdate => { "message_update" }
+            EventKind::Ready => { "ready" }
+            EventKind::InteractionCreate => { "interaction_create" }
+            EventKind::Command => { "execute_command" }
+            EventKind::Autocomplete => { "autocomplete" }
+            EventKind::ThreadCreate => { "thread_create" }
+            EventKind::ThreadDelete => { "thread_delete" }
+            EventKind::Job => { "run_job" }
+        })
+    }
+}
+
+/// Describe the event a module hook was called for
+#[derive(Clone, Debug)]
+pub struct EventContext {
+    pub kind: EventKind,
+    pub guild: Option<GuildId>,
+    pub user: Option<UserId>,
+    pub channel: Option<ChannelId>,
+}
+
+impl EventContext {
+    pub fn new(kind: EventKind) -> Self {
+        Self {
+            kind,
+            guild: None,
+            user: None,
+            channel: None,
+        }
+    }
+
+    /// Guild the event comes from. Modules that are not enabled on this guild don't receive the event.
+    pub fn guild(mut self, guild: Option<GuildId>) -> Self {
+        self.guild = guild;
+        self
+    }
+
+    pub fn user(mut self, user: UserId) -> Self {
+        self.user = Some(user);
+        self
+    }
+
+    pub fn channel(mut self, channel: ChannelId) -> Self {
+        self.channel = Some(channel);
+        self
+    }
+
+    /// Build the context of an interaction (guild, user and channel are retrieved from the interaction)
+    pub fn from_interaction(kind: EventKind, interaction: &Interaction) -> Self {
+        let (guild, user, channel) = match interaction {
+            Interaction::Command(command) => { (command.guild_id, Some(command.user.id), Some(command.channel_id)) }
+            Interaction::Autocomplete(command) => { (command.guild_id, Some(command.user.id), Some(command.channel_id)) }
+            Interaction::Component(component) => { (component.guild_id, Some(component.user.id), Some(component.channel_id)) }
+            Interaction::Modal(modal) => { (modal.guild_id, Some(modal.user.id), Some(modal.channel_id)) }
+            _ => { (None, None, None) }
+        };
+        Self { kind, guild, user, channel }
+    }
+}
+
+impl Display for EventContext {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if let Some(guild) = self.guild {
+            write!(f, " (guild {})", guild)?;
+        }
+        if let Some(user) = self.user {
+            write!(f, " (user {})", user)?;
+        }
+        if let Some(channel) = self.channel {
+            write!(f, " (channel {})", channel)?;
+        }
+        Ok(())
+    }
+}
+
+/// An error returned by a module while handling an event
+pub struct ModuleError {
+    pub module: String,
+    pub event: EventContext,
+    pub error: BidibipError,
+}
+
+impl Display for ModuleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Module {} failed to handle {} : {}", self.module, self.event, self.error)
+    }
+}
+
+/// Error of a module task that did not finish : panicked or cancelled
+pub(crate) fn task_error(err: JoinError) -> BidibipError {
+    if !err.is_panic() {
+        return BidibipError::msg("task was cancelled");
+    }
+    let payload = err.into_panic();
+    let message = if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic payload".to_string()
+    };
+    BidibipError::msg(format!("panicked : {}", message))
+}
+
+/// Tell the user they are not in the allow-list of the command or action
+pub async fn respond_denied(http: &Http, interaction: &Interaction) {
+    let response = CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+        .ephemeral(true)
+        .content(":no_entry: Tu n'as pas la permission d'utiliser cette action."));
+    let result = match interaction {
+        Interaction::Command(command) => { command.create_response(http, response).await }
+        Interaction::Component(component) => { component.create_response(http, response).await }
+        // Autocompletion is just left empty
+        _ => { return; }
+    };
+    if let Err(err) = result {
+        debug!("Failed to send permission denied response : {}", err);
+    }
+}
+
+/// Discord error code of a response to an interaction that was already acknowledged
+const ALREADY_ACKNOWLEDGED: isize = 40060;
+
+fn is_already_acknowledged(err: &serenity::Error) -> bool {
+    match err {
+        serenity::Error::Http(HttpError::UnsuccessfulRequest(response)) => { response.error.code == ALREADY_ACKNOWLEDGED }
+        _ => { false }
+    }
+}
+
+/// Tell the user something went wrong if no module answered the interaction.
+/// If the failing module deferred the interaction, the message is sent as a followup instead.
+pub async fn respond_unanswered(http: &Http, interaction: &Interaction) {
+    let content = ":boom: **Mince alors !**\nQuelque chose s'est mal passé, l'équipe de modération a été prévenue.";
+    let response = CreateInteractionResponse::Message(CreateInteractionResponseMessage::new()
+        .ephemeral(true)
+        .content(content));
+    let result = match interaction {
+        Interaction::Command(command) => { command.create_response(http, response).await }
+        Interaction::Component(component) => { component.create_response(http, response).await }
+        Interaction::Modal(modal) => { modal.create_response(http, response).await }
+        _ => { return; }
+    };
+    let err = match result {
+        Ok(_) => { return; }
+        Err(err) if is_already_acknowledged(&err) => { err }
+        Err(err) => { return debug!("Did not send error response to interaction : {}", err) }
+    };
+    debug!("Interaction already acknowledged ({}), sending the error response as a followup", err);
+    let followup = CreateInteractionResponseFollowup::new().ephemeral(true).content(content);
+    let result = match interaction {
+        Interaction::Command(command) => { command.create_followup(http, followup).await }
+        Interaction::Component(component) => { component.create_followup(http, followup).await }
+        Interaction::Modal(modal) => { modal.create_followup(http, followup).await }
+        _ => { return; }
+    };
+    if let Err(err) = result {
+        debug!("Failed to send error followup to interaction : {}", err);
+    }
+}
@@ -1,6 +1,7 @@
 use std::fmt::{Display, Formatter};
 use std::io;
 
+#[derive(Debug)]
 pub struct BidibipError {
     msg: String,
 }
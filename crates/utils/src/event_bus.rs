@@ -0,0 +1,81 @@
+use std::fmt::{Display, Formatter};
+use bitflags::bitflags;
+use serenity::all::{ChannelId, GuildId, UserId};
+
+bitflags! {
+    /// Set of internal events a module wants to receive
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub struct InternalEvents: u32 {
+        const SANCTION_APPLIED = 1 << 0;
+        const TICKET_OPENED = 1 << 1;
+        const AD_PUBLISHED = 1 << 2;
+    }
+}
+
+/// A sanction was applied to a member and stored in its warn history
+#[derive(Clone, Debug)]
+pub struct SanctionApplied {
+    pub guild: GuildId,
+    pub user: UserId,
+    pub moderator: UserId,
+    // Sanction as displayed in the warn history (ex: "Kick")
+    pub sanction: String,
+    pub reason: String,
+    // Module that applied the sanction, or `discord` for sanctions applied by hand
+    pub source: String,
+}
+
+/// A member opened a ticket to talk with the moderation
+#[derive(Clone, Debug)]
+pub struct TicketOpened {
+    pub guild: GuildId,
+    pub user: UserId,
+    pub thread: ChannelId,
+}
+
+/// An ad was approved and published (or updated) in the ad forum
+#[derive(Clone, Debug)]
+pub struct AdPublished {
+    pub guild: GuildId,
+    pub author: UserId,
+    pub reviewer: UserId,
+    pub post: ChannelId,
+    pub edited: bool,
+}
+
+/// Events sent by a module to the other modules, see BidibipSharedData::publish()
+#[derive(Clone, Debug)]
+pub enum InternalEvent {
+    SanctionApplied(SanctionApplied),
+    TicketOpened(TicketOpened),
+    AdPublished(AdPublished),
+}
+
+impl InternalEvent {
+    pub fn flag(&self) -> InternalEvents {
+        match self {
+            InternalEvent::SanctionApplied(_) => { InternalEvents::SANCTION_APPLIED }
+            InternalEvent::TicketOpened(_) => { InternalEvents::TICKET_OPENED }
+            InternalEvent::AdPublished(_) => { InternalEvents::AD_PUBLISHED }
+        }
+    }
+
+    /// Events are only sent to the modules enabled on this guild
+    pub fn guild(&self) -> GuildId {
+        match self {
+            InternalEvent::SanctionApplied(event) => { event.guild }
+            InternalEvent::TicketOpened(event) => { event.guild }
+            InternalEvent::AdPublished(event) => { event.guild }
+        }
+    }
+}
+
+impl Display for InternalEvent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InternalEvent::SanctionApplied(event) => { write!(f, "SanctionApplied ({} on user {} by {} from {})", event.sanction, event.user, event.moderator, event.source) }
+            InternalEvent::TicketOpened(event) => { write!(f, "TicketOpened (user {} in thread {})", event.user, event.thread) }
+            InternalEvent::AdPublished(event) => { write!(f, "AdPublished (post {} of user {})", event.post, event.author) }
+        }
+    }
+}
@@ -1,37 +1,73 @@
+use std::any::Any;
 use std::collections::{HashMap, HashSet};
+use std::future::Future;
 use std::ops::Deref;
-use std::sync::{Arc};
-use serenity::all::{AuditLogEntry, ChannelId, Command, Context, GuildChannel, GuildId, GuildMemberUpdateEvent, Interaction, Member, Message, MessageId, MessageUpdateEvent, PartialGuildChannel, Ready, User};
+use std::sync::{Arc, OnceLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use anyhow::Error;
+use serde_json::Value;
+use serenity::all::{AuditLogEntry, ChannelId, Command, ConnectionStage, Context, EditCommandPermissions, GuildChannel, GuildId, GuildMemberUpdateEvent, Http, Interaction, Member, Message, MessageId, MessageUpdateEvent, PartialGuildChannel, Ready, ShardStageUpdateEvent, User};
 use serenity::model::Permissions;
 use serenity::prelude::EventHandler;
 use tokio::sync::{RwLock};
 use tracing::{error, info, warn};
-use crate::config::Config;
+use crate::config::{retire_legacy_button_ids, Config};
+use crate::dispatch::{respond_denied, respond_unanswered, task_error, EventContext, EventKind, ModuleError, ModuleEvents};
+use crate::error::BidibipError;
+use crate::interaction_utils::custom_id_owner;
 use crate::logger::DiscordLogConnector;
+use crate::health::Health;
+use crate::metrics::Metrics;
+use crate::config_editor::{remove_field, set_field};
 use crate::module::{BidibipModule, LoadModule};
+use crate::permissions::{command_key, interaction_permission, AllowList, PermissionRules};
+use crate::reload::{ConfigReload, ConfigWatcher};
+use crate::scheduler::Scheduler;
+use crate::event_bus::{InternalEvent, InternalEvents};
+use crate::utilities::{ResultDebug, TruncateText};
+use crate::validation::{ConfigValidator, GuildResources, ValidationReport};
 
 pub struct GlobalInterface {
     log_connector: Arc<DiscordLogConnector>,
     shared_data: Arc<BidibipSharedData>,
+    config_watcher: OnceLock<ConfigWatcher>,
 }
 
 pub struct ModuleData {
-    pub module: Box<dyn BidibipModule>,
+    pub module: Arc<dyn BidibipModule>,
     command_names: RwLock<HashSet<String>>,
     pub name: String,
     #[allow(unused)]
     pub description: String,
+    pub events: ModuleEvents,
+    // Permission keys of the component actions of this module
+    pub permission_actions: Vec<String>,
+    // Internal events this module receives
+    pub subscriptions: InternalEvents,
+    error_count: AtomicUsize,
+    consecutive_failures: AtomicUsize,
 }
 
 impl ModuleData {
-    pub fn new(name: String, module: Box<dyn BidibipModule>, description: String) -> Self {
+    pub fn new(name: String, module: Arc<dyn BidibipModule>, description: String, events: ModuleEvents) -> Self {
         Self {
             module,
             command_names: Default::default(),
+            permission_actions: vec![],
+            subscriptions: InternalEvents::empty(),
             name,
             description,
+            events,
+            error_count: AtomicUsize::default(),
+            consecutive_failures: AtomicUsize::default(),
         }
     }
+
+    /// Number of errors returned by this module since startup
+    pub fn error_count(&self) -> usize {
+        self.error_count.load(Ordering::SeqCst)
+    }
 }
 
 
@@ -58,18 +94,133 @@ pub struct BidibipSharedData {
     available_modules: RwLock<HashMap<String, Arc<ModuleData>>>,
     enabled_modules: RwLock<HashMap<String, Arc<ModuleData>>>,
     disabled_modules: RwLock<HashSet<String>>,
-    pub permissions: RwLock<PermissionData>,
+    // Guild - modules that are not enabled on this guild
+    guild_disabled_modules: RwLock<HashMap<GuildId, HashSet<String>>>,
+    // Module name - load error
+    failed_modules: RwLock<HashMap<String, String>>,
+    // Guild - permissions of the configured roles
+    permissions: RwLock<HashMap<GuildId, PermissionData>>,
+    // Roles and users allowed to use each restricted command or action
+    permission_rules: PermissionRules,
+    // Delayed and recurring jobs of the modules
+    scheduler: Scheduler,
+    // Gateway and startup state reported by the health checks
+    health: Health,
 }
 
 impl BidibipSharedData {
     pub async fn register_module<T: 'static + LoadModule<T> + BidibipModule>(&self, module: T) {
-        let module = Arc::new(ModuleData::new(T::name().to_string(), Box::new(module), T::description().to_string()));
+        let mut module = ModuleData::new(T::name().to_string(), Arc::new(module), T::description().to_string(), T::events());
+        module.permission_actions = T::permission_actions().into_iter().map(|action| format!("{}::{}", T::name(), action)).collect();
+        module.subscriptions = T::subscriptions();
+        let module = Arc::new(module);
         if !self.disabled_modules.read().await.contains(&T::name().to_string()) {
             self.enabled_modules.write().await.insert(T::name().to_string(), module.clone());
         }
         self.available_modules.write().await.insert(T::name().to_string(), module);
     }
 
+    /// Remember a module that could not be loaded
+    pub async fn register_load_failure(&self, name: &str, error: String) {
+        self.failed_modules.write().await.insert(name.to_string(), error);
+    }
+
+    /// Modules that could not be loaded, with the load error
+    pub async fn failed_modules(&self) -> HashMap<String, String> {
+        self.failed_modules.read().await.clone()
+    }
+
+    pub fn health(&self) -> &Health {
+        &self.health
+    }
+
+    /// Permissions of the roles configured for the given guild
+    pub async fn permissions(&self, guild: GuildId) -> PermissionData {
+        self.permissions.read().await.get(&guild).cloned().unwrap_or_default()
+    }
+
+    pub fn permission_rules(&self) -> &PermissionRules {
+        &self.permission_rules
+    }
+
+    pub fn scheduler(&self) -> &Scheduler {
+        &self.scheduler
+    }
+
+    /// Replace the allow-list of a command or action, and update the Discord permissions of the command.
+    /// Returns false if the command permissions could not be synced with Discord (the list is still enforced by the bot).
+    pub async fn set_allow_list(&self, ctx: &Context, guild: GuildId, key: &str, allow_list: AllowList) -> Result<bool, Error> {
+        self.permission_rules.set(guild, key, allow_list.clone()).await?;
+        let command = match key.strip_prefix('/') {
+            None => { return Ok(true) }
+            Some(command) => { command }
+        };
+        // Commands with an allow-list are visible to everyone, unless Discord permissions can be synced
+        self.update_guild_commands(ctx, guild).await;
+        let commands = guild.get_commands(&ctx.http).await?;
+        let command = match commands.iter().find(|registered| registered.name == command) {
+            None => { return Ok(true) }
+            Some(command) => { command }
+        };
+        match guild.edit_command_permissions(&ctx.http, command.id, EditCommandPermissions::new(allow_list.command_permissions(guild))).await {
+            Ok(_) => { Ok(true) }
+            Err(err) => {
+                warn!("Failed to sync permissions of command {} with Discord : {}", key, err);
+                Ok(false)
+            }
+        }
+    }
+
+    /// Commands and component actions of every module that can be restricted with an allow-list, sorted
+    pub async fn permission_keys(&self) -> Vec<String> {
+        let mut keys = vec![];
+        for module in self.available_modules.read().await.values() {
+            keys.extend(module.command_names.read().await.iter().map(|command| command_key(command)));
+            keys.extend(module.permission_actions.iter().cloned());
+        }
+        keys.sort();
+        keys
+    }
+
+    /// Check the allow-list of the command or component action behind the interaction
+    pub async fn is_interaction_allowed(&self, interaction: &Interaction) -> bool {
+        match interaction_permission(interaction) {
+            None => { true }
+            Some((guild, key, user, roles)) => { self.permission_rules.is_allowed(guild, &key, user, &roles).await }
+        }
+    }
+
+    /// Send an internal event to the modules subscribed to it and enabled on its guild.
+    /// Subscribers run in their own task, so the publisher doesn't wait for them.
+    pub async fn publish(&self, ctx: &Context, event: InternalEvent) {
+        let flag = event.flag();
+        let timeout = Duration::from_millis(Config::get().dispatch.module_timeout_ms);
+        for module in self.get_guild_modules(Some(event.guild())).await {
+            if !module.subscriptions.contains(flag) {
+                continue;
+            }
+            let (ctx, event) = (ctx.clone(), event.clone());
+            tokio::spawn(async move {
+                let description = event.to_string();
+                match tokio::time::timeout(timeout, module.module.internal_event(ctx, event)).await {
+                    Ok(Ok(_)) => {}
+                    Ok(Err(err)) => { error!("Module {} failed to handle internal event {} : {}", module.name, description, err) }
+                    Err(_) => { error!("Module {} timed out handling internal event {} after {}ms", module.name, description, timeout.as_millis()) }
+                }
+            });
+        }
+    }
+
+    /// Get another module to call the services it exposes.
+    /// Returns None if the module is not loaded, or not enabled (on the given guild) : the caller should do without it.
+    pub async fn service<T: 'static + LoadModule<T> + BidibipModule>(&self, guild: Option<GuildId>) -> Option<Arc<T>> {
+        if !self.is_module_enabled(T::name(), guild).await {
+            return None;
+        }
+        let module = self.available_modules.read().await.get(T::name())?.module.clone();
+        (module as Arc<dyn Any + Send + Sync>).downcast::<T>().ok()
+    }
+
     pub async fn available_modules(&self) -> HashSet<String> {
         let mut modules = HashSet::<String>::new();
         for module in self.available_modules.read().await.keys() {
@@ -78,21 +229,114 @@ impl BidibipSharedData {
         modules
     }
 
-    pub async fn set_module_enabled(&self, ctx: &Context, name: &str, enabled: bool, update_commands: bool) {
-        if enabled {
+    /// Read the config file of the module again, and apply it if it's valid
+    pub async fn reload_module_config(&self, http: &Http, name: &str) -> ConfigReload {
+        let module = match self.available_modules.read().await.get(name) {
+            None => { return ConfigReload::UnknownModule }
+            Some(module) => { module.clone() }
+        };
+        let guild = match GuildResources::fetch_all(http, &Config::get().guild_ids()).await {
+            Ok(guild) => { Some(guild) }
+            Err(err) => {
+                error!("Failed to fetch guild channels and roles, only checking missing ids : {}", err);
+                None
+            }
+        };
+        let mut validator = ConfigValidator::new(guild.as_ref());
+        match module.module.reload_config(&mut validator).await {
+            Ok(false) => { ConfigReload::Unsupported }
+            Ok(true) => {
+                let issues = validator.issues();
+                if issues.is_empty() { ConfigReload::Reloaded } else { ConfigReload::Invalid(issues) }
+            }
+            Err(err) => { ConfigReload::Unreadable(err.to_string()) }
+        }
+    }
+
+    /// Change a field of the module config file, then reload the module config.
+    /// The previous value of the field is restored if the new config is rejected. Returns the previous value of the field.
+    pub async fn set_module_config_field(&self, http: &Http, name: &str, path: &str, value: Value) -> Result<Option<Value>, Error> {
+        if !self.available_modules.read().await.contains_key(name) {
+            return Err(Error::msg(ConfigReload::UnknownModule.to_string()));
+        }
+        let mut config = Config::get().read_module_config_value(name).map_err(|err| Error::msg(format!("Impossible de lire la configuration : {}", err)))?;
+        let old_value = set_field(&mut config, path, value)?;
+        Config::get().write_module_config_value(name, &config)?;
+        match self.reload_module_config(http, name).await {
+            ConfigReload::Reloaded => { Ok(old_value) }
+            result => {
+                // The module may have saved its config during the reload : only the edited field is restored
+                let mut config = Config::get().read_module_config_value(name)?;
+                match &old_value {
+                    None => { remove_field(&mut config, path); }
+                    Some(old_value) => { set_field(&mut config, path, old_value.clone())?; }
+                }
+                Config::get().write_module_config_value(name, &config)?;
+                Err(Error::msg(result.to_string()))
+            }
+        }
+    }
+
+    /// Returns true if the module state changed
+    pub async fn set_module_enabled(&self, ctx: &Context, name: &str, enabled: bool, update_commands: bool) -> bool {
+        let changed = if enabled {
             self.disabled_modules.write().await.remove(&name.to_string());
-            if let Some(module) = self.available_modules.read().await.get(&name.to_string()) {
-                self.enabled_modules.write().await.insert(name.to_string(), module.clone());
+            match self.available_modules.read().await.get(&name.to_string()) {
+                Some(module) => { self.enabled_modules.write().await.insert(name.to_string(), module.clone()).is_none() }
+                None => { false }
             }
         } else {
             self.disabled_modules.write().await.insert(name.to_string());
-            self.enabled_modules.write().await.remove(&name.to_string());
+            self.enabled_modules.write().await.remove(&name.to_string()).is_some()
+        };
+        if update_commands {
+            self.update_commands(ctx).await;
+        }
+        changed
+    }
+
+    /// Enable or disable a module on a single guild. The module should also be enabled globally to run.
+    pub async fn set_module_enabled_in_guild(&self, ctx: &Context, guild: GuildId, name: &str, enabled: bool, update_commands: bool) {
+        let mut guild_disabled_modules = self.guild_disabled_modules.write().await;
+        let disabled_modules = guild_disabled_modules.entry(guild).or_default();
+        if enabled {
+            disabled_modules.remove(name);
+        } else {
+            disabled_modules.insert(name.to_string());
         }
+        drop(guild_disabled_modules);
         if update_commands {
             self.update_commands(ctx).await;
         }
     }
 
+    /// Modules that are not enabled on the given guild
+    pub async fn guild_disabled_modules(&self, guild: GuildId) -> HashSet<String> {
+        self.guild_disabled_modules.read().await.get(&guild).cloned().unwrap_or_default()
+    }
+
+    /// Check if the module is enabled, and not disabled on the given guild
+    pub async fn is_module_enabled(&self, name: &str, guild: Option<GuildId>) -> bool {
+        if !self.enabled_modules.read().await.contains_key(name) {
+            return false;
+        }
+        match guild {
+            None => { true }
+            Some(guild) => { !self.guild_disabled_modules.read().await.get(&guild).is_some_and(|disabled| disabled.contains(name)) }
+        }
+    }
+
+    /// Enabled modules that are not disabled on the given guild (every enabled module if there is no guild)
+    pub async fn get_guild_modules(&self, guild: Option<GuildId>) -> Vec<Arc<ModuleData>> {
+        let mut modules = vec![];
+        for module in self.get_enabled_modules().await {
+            if self.is_module_enabled(&module.name, guild).await {
+                modules.push(module);
+            }
+        }
+        modules
+    }
+
     pub async fn get_enabled_modules(&self) -> Vec<Arc<ModuleData>> {
         let mut modules = vec![];
         for module in self.enabled_modules.read().await.deref() {
@@ -101,6 +345,17 @@ impl BidibipSharedData {
         modules
     }
 
+    /// Modules enabled on the guild and declaring the given command
+    pub async fn get_command_modules(&self, command: &str, guild: Option<GuildId>) -> Vec<Arc<ModuleData>> {
+        let mut modules = vec![];
+        for module in self.get_guild_modules(guild).await {
+            if module.command_names.read().await.contains(command) {
+                modules.push(module);
+            }
+        }
+        modules
+    }
+
     pub async fn get_disabled_modules(&self) -> Vec<Arc<ModuleData>> {
         let mut modules = vec![];
         for module in self.available_modules.read().await.deref() {
@@ -111,33 +366,55 @@ impl BidibipSharedData {
         modules
     }
 
-    /// Update command list, and register or remove updated commands
+    /// Update command list of each guild, and register or remove updated commands
     pub async fn update_commands(&self, ctx: &Context) {
-        let mut outdated_commands = HashSet::new();
+        match Command::get_global_commands(&ctx.http).await {
+            Ok(glob) => {
+                if !glob.is_empty() {
+                    match Command::set_global_commands(&ctx.http, vec![]).await {
+                        Ok(_) => { warn!("Cleaned up old global commands"); }
+                        Err(err) => { error!("Failed to cleanup old global commands : {}", err); }
+                    };
+                }
+            }
+            Err(err) => { error!("Failed to get global commands : {}", err); }
+        }
+
+        for guild_id in Config::get().guild_ids() {
+            self.update_guild_commands(ctx, guild_id).await;
+        }
+    }
+
+    /// Register the commands of the modules enabled on the guild
+    async fn update_guild_commands(&self, ctx: &Context, guild_id: GuildId) {
+        let mut outdated_commands = HashMap::new();
         let mut command_list = vec![];
 
-        let permissions = self.permissions.read().await.clone();
-        for module in &*self.get_enabled_modules().await {
-            for command in module.module.fetch_commands(&permissions) {
-                outdated_commands.insert(command.name.clone());
+        let permissions = self.permissions(guild_id).await;
+        for module in &*self.get_guild_modules(Some(guild_id)).await {
+            for mut command in module.module.fetch_commands(&permissions) {
+                // The allow-list replaces the Discord permissions
+                if !self.permission_rules.get(guild_id, &command_key(&command.name)).await.is_empty() {
+                    command.default_member_permissions = None;
+                }
+                outdated_commands.insert(command.name.clone(), command.default_member_permissions);
                 command_list.push(command.into());
             }
         }
 
-        let guild_id = Config::get().server_id;
-
-        let glob = Command::get_global_commands(&ctx.http).await.unwrap();
-        if !glob.is_empty() {
-            match Command::set_global_commands(&ctx.http, vec![]).await {
-                Ok(_) => { warn!("Cleaned up old global commands"); }
-                Err(err) => { error!("Failed to cleanup old global commands : {}", err); }
-            };
-        }
+        let existing_commands = match guild_id.get_commands(&ctx.http).await {
+            Ok(commands) => { commands }
+            Err(err) => {
+                return error!("Failed to get commands of guild {} : {}", guild_id, err);
+            }
+        };
 
         let mut outdated = false;
-        for command in guild_id.get_commands(&ctx.http).await.unwrap() {
-            if outdated_commands.contains(&command.name) {
-                outdated_commands.remove(&command.name);
+        for command in existing_commands {
+            if let Some(default_member_permissions) = outdated_commands.remove(&command.name) {
+                if default_member_permissions != command.default_member_permissions {
+                    outdated = true;
+                }
             } else {
                 outdated = true;
             }
@@ -150,230 +427,485 @@ impl BidibipSharedData {
         if outdated {
             match guild_id.set_commands(&ctx.http, command_list).await {
                 Ok(_) => {
-                    warn!("Updated command list");
+                    warn!("Updated command list of guild {}", guild_id);
                 }
                 Err(err) => {
-                    error!("Failed to update command list : {}", err);
+                    error!("Failed to update command list of guild {} : {}", guild_id, err);
                 }
             };
         }
     }
+
+    /// Reset the failure count of the module if its hook or job succeeded, or report the error.
+    /// Returns false if it failed.
+    pub(crate) async fn report_result(&self, ctx: &Context, module: &ModuleData, event: &EventContext, result: Result<(), BidibipError>) -> bool {
+        match result {
+            Ok(_) => {
+                module.consecutive_failures.store(0, Ordering::SeqCst);
+                true
+            }
+            Err(error) => {
+                self.report_error(ctx, module, ModuleError { module: module.name.clone(), event: event.clone(), error }).await;
+                false
+            }
+        }
+    }
+
+    /// Log the error, and disable the module if it failed too many times in a row
+    async fn report_error(&self, ctx: &Context, module: &ModuleData, error: ModuleError) {
+        let count = module.error_count.fetch_add(1, Ordering::SeqCst) + 1;
+        Metrics::get().module_errors.with_label_values(&[module.name.as_str()]).inc();
+        error!("{} ({} error(s) since startup)", error, count);
+
+        let max_failures = Config::get().dispatch.max_consecutive_failures;
+        if max_failures == 0 {
+            return;
+        }
+        // Count and reset in a single step : only one of the concurrent failures reaches the limit
+        let failures = module.consecutive_failures.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |failures| {
+            Some(if failures + 1 >= max_failures { 0 } else { failures + 1 })
+        }).unwrap_or_default() + 1;
+        if failures < max_failures {
+            return;
+        }
+
+        // The module may already have been disabled by someone else
+        if !self.set_module_enabled(ctx, module.name.as_str(), false, true).await {
+            return;
+        }
+        warn!("Module {} disabled after {} consecutive failures", module.name, failures);
+        Config::get().channels.staff_channel.say(&ctx.http, format!(":warning: Le module `{}` a été désactivé automatiquement après {} échecs consécutifs.\n> Dernière erreur : `{}`\nUtilise `/set-module-enabled` pour le réactiver.", module.name, failures, error.error.truncate_text(1000))).await.on_fail("Failed to send module disabled notice");
+    }
 }
 
 
 impl GlobalInterface {
     /// Default constructor
     /// The log connector is used to provide the log channel to the logger
-    pub async fn new(log_connector: Arc<DiscordLogConnector>) -> Self {
-        let shared_data = Arc::new(BidibipSharedData { available_modules: Default::default(), enabled_modules: Default::default(), permissions: Default::default(), disabled_modules: Default::default() });
-        Self { shared_data, log_connector }
+    pub async fn new(log_connector: Arc<DiscordLogConnector>) -> Result<Self, Error> {
+        let guild_disabled_modules = Config::get().guilds.iter().map(|(guild, config)| (*guild, config.disabled_modules.iter().cloned().collect())).collect();
+        let shared_data = Arc::new(BidibipSharedData { available_modules: Default::default(), enabled_modules: Default::default(), permissions: Default::default(), disabled_modules: Default::default(), guild_disabled_modules: RwLock::new(guild_disabled_modules), failed_modules: Default::default(), permission_rules: PermissionRules::load()?, scheduler: Scheduler::load()?, health: Default::default() });
+        Ok(Self { shared_data, log_connector, config_watcher: OnceLock::new() })
     }
 
     pub fn shared_data(&self) -> &Arc<BidibipSharedData> {
         &self.shared_data
     }
 
-    /// Update roles from configured roles id (used to handle permissions)
+    /// Check the global and module configs against the guilds, and disable the modules with an invalid config
+    async fn validate_configs(&self, ctx: &Context) {
+        let mut report = ValidationReport::default();
+        for guild_id in Config::get().guild_ids() {
+            let guild = match GuildResources::fetch(&ctx.http, guild_id).await {
+                Ok(guild) => { Some(guild) }
+                Err(err) => {
+                    error!("Failed to fetch channels and roles of guild {}, only checking missing ids : {}", guild_id, err);
+                    None
+                }
+            };
+            let mut validator = ConfigValidator::new(guild.as_ref());
+            Config::get().validate(guild_id, &mut validator);
+            report.add(if guild_id == Config::get().server_id { "config".to_string() } else { format!("config (serveur {})", guild_id) }, validator.issues());
+        }
+
+        // Module configs can reference any handled guild
+        let guild = match GuildResources::fetch_all(&ctx.http, &Config::get().guild_ids()).await {
+            Ok(guild) => { Some(guild) }
+            Err(err) => {
+                error!("Failed to fetch guild channels and roles, only checking missing ids : {}", err);
+                None
+            }
+        };
+
+        for module in self.shared_data.get_enabled_modules().await {
+            let mut validator = ConfigValidator::new(guild.as_ref());
+            module.module.validate_config(&mut validator).await;
+            let issues = validator.issues();
+            if !issues.is_empty() {
+                report.add(&module.name, issues);
+                report.disable_module(&module.name);
+                self.shared_data.set_module_enabled(ctx, &module.name, false, false).await;
+            }
+        }
+
+        for (module, error) in self.shared_data.failed_modules().await {
+            report.add(module, vec![format!("échec du chargement : {}", error)]);
+        }
+
+        if report.is_empty() {
+            info!("{}", report);
+        } else {
+            error!("{}", report);
+        }
+    }
+
+    /// Look for the buttons that leaked because their message was deleted by hand, and retire the old button id allocator
+    async fn reclaim_leaked_buttons(&self, ctx: &Context) {
+        match retire_legacy_button_ids() {
+            Ok(Some(allocated)) => { info!("Retired the button id allocator file ({} ids were allocated), button ids are now stateless", allocated) }
+            Ok(None) => {}
+            Err(err) => { error!("Failed to retire button id allocator file : {}", err) }
+        }
+
+        let mut leaks = vec![];
+        for module in self.shared_data.get_enabled_modules().await {
+            match module.module.reclaim_leaked_buttons(&ctx.http).await {
+                Ok(module_leaks) => { leaks.extend(module_leaks.into_iter().map(|leak| format!("{} : {}", module.name, leak))) }
+                Err(err) => { error!("Failed to look for leaked buttons of module {} : {}", module.name, err) }
+            }
+        }
+        if leaks.is_empty() {
+            info!("No leaked button found");
+        } else {
+            warn!("Reclaimed {} leaked button(s) :\n{}", leaks.len(), leaks.join("\n"));
+        }
+    }
+
+    /// Update roles from configured roles id of each guild (used to handle permissions)
     async fn fetch_roles(&self, ctx: &Context) {
-        let roles = match GuildId::from(Config::get().server_id).roles(&ctx.http).await {
+        for guild in Config::get().guild_ids() {
+            if let Some(permissions) = self.fetch_guild_permissions(ctx, guild).await {
+                self.shared_data.permissions.write().await.insert(guild, permissions);
+            }
+        }
+    }
+
+    async fn fetch_guild_permissions(&self, ctx: &Context, guild: GuildId) -> Option<PermissionData> {
+        let roles = match guild.roles(&ctx.http).await {
             Ok(roles) => { roles }
             Err(err) => {
-                return error!("Failed to fetch roles : {}", err);
+                error!("Failed to fetch roles of guild {} : {}", guild, err);
+                return None;
             }
         };
 
-        let member_role = match roles.get(&Config::get().roles.member) {
+        let configured_roles = Config::get().guild_roles(guild);
+        let member_role = match roles.get(&configured_roles.member) {
             None => {
-                return error!("Member role with id {} does not exists", Config::get().roles.member);
+                error!("Member role with id {} does not exists", configured_roles.member);
+                return None;
             }
             Some(role) => { role }
         };
 
-        let admin_role = match roles.get(&Config::get().roles.administrator) {
+        let admin_role = match roles.get(&configured_roles.administrator) {
             None => {
-                return error!("Administrator role with id {} does not exists", Config::get().roles.administrator);
+                error!("Administrator role with id {} does not exists", configured_roles.administrator);
+                return None;
             }
             Some(role) => { role }
         };
 
-        let helper_role = match roles.get(&Config::get().roles.helper) {
+        let helper_role = match roles.get(&configured_roles.helper) {
             None => {
-                return error!("Helper role with id {} does not exists", Config::get().roles.helper);
+                error!("Helper role with id {} does not exists", configured_roles.helper);
+                return None;
             }
             Some(role) => { role }
         };
 
-        let mut permissions = self.shared_data.permissions.write().await;
+        let mut permissions = PermissionData::default();
 
         permissions.member_permissions = member_role.permissions;
         permissions.administrator_permissions = admin_role.permissions;
         permissions.helper_permissions = helper_role.permissions;
 
+        // Keep the role bits : the commands of both levels get the same default permissions, restrict them with /permissions
         if member_role.permissions == admin_role.permissions {
-            permissions.member_permissions = Permissions::all();
-            permissions.administrator_permissions = Permissions::all();
-            error!("Member and admin permissions are the same !!!")
+            error!("Member and admin permissions of guild {} are the same, restrict the admin commands with /permissions", guild)
         }
 
         if helper_role.permissions == admin_role.permissions {
-            permissions.helper_permissions = Permissions::all();
-            permissions.administrator_permissions = Permissions::all();
-            error!("Helper and admin permissions are the same !!!")
+            error!("Helper and admin permissions of guild {} are the same, restrict the admin commands with /permissions", guild)
         }
 
         if member_role.permissions == helper_role.permissions {
-            permissions.member_permissions = Permissions::all();
-            permissions.helper_permissions = Permissions::all();
-            error!("Member and helper permissions are the same !!!")
+            error!("Member and helper permissions of guild {} are the same, restrict the helper commands with /permissions", guild)
+        }
+        Some(permissions)
+    }
+}
+
+impl GlobalInterface {
+    /// Call the given hook on every enabled module subscribed to this event and report the errors they return.
+    /// Returns true if at least one module failed
+    async fn dispatch<F, Fut>(&self, ctx: &Context, event: EventContext, hook: F) -> bool
+    where
+        F: Fn(Arc<ModuleData>) -> Fut,
+        Fut: Future<Output=Result<(), BidibipError>> + Send + 'static,
+    {
+        self.shared_data.health.record_event();
+        let modules = self.event_modules(&event).await;
+        if modules.is_empty() {
+            return false;
+        }
+        self.dispatch_to(ctx, modules, event, hook).await
+    }
+
+    /// Enabled modules subscribed to this event
+    async fn event_modules(&self, event: &EventContext) -> Vec<Arc<ModuleData>> {
+        if let Some(guild) = event.guild {
+            if !Config::get().is_handled_guild(guild) {
+                return vec![];
+            }
         }
+        let flag = event.kind.flag();
+        self.shared_data.get_guild_modules(event.guild).await.into_iter().filter(|module| module.events.contains(flag)).collect()
+    }
+
+    /// Run the hook of each given module in its own task, with a timeout.
+    /// Returns true if at least one module failed, timed out or panicked
+    async fn dispatch_to<F, Fut>(&self, ctx: &Context, modules: Vec<Arc<ModuleData>>, event: EventContext, hook: F) -> bool
+    where
+        F: Fn(Arc<ModuleData>) -> Fut,
+        Fut: Future<Output=Result<(), BidibipError>> + Send + 'static,
+    {
+        let timeout = Duration::from_millis(Config::get().dispatch.module_timeout_ms);
+
+        let metrics = Metrics::get();
+        let event_name = event.kind.to_string();
+        let mut tasks = vec![];
+        for module in modules {
+            metrics.events.with_label_values(&[event_name.as_str(), module.name.as_str()]).inc();
+            let duration = metrics.handler_duration.with_label_values(&[event_name.as_str(), module.name.as_str()]);
+            let handler = hook(module.clone());
+            let task = tokio::spawn(async move {
+                let _timer = duration.start_timer();
+                tokio::time::timeout(timeout, handler).await
+            });
+            tasks.push((module, task));
+        }
+
+        let mut failed = false;
+        for (module, task) in tasks {
+            let result = match task.await {
+                Ok(Ok(result)) => { result }
+                Ok(Err(_)) => { Err(BidibipError::msg(format!("timed out after {}ms", timeout.as_millis()))) }
+                Err(err) => { Err(task_error(err)) }
+            };
+            if !self.shared_data.report_result(ctx, &module, &event, result).await {
+                failed = true;
+            }
+        }
+        failed
     }
 }
 
 #[serenity::async_trait]
 impl EventHandler for GlobalInterface {
     async fn channel_create(&self, ctx: Context, channel: GuildChannel) {
-        for module in self.shared_data.get_enabled_modules().await {
-            #[allow(unused)]
-            module.module.channel_create(ctx.clone(), channel.clone()).await;
-        }
+        self.dispatch(&ctx, EventContext::new(EventKind::ChannelCreate).guild(Some(channel.guild_id)).channel(channel.id), |module| {
+            let (ctx, channel) = (ctx.clone(), channel.clone());
+            async move { module.module.channel_create(ctx, channel).await }
+        }).await;
     }
 
     async fn channel_delete(&self, ctx: Context, channel: GuildChannel, messages: Option<Vec<Message>>) {
-        for module in self.shared_data.get_enabled_modules().await {
-            #[allow(unused)]
-            module.module.channel_delete(ctx.clone(), channel.clone(), messages.clone()).await;
-        }
+        self.dispatch(&ctx, EventContext::new(EventKind::ChannelDelete).guild(Some(channel.guild_id)).channel(channel.id), |module| {
+            let (ctx, channel, messages) = (ctx.clone(), channel.clone(), messages.clone());
+            async move { module.module.channel_delete(ctx, channel, messages).await }
+        }).await;
     }
 
     async fn guild_audit_log_entry_create(&self, ctx: Context, entry: AuditLogEntry, guild_id: GuildId) {
-        for module in self.shared_data.get_enabled_modules().await {
-            #[allow(unused)]
-            module.module.guild_audit_log_entry_create(ctx.clone(), entry.clone(), guild_id).await;
-        }
+        self.dispatch(&ctx, EventContext::new(EventKind::GuildAuditLogEntryCreate).guild(Some(guild_id)).user(entry.user_id), |module| {
+            let (ctx, entry) = (ctx.clone(), entry.clone());
+            async move { module.module.guild_audit_log_entry_create(ctx, entry, guild_id).await }
+        }).await;
     }
 
     async fn guild_ban_addition(&self, ctx: Context, guild_id: GuildId, banned_user: User) {
-        for module in self.shared_data.get_enabled_modules().await {
-            #[allow(unused)]
-            module.module.guild_ban_addition(ctx.clone(), guild_id, banned_user.clone()).await;
-        }
+        self.dispatch(&ctx, EventContext::new(EventKind::GuildBanAddition).guild(Some(guild_id)).user(banned_user.id), |module| {
+            let (ctx, banned_user) = (ctx.clone(), banned_user.clone());
+            async move { module.module.guild_ban_addition(ctx, guild_id, banned_user).await }
+        }).await;
     }
 
     async fn guild_ban_removal(&self, ctx: Context, guild_id: GuildId, unbanned_user: User) {
-        for module in self.shared_data.get_enabled_modules().await {
-            #[allow(unused)]
-            module.module.guild_ban_removal(ctx.clone(), guild_id, unbanned_user.clone()).await;
-        }
+        self.dispatch(&ctx, EventContext::new(EventKind::GuildBanRemoval).guild(Some(guild_id)).user(unbanned_user.id), |module| {
+            let (ctx, unbanned_user) = (ctx.clone(), unbanned_user.clone());
+            async move { module.module.guild_ban_removal(ctx, guild_id, unbanned_user).await }
+        }).await;
     }
 
     async fn guild_member_addition(&self, ctx: Context, new_member: Member) {
-        for module in self.shared_data.get_enabled_modules().await {
-            #[allow(unused)]
-            module.module.guild_member_addition(ctx.clone(), new_member.clone()).await;
-        }
+        self.dispatch(&ctx, EventContext::new(EventKind::GuildMemberAddition).guild(Some(new_member.guild_id)).user(new_member.user.id), |module| {
+            let (ctx, new_member) = (ctx.clone(), new_member.clone());
+            async move { module.module.guild_member_addition(ctx, new_member).await }
+        }).await;
     }
 
     async fn guild_member_removal(&self, ctx: Context, guild_id: GuildId, user: User, member_data_if_available: Option<Member>) {
-        for module in self.shared_data.get_enabled_modules().await {
-            #[allow(unused)]
-            module.module.guild_member_removal(ctx.clone(), guild_id, user.clone(), member_data_if_available.clone()).await;
-        }
+        self.dispatch(&ctx, EventContext::new(EventKind::GuildMemberRemoval).guild(Some(guild_id)).user(user.id), |module| {
+            let (ctx, user, member_data_if_available) = (ctx.clone(), user.clone(), member_data_if_available.clone());
+            async move { module.module.guild_member_removal(ctx, guild_id, user, member_data_if_available).await }
+        }).await;
     }
 
     async fn guild_member_update(&self, ctx: Context, old_if_available: Option<Member>, new: Option<Member>, event: GuildMemberUpdateEvent) {
-        for module in self.shared_data.get_enabled_modules().await {
-            #[allow(unused)]
-            module.module.guild_member_update(ctx.clone(), old_if_available.clone(), new.clone(), event.clone()).await;
-        }
+        self.dispatch(&ctx, EventContext::new(EventKind::GuildMemberUpdate).guild(Some(event.guild_id)).user(event.user.id), |module| {
+            let (ctx, old_if_available, new, event) = (ctx.clone(), old_if_available.clone(), new.clone(), event.clone());
+            async move { module.module.guild_member_update(ctx, old_if_available, new, event).await }
+        }).await;
     }
 
     async fn message(&self, ctx: Context, new_message: Message) {
-        for module in self.shared_data.get_enabled_modules().await {
-            #[allow(unused)]
-            module.module.message(ctx.clone(), new_message.clone()).await;
-        }
+        self.dispatch(&ctx, EventContext::new(EventKind::Message).guild(new_message.guild_id).user(new_message.author.id).channel(new_message.channel_id), |module| {
+            let (ctx, new_message) = (ctx.clone(), new_message.clone());
+            async move { module.module.message(ctx, new_message).await }
+        }).await;
     }
 
     async fn message_delete(&self, ctx: Context, channel_id: ChannelId, deleted_message_id: MessageId, guild_id: Option<GuildId>) {
-        for module in self.shared_data.get_enabled_modules().await {
-            #[allow(unused)]
-            module.module.message_delete(ctx.clone(), channel_id, deleted_message_id, guild_id).await;
-        }
+        self.dispatch(&ctx, EventContext::new(EventKind::MessageDelete).guild(guild_id).channel(channel_id), |module| {
+            let ctx = ctx.clone();
+            async move { module.module.message_delete(ctx, channel_id, deleted_message_id, guild_id).await }
+        }).await;
     }
 
     async fn message_delete_bulk(&self, ctx: Context, channel_id: ChannelId, multiple_deleted_messages_ids: Vec<MessageId>, guild_id: Option<GuildId>) {
-        for module in self.shared_data.get_enabled_modules().await {
-            #[allow(unused)]
-            module.module.message_delete_bulk(ctx.clone(), channel_id, multiple_deleted_messages_ids.clone(), guild_id).await;
-        }
+        self.dispatch(&ctx, EventContext::new(EventKind::MessageDeleteBulk).guild(guild_id).channel(channel_id), |module| {
+            let (ctx, multiple_deleted_messages_ids) = (ctx.clone(), multiple_deleted_messages_ids.clone());
+            async move { module.module.message_delete_bulk(ctx, channel_id, multiple_deleted_messages_ids, guild_id).await }
+        }).await;
     }
 
     async fn message_update(&self, ctx: Context, old_if_available: Option<Message>, new: Option<Message>, event: MessageUpdateEvent) {
-        for module in self.shared_data.get_enabled_modules().await {
-            #[allow(unused)]
-            module.module.message_update(ctx.clone(), old_if_available.clone(), new.clone(), event.clone()).await;
-        }
+        let mut event_context = EventContext::new(EventKind::MessageUpdate).guild(event.guild_id).channel(event.channel_id);
+        if let Some(author) = &event.author {
+            event_context = event_context.user(author.id);
+        }
+        self.dispatch(&ctx, event_context, |module| {
+            let (ctx, old_if_available, new, event) = (ctx.clone(), old_if_available.clone(), new.clone(), event.clone());
+            async move { module.module.message_update(ctx, old_if_available, new, event).await }
+        }).await;
     }
 
     async fn ready(&self, ctx: Context, ready: Ready) {
+        self.shared_data.health.record_event();
+        self.log_connector.init_for_channel(Config::get().channels.log_channel, ctx.http.clone());
 
-        //migrate(&ctx).await;
+        self.fetch_roles(&ctx).await;
 
+        self.validate_configs(&ctx).await;
 
-        self.log_connector.init_for_channel(Config::get().channels.log_channel, ctx.http.clone());
+        self.reclaim_leaked_buttons(&ctx).await;
 
-        self.fetch_roles(&ctx).await;
+        // Ready is sent again after a reconnection : the watcher is only started once
+        if self.config_watcher.get().is_none() {
+            match ConfigWatcher::start(self.shared_data.clone(), ctx.http.clone()) {
+                Ok(watcher) => { let _ = self.config_watcher.set(watcher); }
+                Err(err) => { error!("Failed to watch module config directory : {}", err) }
+            }
+        }
 
-        let permissions = self.shared_data.permissions.read().await.clone();
-        for module in self.shared_data.get_enabled_modules().await {
+        // Disabled modules can be enabled later, so their commands are also collected.
+        // Commands can depend on the roles of each guild : the names of every guild are kept.
+        let mut guild_permissions = vec![];
+        for guild in Config::get().guild_ids() {
+            guild_permissions.push(self.shared_data.permissions(guild).await);
+        }
+        for module in self.shared_data.get_enabled_modules().await.into_iter().chain(self.shared_data.get_disabled_modules().await) {
             let mut command_names = HashSet::new();
-            for command in module.module.fetch_commands(&permissions) {
-                command_names.insert(command.name);
+            for permissions in &guild_permissions {
+                for command in module.module.fetch_commands(permissions) {
+                    command_names.insert(command.name);
+                }
             }
             *module.command_names.write().await = command_names;
         }
 
-        let mut init_message = String::new();
+        self.dispatch(&ctx, EventContext::new(EventKind::Ready), |module| {
+            let (ctx, ready) = (ctx.clone(), ready.clone());
+            async move { module.module.ready(ctx, ready).await }
+        }).await;
+
+        // Modules register their recurring jobs when they are ready
+        Scheduler::start(self.shared_data.clone(), ctx.clone());
 
+        let mut init_message = String::new();
         for module in self.shared_data.get_enabled_modules().await {
-            #[allow(unused)]
-            module.module.ready(ctx.clone(), ready.clone()).await;
             init_message += format!("{}, ", module.name).as_str();
         }
         info!("Initialized modules {}", init_message);
 
+        let dry_run = &Config::get().dry_run;
+        if dry_run.enabled {
+            warn!("Dry run enabled : kicks, bans, role changes, message deletes and timeouts are logged instead of being applied");
+        } else if dry_run.is_active() {
+            warn!("Dry run enabled for modules {:?} : their kicks, bans, role changes, message deletes and timeouts are logged instead of being applied", dry_run.modules);
+        }
+
         self.shared_data.update_commands(&ctx).await;
+        self.shared_data.health.set_startup_finished();
 
         info!("Je suis prêt à botter des culs ! >:)");
     }
 
     async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
-        for module in self.shared_data.get_enabled_modules().await {
-            #[allow(unused)]
-            module.module.interaction_create(ctx.clone(), interaction.clone()).await;
+        self.shared_data.health.record_event();
+        if !self.shared_data.is_interaction_allowed(&interaction).await {
+            info!("{} denied by the permission allow-list", EventContext::from_interaction(EventKind::InteractionCreate, &interaction));
+            respond_denied(&ctx.http, &interaction).await;
+            return;
+        }
+
+        // Components and modals are only sent to the module owning their custom id
+        let event = EventContext::from_interaction(EventKind::InteractionCreate, &interaction);
+        let mut modules = self.event_modules(&event).await;
+        if let Some(owner) = custom_id_owner(&interaction) {
+            modules.retain(|module| module.name == owner);
+        }
+        let mut failed = self.dispatch_to(&ctx, modules, event, |module| {
+            let (ctx, interaction) = (ctx.clone(), interaction.clone());
+            async move { module.module.interaction_create(ctx, interaction).await }
+        }).await;
+
+        match &interaction {
+            Interaction::Command(command) => {
+                Metrics::get().commands.with_label_values(&[command.data.name.as_str()]).inc();
+                let modules = self.shared_data.get_command_modules(&command.data.name, command.guild_id).await;
+                failed |= self.dispatch_to(&ctx, modules, EventContext::from_interaction(EventKind::Command, &interaction), |module| {
+                    let (ctx, command) = (ctx.clone(), command.clone());
+                    async move {
+                        let name = command.data.name.clone();
+                        module.module.execute_command(ctx, name.as_str(), command).await
+                    }
+                }).await;
+            }
+            Interaction::Autocomplete(command) => {
+                let modules = self.shared_data.get_command_modules(&command.data.name, command.guild_id).await;
+                self.dispatch_to(&ctx, modules, EventContext::from_interaction(EventKind::Autocomplete, &interaction), |module| {
+                    let (ctx, command) = (ctx.clone(), command.clone());
+                    async move {
+                        let name = command.data.name.clone();
+                        module.module.autocomplete(ctx, name.as_str(), command).await
+                    }
+                }).await;
+            }
+            _ => {}
         }
 
-        if let Interaction::Command(command) = interaction {
-            for module in self.shared_data.get_enabled_modules().await {
-                if module.command_names.read().await.contains(&command.data.name) {
-                    #[allow(unused)]
-                    module.module.execute_command(ctx.clone(), command.data.name.as_str(), command.clone()).await;
-                }
-            }
+        if failed {
+            respond_unanswered(&ctx.http, &interaction).await;
         }
     }
 
+    async fn shard_stage_update(&self, _: Context, event: ShardStageUpdateEvent) {
+        self.shared_data.health.set_connected(event.new == ConnectionStage::Connected);
+    }
+
     async fn thread_create(&self, ctx: Context, thread: GuildChannel) {
-        for module in self.shared_data.get_enabled_modules().await {
-            #[allow(unused)]
-            module.module.thread_create(ctx.clone(), thread.clone()).await;
-        }
+        self.dispatch(&ctx, EventContext::new(EventKind::ThreadCreate).guild(Some(thread.guild_id)).channel(thread.id), |module| {
+            let (ctx, thread) = (ctx.clone(), thread.clone());
+            async move { module.module.thread_create(ctx, thread).await }
+        }).await;
     }
 
     async fn thread_delete(&self, ctx: Context, thread: PartialGuildChannel, full_thread_data: Option<GuildChannel>) {
-        for module in self.shared_data.get_enabled_modules().await {
-            #[allow(unused)]
-            module.module.thread_delete(ctx.clone(), thread.clone(), full_thread_data.clone()).await;
-        }
+        self.dispatch(&ctx, EventContext::new(EventKind::ThreadDelete).guild(Some(thread.guild_id)).channel(thread.id), |module| {
+            let (ctx, thread, full_thread_data) = (ctx.clone(), thread.clone(), full_thread_data.clone());
+            async move { module.module.thread_delete(ctx, thread, full_thread_data).await }
+        }).await;
     }
-}
\ No newline at end of file
+}
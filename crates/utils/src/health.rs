@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::time::Duration;
+use chrono::Utc;
+use serde::Serialize;
+use crate::global_interface::BidibipSharedData;
+
+/// Connection and startup state of the bot, reported by /healthz and /readyz
+#[derive(Default)]
+pub struct Health {
+    connected: AtomicBool,
+    // Unix timestamp in milliseconds of the last gateway event (0 = none yet)
+    last_event: AtomicI64,
+    startup_finished: AtomicBool,
+}
+
+impl Health {
+    pub fn set_connected(&self, connected: bool) {
+        self.connected.store(connected, Ordering::SeqCst);
+    }
+
+    pub fn record_event(&self) {
+        self.last_event.store(Utc::now().timestamp_millis(), Ordering::SeqCst);
+    }
+
+    /// Roles are fetched and commands are registered
+    pub fn set_startup_finished(&self) {
+        self.startup_finished.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::SeqCst)
+    }
+
+    pub fn is_startup_finished(&self) -> bool {
+        self.startup_finished.load(Ordering::SeqCst)
+    }
+
+    /// Time elapsed since the last gateway event
+    pub fn last_event_age(&self) -> Option<Duration> {
+        match self.last_event.load(Ordering::SeqCst) {
+            0 => { None }
+            last_event => { Some(Duration::from_millis((Utc::now().timestamp_millis() - last_event).max(0) as u64)) }
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct HealthReport {
+    /// The gateway is connected and events are still received
+    pub alive: bool,
+    /// Alive, started and every module is loaded
+    pub ready: bool,
+    pub gateway_connected: bool,
+    pub last_event_s: Option<u64>,
+    pub startup_finished: bool,
+    /// Module name - load error
+    pub failed_modules: HashMap<String, String>,
+}
+
+impl HealthReport {
+    /// Without any event for max_event_age, the bot is not alive anymore
+    pub async fn collect(shared_data: &BidibipSharedData, max_event_age: Option<Duration>) -> Self {
+        let health = shared_data.health();
+        let last_event_age = health.last_event_age();
+        let gateway_connected = health.is_connected();
+        let event_too_old = match (max_event_age, last_event_age) {
+            (Some(max_event_age), Some(last_event_age)) => { last_event_age > max_event_age }
+            _ => { false }
+        };
+        let alive = gateway_connected && !event_too_old;
+        let startup_finished = health.is_startup_finished();
+        let failed_modules = shared_data.failed_modules().await;
+        Self {
+            alive,
+            ready: alive && startup_finished && failed_modules.is_empty(),
+            gateway_connected,
+            last_event_s: last_event_age.map(|age| age.as_secs()),
+            startup_finished,
+            failed_modules,
+        }
+    }
+}
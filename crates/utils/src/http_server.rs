@@ -0,0 +1,88 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, info};
+use crate::global_interface::BidibipSharedData;
+use crate::health::HealthReport;
+use crate::metrics::Metrics;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct HttpConfig {
+    /// Serve /healthz, /readyz and /metrics on http://<address>
+    pub enabled: bool,
+    pub address: String,
+    /// Expose the Prometheus metrics on /metrics
+    pub metrics: bool,
+    /// /healthz fails when no gateway event was received for this many seconds (0 = disabled)
+    pub max_event_age_s: u64,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: "0.0.0.0:9100".to_string(),
+            metrics: true,
+            max_event_age_s: 0,
+        }
+    }
+}
+
+/// Start the HTTP listener. Returns the address it listens to.
+pub async fn start_server(config: &HttpConfig, shared_data: Arc<BidibipSharedData>) -> std::io::Result<SocketAddr> {
+    let listener = TcpListener::bind(&config.address).await?;
+    let address = listener.local_addr()?;
+    info!("Health checks available on http://{}/healthz and http://{}/readyz", address, address);
+    if config.metrics {
+        info!("Metrics available on http://{}/metrics", address);
+    }
+    let config = Arc::new(config.clone());
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => { tokio::spawn(handle_connection(stream, config.clone(), shared_data.clone())); }
+                Err(err) => { debug!("Failed to accept HTTP connection : {}", err) }
+            }
+        }
+    });
+    Ok(address)
+}
+
+/// Answer a single request, then close the connection
+async fn handle_connection(mut stream: TcpStream, config: Arc<HttpConfig>, shared_data: Arc<BidibipSharedData>) {
+    let mut request = vec![];
+    let mut buffer = [0u8; 1024];
+    // Only the request line is needed : read until the end of the headers
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") && request.len() < 8192 {
+        match stream.read(&mut buffer).await {
+            Ok(0) | Err(_) => { break; }
+            Ok(read) => { request.extend_from_slice(&buffer[..read]) }
+        }
+    }
+    let request = String::from_utf8_lossy(&request);
+    let mut request_line = request.lines().next().unwrap_or_default().split_whitespace();
+
+    let (status, content_type, body) = match (request_line.next(), request_line.next()) {
+        (Some("GET"), Some(path @ ("/healthz" | "/readyz"))) => {
+            let max_event_age = match config.max_event_age_s {
+                0 => { None }
+                seconds => { Some(Duration::from_secs(seconds)) }
+            };
+            let report = HealthReport::collect(&shared_data, max_event_age).await;
+            let ok = if path == "/healthz" { report.alive } else { report.ready };
+            let body = serde_json::to_string(&report).unwrap_or_default() + "\n";
+            (if ok { "200 OK" } else { "503 Service Unavailable" }, "application/json", body)
+        }
+        (Some("GET"), Some("/metrics")) if config.metrics => { ("200 OK", "text/plain; version=0.0.4", Metrics::get().encode()) }
+        (Some("GET"), Some(_)) => { ("404 Not Found", "text/plain", "Not found\n".to_string()) }
+        _ => { ("405 Method Not Allowed", "text/plain", "Method not allowed\n".to_string()) }
+    };
+    let response = format!("HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}", body.len());
+    if let Err(err) = stream.write_all(response.as_bytes()).await {
+        debug!("Failed to send HTTP response : {}", err);
+    }
+}
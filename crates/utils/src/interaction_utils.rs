@@ -1,11 +1,82 @@
-use serenity::all::ComponentInteractionData;
+use serenity::all::{ComponentInteractionData, Interaction};
 use std::fmt::Display;
+use anyhow::Error;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use crate::module::{BidibipModule, LoadModule};
 
+/// Version tag of the typed custom ids
+const CUSTOM_ID_VERSION: &str = "1";
+/// Maximum length of a custom id allowed by Discord
+const MAX_CUSTOM_ID_LENGTH: usize = 100;
+
 pub fn make_custom_id<Module: BidibipModule + LoadModule<Module>>(action: &str, id: impl Display) -> String {
     format!("{}::{}::{}", Module::name(), action, id)
 }
 
+/// A typed component or modal custom id owned by a module.
+/// It is written as `module::action::version:checksum:payload` where the payload is the json value of the id,
+/// so the module (dispatch) and the action (permissions) can still be read without knowing the type.
+pub trait CustomId: Serialize + DeserializeOwned {
+    type Module: BidibipModule + LoadModule<Self::Module>;
+
+    /// Name of the action, used by the permission allow-lists
+    fn action(&self) -> &'static str;
+
+    /// Read an id written before the typed format (raw string or `make_custom_id`)
+    fn from_legacy(_custom_id: &str) -> Option<Self> { None }
+
+    fn encode(&self) -> Result<String, Error> {
+        let prefix = format!("{}::{}::{}", Self::Module::name(), self.action(), CUSTOM_ID_VERSION);
+        let payload = serde_json::to_string(self)?;
+        let custom_id = format!("{}:{:08x}:{}", prefix, checksum(&prefix, &payload), payload);
+        if custom_id.len() > MAX_CUSTOM_ID_LENGTH {
+            return Err(Error::msg(format!("Custom id is too long ({} characters) : {}", custom_id.len(), custom_id)));
+        }
+        Ok(custom_id)
+    }
+
+    /// Returns None if the id belongs to another module or type, or if it was altered
+    fn decode(custom_id: &str) -> Option<Self> {
+        let mut split = custom_id.splitn(3, "::");
+        if let (Some(module), Some(action), Some(data)) = (split.next(), split.next(), split.next()) {
+            if let Some(data) = data.strip_prefix(CUSTOM_ID_VERSION).and_then(|data| data.strip_prefix(':')) {
+                if module != Self::Module::name() {
+                    return None;
+                }
+                let (check, payload) = data.split_once(':')?;
+                let prefix = format!("{}::{}::{}", module, action, CUSTOM_ID_VERSION);
+                if u32::from_str_radix(check, 16).ok()? != checksum(&prefix, payload) {
+                    return None;
+                }
+                let value: Self = serde_json::from_str(payload).ok()?;
+                return if value.action() == action { Some(value) } else { None };
+            }
+        }
+        Self::from_legacy(custom_id)
+    }
+}
+
+/// FNV-1a hash of the id, only used to detect truncated or hand edited ids
+fn checksum(prefix: &str, payload: &str) -> u32 {
+    let mut hash: u32 = 0x811c9dc5;
+    for byte in prefix.bytes().chain(payload.bytes()) {
+        hash ^= byte as u32;
+        hash = hash.wrapping_mul(0x01000193);
+    }
+    hash
+}
+
+/// Name of the module owning the custom id of a component or modal interaction.
+/// Raw legacy ids don't have an owner and are sent to every module.
+pub fn custom_id_owner(interaction: &Interaction) -> Option<&str> {
+    let custom_id = match interaction {
+        Interaction::Component(component) => { component.data.custom_id.as_str() }
+        Interaction::Modal(modal) => { modal.data.custom_id.as_str() }
+        _ => { return None }
+    };
+    custom_id.split_once("::").map(|(module, _)| module)
+}
 
 pub trait InteractionUtils {
     fn get_custom_id_data<Module: BidibipModule + LoadModule<Module>>(&self, action: &str) -> Option<String>;
@@ -15,17 +86,11 @@ pub trait InteractionUtils {
 impl InteractionUtils for ComponentInteractionData {
     fn get_custom_id_data<Module: BidibipModule + LoadModule<Module>>(&self, action: &str) -> Option<String> {
         let mut split = self.custom_id.split("::");
-        let module = match split.next() {
-            None => { return None }
-            Some(module) => { module }
-        };
+        let module = split.next()?;
         if module != Module::name() {
             return None;
         }
-        let data_action = match split.next() {
-            None => { return None }
-            Some(data_action) => { data_action }
-        };
+        let data_action = split.next()?;
         if data_action != action {
             return None;
         }
@@ -37,20 +102,14 @@ impl InteractionUtils for ComponentInteractionData {
 
     fn get_custom_id_action<Module: BidibipModule + LoadModule<Module>>(&self) -> Option<(String, String)> {
         let mut split = self.custom_id.split("::");
-        let module = match split.next() {
-            None => { return None }
-            Some(module) => { module }
-        };
+        let module = split.next()?;
         if module != Module::name() {
             return None;
         }
-        let data_action = match split.next() {
-            None => { return None }
-            Some(data_action) => { data_action }
-        };
+        let data_action = split.next()?;
         match split.next() {
             None => { Some((data_action.to_string(), String::new())) }
             Some(payload) => { Some((data_action.to_string(), payload.to_string())) }
         }
     }
-}
\ No newline at end of file
+}
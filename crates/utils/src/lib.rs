@@ -1,5 +1,6 @@
 pub mod config;
 pub mod logger;
+pub mod rolling_file;
 pub mod global_interface;
 pub mod utilities;
 pub mod create_command_detailed;
@@ -7,4 +8,18 @@ pub mod json_to_message;
 pub mod message_reference;
 pub mod interaction_utils;
 pub mod error;
+pub mod dispatch;
+pub mod slash_command;
+pub mod storage;
+pub mod persistence;
+pub mod validation;
+pub mod reload;
+pub mod config_editor;
+pub mod permissions;
+pub mod scheduler;
+pub mod event_bus;
+pub mod actions;
+pub mod metrics;
+pub mod health;
+pub mod http_server;
 pub mod module;
\ No newline at end of file
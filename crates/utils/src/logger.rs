@@ -1,29 +1,229 @@
-use chrono::{DateTime, Utc};
-use serenity::all::{ChannelId, Http, Mentionable};
+use serenity::all::{ChannelId, Colour, CreateAttachment, CreateEmbed, CreateMessage, Http, Mentionable};
 use std::fmt::{Debug};
-use std::fs;
-use std::fs::OpenOptions;
-use std::path::Path;
-use std::sync::{Arc, RwLock};
+use std::io;
+use std::io::Write;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use anyhow::Error;
+use tokio::sync::mpsc;
 use tracing::field::{Field, Visit};
-use tracing::{Level, Subscriber};
-use tracing_subscriber::{fmt, Layer};
+use tracing::{warn, Level, Subscriber};
+use tracing_subscriber::{fmt, reload, EnvFilter, Layer, Registry};
+use tracing_subscriber::filter::{filter_fn, FilterExt, LevelFilter};
+use tracing_subscriber::fmt::MakeWriter;
 use tracing_subscriber::layer::SubscriberExt;
+use crate::assert_some;
 use crate::config::Config;
+use crate::persistence::replay_early_warnings;
+use crate::rolling_file::{RollingFile, RotationPolicy};
+use crate::utilities::TruncateText;
 
+/// Maximum count of logs waiting to be sent to Discord. Logs are dropped when it is full.
+const LOG_QUEUE_CAPACITY: usize = 1000;
+/// Time spent collecting logs before sending them in a single message
+const LOG_FLUSH_DELAY: Duration = Duration::from_secs(2);
+/// Maximum count of logs sent in a single message
+const LOG_BATCH_SIZE: usize = 200;
+/// Maximum length of the embed description (Discord allows 4096 characters)
+const EMBED_TEXT_LENGTH: usize = 4000;
+
+/// Target of the audit logs (config and permission changes...). They are always sent to the log channel :
+/// the logging.discord filter and the logging.channels routes don't apply to them.
+pub const AUDIT_TARGET: &str = "audit";
+
+/// A log to send in the log channel
+#[derive(Clone, PartialEq)]
+pub struct LogEntry {
+    pub level: Level,
+    pub target: String,
+    pub line: Option<u32>,
+    pub message: String,
+}
+
+impl LogEntry {
+    fn format(&self, occurrences: usize) -> String {
+        let icon = match self.level {
+            Level::ERROR => { ":red_circle:" }
+            Level::WARN => { ":yellow_circle:" }
+            _ => { ":green_circle:" }
+        };
+        let line = match self.line {
+            None => { String::new() }
+            Some(line) => { format!(":{line}") }
+        };
+        let message = self.message.truncate_text(900);
+        match occurrences {
+            1 => { format!("{icon} `{}{line}` {message}", self.target) }
+            occurrences => { format!("{icon} `{}{line}` {message} (x{occurrences})", self.target) }
+        }
+    }
+}
+
+/// Sends the logs to the log channel once it is known.
+/// The logs are pushed in a bounded queue drained by a single worker, which groups them in one message at a time.
+/// The queue exists from the start : the logs written before the log channel is known are sent once it is.
 pub struct DiscordLogConnector {
-    connected_log_channel: RwLock<Option<(Arc<Http>, ChannelId)>>,
+    sender: mpsc::Sender<LogEntry>,
+    // Taken by the worker when the log channel is known
+    receiver: Mutex<Option<mpsc::Receiver<LogEntry>>>,
+    dropped: Arc<AtomicUsize>,
+    flush_delay: Duration,
+}
+
+impl Default for DiscordLogConnector {
+    fn default() -> Self {
+        Self::new(LOG_QUEUE_CAPACITY, LOG_FLUSH_DELAY)
+    }
 }
 
 impl DiscordLogConnector {
-    pub fn new() -> Self {
-        Self {
-            connected_log_channel: Default::default(),
-        }
+    pub fn new(capacity: usize, flush_delay: Duration) -> Self {
+        let (sender, receiver) = mpsc::channel(capacity);
+        Self { sender, receiver: Mutex::new(Some(receiver)), dropped: Default::default(), flush_delay }
     }
 
+    /// Start sending the logs to this channel. Must be called from the tokio runtime.
+    /// Ready is sent again after a reconnection : the worker is only started once.
     pub fn init_for_channel(&self, channel: ChannelId, http: Arc<Http>) {
-        *self.connected_log_channel.write().unwrap() = Some((http, channel));
+        if let Some(receiver) = self.receiver.lock().unwrap_or_else(|err| err.into_inner()).take() {
+            tokio::spawn(log_worker(receiver, self.dropped.clone(), self.flush_delay, channel, http));
+        }
+    }
+
+    /// Queue a log. It is dropped if the queue is full, and counted in the next message.
+    pub fn push(&self, entry: LogEntry) {
+        if self.sender.try_send(entry).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+async fn log_worker(mut receiver: mpsc::Receiver<LogEntry>, dropped: Arc<AtomicUsize>, flush_delay: Duration, log_channel: ChannelId, http: Arc<Http>) {
+    let logging = &Config::get().logging;
+    let ping_level = LevelFilter::from_str(&logging.ping_level).unwrap_or_else(|_| {
+        warn!("Invalid logging.ping_level '{}', using 'error'", logging.ping_level);
+        LevelFilter::ERROR
+    });
+    let ping_cooldown = Duration::from_secs(logging.ping_cooldown_s);
+    let mut last_pings: HashMap<ChannelId, Instant> = HashMap::new();
+
+    while let Some(first) = receiver.recv().await {
+        tokio::time::sleep(flush_delay).await;
+        let mut batch = vec![first];
+        while batch.len() < LOG_BATCH_SIZE {
+            match receiver.try_recv() {
+                Ok(entry) => { batch.push(entry) }
+                Err(_) => { break; }
+            }
+        }
+
+        // Send each log to its dedicated channel, or to the log channel
+        let mut routed: Vec<(ChannelId, Vec<LogEntry>)> = vec![(log_channel, vec![])];
+        for entry in batch {
+            let channel = match entry.target.as_str() {
+                AUDIT_TARGET => { log_channel }
+                target => { logging.channel_for(target).unwrap_or(log_channel) }
+            };
+            match routed.iter_mut().find(|(other, _)| *other == channel) {
+                None => { routed.push((channel, vec![entry])) }
+                Some((_, entries)) => { entries.push(entry) }
+            }
+        }
+
+        let dropped = dropped.swap(0, Ordering::Relaxed);
+        for (channel, entries) in routed {
+            let dropped = if channel == log_channel { dropped } else { 0 };
+            if entries.is_empty() && dropped == 0 {
+                continue;
+            }
+            let ping = entries.iter().any(|entry| entry.level <= ping_level) && last_pings.get(&channel).is_none_or(|last| last.elapsed() >= ping_cooldown);
+            if ping {
+                last_pings.insert(channel, Instant::now());
+            }
+            // Messages are sent one after another : the Discord rate limits slow down the worker, and the queue fills up instead
+            if let Err(err) = channel.send_message(&http, batch_message(entries, dropped, ping)).await {
+                warn!("Failed to send logs to channel {} : {}", channel, err);
+            }
+        }
+    }
+}
+
+/// Group identical logs with a counter, then put them in an embed (and in an attachment if they don't fit)
+fn batch_message(batch: Vec<LogEntry>, dropped: usize, ping: bool) -> CreateMessage {
+    let mut grouped: Vec<(LogEntry, usize)> = vec![];
+    for entry in batch {
+        match grouped.iter_mut().find(|(other, _)| *other == entry) {
+            None => { grouped.push((entry, 1)) }
+            Some((_, occurrences)) => { *occurrences += 1 }
+        }
+    }
+    let mut lines: Vec<String> = grouped.iter().map(|(entry, occurrences)| entry.format(*occurrences)).collect();
+    if dropped > 0 {
+        lines.push(format!(":warning: {dropped} log(s) dropped : the log queue was full"));
+    }
+
+    let level = grouped.iter().map(|(entry, _)| entry.level).min().unwrap_or(Level::INFO);
+    let colour = match level {
+        Level::ERROR => { Colour::RED }
+        Level::WARN => { Colour::GOLD }
+        _ => { Colour::DARK_GREEN }
+    };
+
+    let mut description = String::new();
+    let mut truncated = false;
+    for line in &lines {
+        if description.len() + line.len() + 1 > EMBED_TEXT_LENGTH {
+            truncated = true;
+            break;
+        }
+        description += line;
+        description += "\n";
+    }
+
+    let mut message = CreateMessage::new();
+    if ping {
+        message = message.content(Config::get().roles.support.mention().to_string());
+    }
+    if truncated {
+        description += "... (full logs in logs.txt)";
+        message = message.add_file(CreateAttachment::bytes(lines.join("\n"), "logs.txt"));
+    }
+    message.embed(CreateEmbed::new().description(description).colour(colour))
+}
+
+/// Remove secrets (the Discord token) from a log message
+pub fn redact(message: &str) -> String {
+    match Config::get().token.expose() {
+        token if token.len() >= 8 => { message.replace(token, "[redacted]") }
+        _ => { message.to_string() }
+    }
+}
+
+/// Writer that removes the secrets from the written logs
+pub struct RedactedWriter<W: Write>(W);
+
+impl<W: Write> Write for RedactedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Log lines are formatted then written at once, so a secret is never split between two writes
+        self.0.write_all(redact(&String::from_utf8_lossy(buf)).as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+pub struct RedactedMakeWriter<M>(M);
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for RedactedMakeWriter<M> {
+    type Writer = RedactedWriter<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactedWriter(self.0.make_writer())
     }
 }
 
@@ -43,103 +243,135 @@ where
     S: Subscriber,
 {
     fn on_event(&self, event: &tracing::Event<'_>, _ctx: tracing_subscriber::layer::Context<'_, S>) {
-        if let Some((http, channel)) = &*self.connector.connected_log_channel.read().unwrap() {
-            let level = *event.metadata().level();
-
-            if level == Level::INFO || level == Level::WARN || level == Level::ERROR {
-                let target = event.metadata().target().to_string();
-                if target == "log" { return; }
-                let line = match event.metadata().line() {
-                    None => { String::new() }
-                    Some(line) => { format!(":{line}") }
-                };
-
-                let mut visitor = FieldMessageVisitor(String::new());
-                event.record(&mut visitor);
-                let http = http.clone();
-                let channel = *channel;
-                let support_role = Config::get().roles.support;
-                tokio::spawn(async move {
-                    match level {
-                        Level::INFO => {
-                            channel.say(&http, format!(":green_circle: `{target}{line}` {}", &visitor.0[0..std::cmp::min(900, visitor.0.len())])).await.expect("Failed to send info log");
-                        }
-                        Level::WARN => {
-                            channel.say(&http, format!(":yellow_circle: `{target}{line}` {}", &visitor.0[0..std::cmp::min(900, visitor.0.len())])).await.expect("Failed to send warning log");
-                        }
-                        Level::ERROR => {
-                            channel.say(&http, format!(":red_circle: `{target}{line}` {} {}", support_role.mention(), &visitor.0[0..std::cmp::min(900, visitor.0.len())])).await.expect("Failed to send error log");
-                        }
-                        _ => {}
-                    }
-                });
-            }
+        // Levels and targets are filtered by the logging.discord filter
+        let target = event.metadata().target();
+        // The logs of the log worker are not sent to discord to avoid loops
+        if target == module_path!() { return; }
+
+        let mut visitor = FieldMessageVisitor(String::new());
+        event.record(&mut visitor);
+        self.connector.push(LogEntry {
+            level: *event.metadata().level(),
+            target: target.to_string(),
+            line: event.metadata().line(),
+            message: redact(&visitor.0),
+        });
+    }
+}
+
+/// Destinations of the logs, each one with its own filter
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum LogSink {
+    Stdout,
+    File,
+    ErrorFile,
+    Discord,
+}
+
+impl LogSink {
+    pub const ALL: [LogSink; 4] = [LogSink::Stdout, LogSink::File, LogSink::ErrorFile, LogSink::Discord];
+
+    /// Name of the sink in the logging config
+    pub fn name(&self) -> &'static str {
+        match self {
+            LogSink::Stdout => { "stdout" }
+            LogSink::File => { "file" }
+            LogSink::ErrorFile => { "error_file" }
+            LogSink::Discord => { "discord" }
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|sink| sink.name() == name)
+    }
+
+    /// Filter directives of the sink in the config
+    pub fn configured_filter(&self) -> &'static str {
+        let logging = &Config::get().logging;
+        match self {
+            LogSink::Stdout => { &logging.stdout }
+            LogSink::File => { &logging.file }
+            LogSink::ErrorFile => { &logging.error_file }
+            LogSink::Discord => { &logging.discord }
         }
     }
 }
 
+type FilterHandle = reload::Handle<EnvFilter, Registry>;
+
+/// Filters of the logger, with the directives they were built from
+static LOG_FILTERS: OnceLock<Mutex<HashMap<LogSink, (FilterHandle, String)>>> = OnceLock::new();
+
+/// Current filter directives of each sink (empty if the logger is not initialized)
+pub fn log_filters() -> Vec<(LogSink, String)> {
+    match LOG_FILTERS.get() {
+        None => { vec![] }
+        Some(filters) => {
+            let filters = filters.lock().unwrap_or_else(|err| err.into_inner());
+            LogSink::ALL.into_iter().filter_map(|sink| filters.get(&sink).map(|(_, directives)| (sink, directives.clone()))).collect()
+        }
+    }
+}
+
+/// Replace the filter of a sink until the next restart
+pub fn set_log_filter(sink: LogSink, directives: &str) -> Result<(), Error> {
+    let filter = EnvFilter::try_new(directives)?;
+    let mut filters = assert_some!(LOG_FILTERS.get(), "Logger not initialized")?.lock().unwrap_or_else(|err| err.into_inner());
+    let (handle, current) = assert_some!(filters.get_mut(&sink), format!("No filter for sink {}", sink.name()))?;
+    handle.reload(filter)?;
+    *current = directives.to_string();
+    Ok(())
+}
+
 pub fn init_logger() -> Arc<DiscordLogConnector> {
     let log_directory = &Config::get().log_directory;
+    let policy = RotationPolicy::from(&Config::get().log_rotation);
 
-    // Create log directory
-    fs::create_dir_all(log_directory).expect("Failed to create log directories");
-    let log_file = Path::join(log_directory, "log.log");
-    let error_file = Path::join(log_directory, "error.log");
-
-    // Archive previous log files
-    if fs::exists(&error_file).expect("Cannot determine if previous error file exists") {
-        let last_write_time: DateTime<Utc> = fs::metadata(&error_file).expect("Failed to get error metadata").modified().expect("Failed to get error modified infos").into();
-        let last_write_time = format!("{last_write_time}").replace(":", "-").replace(" ", "_");
-        fs::rename(&error_file, Path::join(log_directory, format!("error_{}.log", last_write_time))).expect("Failed to rename old error file");
-    }
-    if fs::exists(&log_file).expect("Cannot determine if previous log file exists") {
-        let last_write_time: DateTime<Utc> = fs::metadata(&log_file).expect("Failed to get log metadata").modified().expect("Failed to get log modified infos").into();
-        let last_write_time = format!("{last_write_time}").replace(":", "-").replace(" ", "_");
-        fs::rename(&log_file, Path::join(log_directory, format!("log_{}.log", last_write_time))).expect("Failed to rename old log file");
-    }
-
-    // Create log files and channels
-    let err_file = OpenOptions::new()
-        .append(true)
-        .create(true)
-        .open(error_file)
-        .unwrap();
-    let debug_file = OpenOptions::new()
-        .append(true)
-        .create(true)
-        .open(log_file)
-        .unwrap();
-    let connector = Arc::new(DiscordLogConnector::new());
+    // Create log files, the files of the previous run are archived
+    let err_file = RollingFile::open(log_directory, "error", policy.clone()).expect("Failed to open error log file");
+    let debug_file = RollingFile::open(log_directory, "log", policy).expect("Failed to open log file");
+    let connector = Arc::new(DiscordLogConnector::default());
 
     // Setup logger
-    let subscriber = tracing_subscriber::Registry::default()
-        .with(
-            // stdout layer, to view everything in the console
-            fmt::layer()
-                .compact()
-                .with_ansi(true)
-                .with_filter(tracing_subscriber::filter::LevelFilter::from_level(Level::INFO))
-        )
-        .with(
-            // log-error file, to log the errors that arise
-            fmt::layer()
-                .json()
-                .with_writer(err_file)
-                .with_filter(tracing_subscriber::filter::LevelFilter::from_level(Level::WARN))
-        )
-        .with(
-            // log-debug file, to log the debug
-            fmt::layer()
-                .json()
-                .with_writer(debug_file)
-                .with_filter(tracing_subscriber::filter::LevelFilter::from_level(Level::INFO))
-        )
-        .with(
-            // log to discord channels
-            ChannelWriter { connector: connector.clone() }
-        );
+    let mut filters = HashMap::new();
+    let mut filter = |sink: LogSink| {
+        let directives = sink.configured_filter();
+        let (filter, handle) = reload::Layer::new(EnvFilter::try_new(directives).unwrap_or_else(|err| panic!("Invalid logging.{} filter '{}' : {}", sink.name(), directives, err)));
+        filters.insert(sink, (handle, directives.to_string()));
+        filter
+    };
+    let layers = vec![
+        // stdout layer, to view everything in the console
+        fmt::layer()
+            .compact()
+            .with_ansi(true)
+            .with_writer(RedactedMakeWriter(io::stdout))
+            .with_filter(filter(LogSink::Stdout))
+            .boxed(),
+        // log-error file, to log the errors that arise
+        fmt::layer()
+            .json()
+            .with_writer(RedactedMakeWriter(err_file))
+            .with_filter(filter(LogSink::ErrorFile))
+            .boxed(),
+        // log-debug file, to log the debug
+        fmt::layer()
+            .json()
+            .with_writer(RedactedMakeWriter(debug_file))
+            .with_filter(filter(LogSink::File))
+            .boxed(),
+        // log to discord channels, the audit logs can't be filtered out
+        ChannelWriter { connector: connector.clone() }
+            .with_filter(filter(LogSink::Discord).or(filter_fn(|metadata| metadata.target() == AUDIT_TARGET)))
+            .boxed(),
+    ];
+    let subscriber = tracing_subscriber::Registry::default().with(layers);
+    if LOG_FILTERS.set(Mutex::new(filters)).is_err() {
+        panic!("Logger already initialized");
+    }
 
     tracing::subscriber::set_global_default(subscriber).expect("Failed to initialize tracing subscriber");
+    replay_early_warnings();
 
     connector
 }
\ No newline at end of file
@@ -4,7 +4,7 @@ use serde::{Deserialize, Serialize};
 use serenity::all::{ChannelId, GuildId, Http, Message, MessageId};
 use serenity::Error;
 
-#[derive(Default, Copy, Clone, Serialize, Deserialize, Hash, Eq)]
+#[derive(Default, Copy, Clone, Serialize, Deserialize, Hash, PartialEq, Eq)]
 pub struct MessageReference {
     id: MessageId,
     channel: ChannelId
@@ -54,12 +54,6 @@ impl MessageReference {
 
 
     pub async fn message(&self, http: &Arc<Http>) -> Result<Message, Error> {
-        Ok(self.channel.message(http, self.id).await?)
-    }
-}
-
-impl PartialEq for MessageReference {
-    fn eq(&self, other: &Self) -> bool {
-        self.channel == other.channel && self.id == other.id
+        self.channel.message(http, self.id).await
     }
 }
\ No newline at end of file
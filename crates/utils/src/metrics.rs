@@ -0,0 +1,91 @@
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use prometheus::{Encoder, Gauge, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts, Registry, TextEncoder};
+use serenity::all::ShardManager;
+use tracing::debug;
+
+/// Delay between two reads of the gateway latency
+const GATEWAY_LATENCY_INTERVAL: Duration = Duration::from_secs(30);
+
+static METRICS: OnceLock<Metrics> = OnceLock::new();
+
+/// Prometheus metrics of the bot. They are always recorded, and only exposed if the HTTP listener is enabled.
+pub struct Metrics {
+    registry: Registry,
+    /// Events dispatched to the modules, per event kind and module
+    pub events: IntCounterVec,
+    /// Duration of the module hooks, per event kind and module
+    pub handler_duration: HistogramVec,
+    /// Errors, timeouts and panics of the module hooks
+    pub module_errors: IntCounterVec,
+    /// Slash commands executed, per command name
+    pub commands: IntCounterVec,
+    /// Sanctions applied, per action type
+    pub sanctions: IntCounterVec,
+    /// Potential spammers detected by the anti spam
+    pub spam_detections: IntCounter,
+    pub gateway_latency: Gauge,
+    /// Members of the main guild, counted by the member count module
+    pub members: IntGauge,
+    pub cached_users: IntGauge,
+    pub cached_guilds: IntGauge,
+    pub cached_channels: IntGauge,
+}
+
+impl Metrics {
+    pub fn get() -> &'static Metrics {
+        METRICS.get_or_init(|| Metrics::new().expect("Failed to register metrics"))
+    }
+
+    fn new() -> Result<Self, prometheus::Error> {
+        let registry = Registry::new_custom(Some("bidibip".to_string()), None)?;
+        let metrics = Self {
+            events: IntCounterVec::new(Opts::new("events_total", "Events dispatched to the modules"), &["event", "module"])?,
+            handler_duration: HistogramVec::new(HistogramOpts::new("handler_duration_seconds", "Duration of the module event handlers"), &["event", "module"])?,
+            module_errors: IntCounterVec::new(Opts::new("module_errors_total", "Errors, timeouts and panics of the module event handlers"), &["module"])?,
+            commands: IntCounterVec::new(Opts::new("commands_total", "Slash commands executed"), &["command"])?,
+            sanctions: IntCounterVec::new(Opts::new("sanctions_total", "Sanctions applied"), &["action"])?,
+            spam_detections: IntCounter::new("spam_detections_total", "Potential spammers detected by the anti spam")?,
+            gateway_latency: Gauge::new("gateway_latency_seconds", "Latency of the Discord gateway heartbeat")?,
+            members: IntGauge::new("members", "Members of the main guild")?,
+            cached_users: IntGauge::new("cached_users", "Users in the cache")?,
+            cached_guilds: IntGauge::new("cached_guilds", "Guilds in the cache")?,
+            cached_channels: IntGauge::new("cached_channels", "Guild channels in the cache")?,
+            registry,
+        };
+        metrics.registry.register(Box::new(metrics.events.clone()))?;
+        metrics.registry.register(Box::new(metrics.handler_duration.clone()))?;
+        metrics.registry.register(Box::new(metrics.module_errors.clone()))?;
+        metrics.registry.register(Box::new(metrics.commands.clone()))?;
+        metrics.registry.register(Box::new(metrics.sanctions.clone()))?;
+        metrics.registry.register(Box::new(metrics.spam_detections.clone()))?;
+        metrics.registry.register(Box::new(metrics.gateway_latency.clone()))?;
+        metrics.registry.register(Box::new(metrics.members.clone()))?;
+        metrics.registry.register(Box::new(metrics.cached_users.clone()))?;
+        metrics.registry.register(Box::new(metrics.cached_guilds.clone()))?;
+        metrics.registry.register(Box::new(metrics.cached_channels.clone()))?;
+        Ok(metrics)
+    }
+
+    /// Metrics in the Prometheus text format
+    pub fn encode(&self) -> String {
+        let mut buffer = vec![];
+        if let Err(err) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            debug!("Failed to encode metrics : {}", err);
+        }
+        String::from_utf8_lossy(&buffer).to_string()
+    }
+}
+
+/// Read the latency of the shards regularly
+pub fn watch_gateway_latency(shard_manager: Arc<ShardManager>) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(GATEWAY_LATENCY_INTERVAL).await;
+            let latencies: Vec<Duration> = shard_manager.runners.lock().await.values().filter_map(|runner| runner.latency).collect();
+            if let Some(latency) = latencies.iter().max() {
+                Metrics::get().gateway_latency.set(latency.as_secs_f64());
+            }
+        }
+    });
+}
@@ -1,16 +1,36 @@
+use std::any::Any;
 use std::sync::Arc;
 use anyhow::Error;
-use serenity::all::{AuditLogEntry, ChannelId, CommandInteraction, Context, GuildChannel, GuildId, GuildMemberUpdateEvent, Interaction, Member, Message, MessageId, MessageUpdateEvent, PartialGuildChannel, Ready, User};
+use serenity::all::{AuditLogEntry, ChannelId, CommandInteraction, Context, GuildChannel, GuildId, GuildMemberUpdateEvent, Http, Interaction, Member, Message, MessageId, MessageUpdateEvent, PartialGuildChannel, Ready, User};
+use crate::config::ConfigMigration;
 use crate::create_command_detailed::CreateCommandDetailed;
+use crate::dispatch::ModuleEvents;
 use crate::error::BidibipError;
 use crate::global_interface::{BidibipSharedData, PermissionData};
+use crate::scheduler::ScheduledJob;
+use crate::storage::TableMigration;
+use crate::event_bus::{InternalEvent, InternalEvents};
+use crate::validation::ConfigValidator;
 
 #[serenity::async_trait]
-pub trait BidibipModule: Sync + Send {
+pub trait BidibipModule: Any + Sync + Send {
     // When one of the specified command is executed
     async fn execute_command(&self, _: Context, _: &str, _: CommandInteraction) -> Result<(), BidibipError> { Ok(()) }
+    // When the user is filling an option of one of the specified command that has autocompletion enabled
+    async fn autocomplete(&self, _: Context, _: &str, _: CommandInteraction) -> Result<(), BidibipError> { Ok(()) }
     // Get a list of available commands for this module
     fn fetch_commands(&self, _: &PermissionData) -> Vec<CreateCommandDetailed> { vec![] }
+    // Report the problems of the module config (missing ids, unknown channels or roles...). The module is disabled if any problem is found.
+    async fn validate_config(&self, _: &mut ConfigValidator) {}
+    // Read the module config file again and replace the current config only if the validator doesn't find any problem.
+    // Returns false if the module doesn't have a reloadable config.
+    async fn reload_config(&self, _: &mut ConfigValidator) -> Result<bool, Error> { Ok(false) }
+    // Drop the stored buttons whose message was deleted without the buttons being handled, and describe each leak.
+    async fn reclaim_leaked_buttons(&self, _: &Http) -> Result<Vec<String>, Error> { Ok(vec![]) }
+    // When a job registered by this module in the scheduler is due
+    async fn run_job(&self, _: Context, _: ScheduledJob) -> Result<(), BidibipError> { Ok(()) }
+    // When another module publishes an internal event this module subscribed to (see LoadModule::subscriptions)
+    async fn internal_event(&self, _: Context, _: InternalEvent) -> Result<(), BidibipError> { Ok(()) }
 
     async fn channel_create(&self, _: Context, _: GuildChannel) -> Result<(), BidibipError> {
         Ok(())
@@ -81,8 +101,18 @@ pub trait BidibipModule: Sync + Send {
 pub trait LoadModule<T: BidibipModule> {
     // Module display name
     fn name() -> &'static str;
-    // Module display name
+    // Module description
     fn description() -> &'static str;
+    // Events this module should receive (commands are always routed using fetch_commands)
+    fn events() -> ModuleEvents;
+    // Ordered upgrade steps of the module config (see MigrationRegistry)
+    fn migrations() -> Vec<ConfigMigration> { vec![] }
+    // Ordered upgrade steps of the values stored in the given table of the module (see Storage::table)
+    fn table_migrations(_: &str) -> Vec<TableMigration> { vec![] }
+    // Component actions (custom id `module::action::...`) that can be restricted with an allow-list
+    fn permission_actions() -> Vec<&'static str> { vec![] }
+    // Internal events published by other modules this module should receive
+    fn subscriptions() -> InternalEvents { InternalEvents::empty() }
     // Module constructor
     async fn load(shared_data: &Arc<BidibipSharedData>) -> Result<T, Error>;
 }
@@ -0,0 +1,121 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{Display, Formatter};
+use anyhow::Error;
+use serde::{Deserialize, Serialize};
+use serenity::all::{CreateCommandPermission, GuildId, Interaction, Member, Mentionable, RoleId, UserId};
+use tokio::sync::RwLock;
+use crate::config::Config;
+use crate::storage::{Storage, Table};
+
+/// Roles and users allowed to use a command or a component action.
+/// An empty list doesn't restrict anything : Discord permissions (default_member_permissions) apply.
+#[derive(Serialize, Deserialize, Default, Clone, PartialEq)]
+pub struct AllowList {
+    #[serde(default)]
+    pub roles: BTreeSet<RoleId>,
+    #[serde(default)]
+    pub users: BTreeSet<UserId>,
+}
+
+impl AllowList {
+    pub fn is_empty(&self) -> bool {
+        self.roles.is_empty() && self.users.is_empty()
+    }
+
+    pub fn allows(&self, user: UserId, roles: &[RoleId]) -> bool {
+        self.users.contains(&user) || roles.iter().any(|role| self.roles.contains(role))
+    }
+
+    /// Discord command permissions matching this list. The administrators keep the access to the command.
+    pub fn command_permissions(&self, guild: GuildId) -> Vec<CreateCommandPermission> {
+        if self.is_empty() {
+            return vec![];
+        }
+        let mut permissions = vec![CreateCommandPermission::everyone(guild, false), CreateCommandPermission::role(Config::get().guild_roles(guild).administrator, true)];
+        permissions.extend(self.roles.iter().map(|role| CreateCommandPermission::role(*role, true)));
+        permissions.extend(self.users.iter().map(|user| CreateCommandPermission::user(*user, true)));
+        permissions
+    }
+}
+
+impl Display for AllowList {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return f.write_str("permissions Discord");
+        }
+        let mentions: Vec<String> = self.roles.iter().map(|role| role.mention().to_string()).chain(self.users.iter().map(|user| user.mention().to_string())).collect();
+        f.write_str(mentions.join(", ").as_str())
+    }
+}
+
+/// Permission key of a command (ex: `/sanction`)
+pub fn command_key(name: &str) -> String {
+    format!("/{}", name)
+}
+
+/// Permission key of a component action, read from its custom id (`module::action::payload` gives `module::action`)
+pub fn action_key(custom_id: &str) -> Option<String> {
+    let mut split = custom_id.split("::");
+    let module = split.next()?;
+    let action = split.next()?;
+    if module.is_empty() || action.is_empty() {
+        return None;
+    }
+    Some(format!("{}::{}", module, action))
+}
+
+/// Permission key, user and roles of the member behind an interaction. Modals are checked by the component that opened them.
+pub fn interaction_permission(interaction: &Interaction) -> Option<(GuildId, String, UserId, Vec<RoleId>)> {
+    let roles = |member: Option<&Member>| member.map(|member| member.roles.clone()).unwrap_or_default();
+    match interaction {
+        Interaction::Command(command) => { Some((command.guild_id?, command_key(&command.data.name), command.user.id, roles(command.member.as_deref()))) }
+        Interaction::Autocomplete(command) => { Some((command.guild_id?, command_key(&command.data.name), command.user.id, roles(command.member.as_deref()))) }
+        Interaction::Component(component) => { Some((component.guild_id?, action_key(&component.data.custom_id)?, component.user.id, roles(component.member.as_ref()))) }
+        _ => { None }
+    }
+}
+
+/// Allow-lists of each guild, stored in the storage and cached in memory
+pub struct PermissionRules {
+    table: Table<(GuildId, String), AllowList>,
+    allow_lists: RwLock<HashMap<(GuildId, String), AllowList>>,
+}
+
+impl PermissionRules {
+    pub fn load() -> Result<Self, Error> {
+        let table = Storage::get().shared_table("permissions")?;
+        let allow_lists = table.entries()?.into_iter().collect();
+        Ok(Self { table, allow_lists: RwLock::new(allow_lists) })
+    }
+
+    pub async fn get(&self, guild: GuildId, key: &str) -> AllowList {
+        self.allow_lists.read().await.get(&(guild, key.to_string())).cloned().unwrap_or_default()
+    }
+
+    /// Every restricted command or action of the guild, sorted by key
+    pub async fn guild_allow_lists(&self, guild: GuildId) -> Vec<(String, AllowList)> {
+        let mut allow_lists: Vec<(String, AllowList)> = self.allow_lists.read().await.iter().filter(|((list_guild, _), _)| *list_guild == guild).map(|((_, key), list)| (key.clone(), list.clone())).collect();
+        allow_lists.sort_by(|a, b| a.0.cmp(&b.0));
+        allow_lists
+    }
+
+    /// Replace the allow-list of a command or action. An empty list removes the restriction.
+    pub async fn set(&self, guild: GuildId, key: &str, allow_list: AllowList) -> Result<(), Error> {
+        let mut allow_lists = self.allow_lists.write().await;
+        let entry = (guild, key.to_string());
+        if allow_list.is_empty() {
+            self.table.remove(&entry)?;
+            allow_lists.remove(&entry);
+        } else {
+            self.table.set(&entry, &allow_list)?;
+            allow_lists.insert(entry, allow_list);
+        }
+        Ok(())
+    }
+
+    /// Administrators are always allowed, so nobody can be locked out of the permission command
+    pub async fn is_allowed(&self, guild: GuildId, key: &str, user: UserId, roles: &[RoleId]) -> bool {
+        let allow_list = self.get(guild, key).await;
+        allow_list.is_empty() || roles.contains(&Config::get().guild_roles(guild).administrator) || allow_list.allows(user, roles)
+    }
+}
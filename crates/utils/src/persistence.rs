@@ -0,0 +1,153 @@
+use std::fs;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use anyhow::Error;
+use chrono::{DateTime, Local, NaiveDateTime};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tracing::{error, warn};
+use crate::assert_some;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct BackupConfig {
+    /// Number of backups kept for each persisted file
+    pub max_backups: usize,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            max_backups: 10,
+        }
+    }
+}
+
+const BACKUP_DATE_FORMAT: &str = "%Y%m%d-%H%M%S%.3f";
+// Backups created before the milliseconds were added to their name
+const LEGACY_BACKUP_DATE_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+// Warnings raised before the logger is initialized
+static EARLY_WARNINGS: Mutex<Vec<String>> = Mutex::new(vec![]);
+
+/// Log the warning, or keep it until the logger is initialized
+fn warn_or_defer(message: String) {
+    if tracing::dispatcher::has_been_set() {
+        warn!("{}", message);
+    } else {
+        EARLY_WARNINGS.lock().unwrap_or_else(|err| err.into_inner()).push(message);
+    }
+}
+
+/// Warnings raised before the logger was initialized (ex: a config loaded from a backup)
+pub fn take_early_warnings() -> Vec<String> {
+    std::mem::take(&mut *EARLY_WARNINGS.lock().unwrap_or_else(|err| err.into_inner()))
+}
+
+/// Log the warnings raised before the logger was initialized. They keep the target of this module, so they are
+/// filtered and routed like the other persistence logs (the logs of the logger itself are not sent to Discord).
+pub fn replay_early_warnings() {
+    for warning in take_early_warnings() {
+        warn!("{}", warning);
+    }
+}
+
+fn backup_directory(path: &Path) -> Result<PathBuf, Error> {
+    Ok(assert_some!(path.parent(), "Failed to get parent path")?.join("backups"))
+}
+
+fn file_name(path: &Path) -> Result<String, Error> {
+    Ok(assert_some!(path.file_name(), "Invalid file name")?.to_string_lossy().to_string())
+}
+
+/// Backups of the given file, newest first
+pub fn list_backups(path: &Path) -> Result<Vec<(NaiveDateTime, PathBuf)>, Error> {
+    let directory = backup_directory(path)?;
+    if !directory.exists() {
+        return Ok(vec![]);
+    }
+    let prefix = format!("{}.", file_name(path)?);
+    let mut backups = vec![];
+    for entry in fs::read_dir(directory)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if let Some(date) = name.strip_prefix(prefix.as_str()) {
+            if let Ok(date) = NaiveDateTime::parse_from_str(date, BACKUP_DATE_FORMAT).or_else(|_| NaiveDateTime::parse_from_str(date, LEGACY_BACKUP_DATE_FORMAT)) {
+                backups.push((date, entry.path()));
+            }
+        }
+    }
+    backups.sort_by_key(|backup| std::cmp::Reverse(backup.0));
+    Ok(backups)
+}
+
+/// Copy the current content of the file to a timestamped backup, and remove the oldest backups.
+/// Nothing is done if the file does not exist yet or if backups are disabled.
+pub fn backup_file(path: &Path, config: &BackupConfig) -> Result<Option<PathBuf>, Error> {
+    if !path.exists() || config.max_backups == 0 {
+        return Ok(None);
+    }
+    Ok(Some(create_backup(path, config)?))
+}
+
+/// Copy the current content of the file to a new timestamped backup, and remove the oldest backups.
+pub fn create_backup(path: &Path, config: &BackupConfig) -> Result<PathBuf, Error> {
+    let backups = list_backups(path)?;
+    let directory = backup_directory(path)?;
+    fs::create_dir_all(&directory)?;
+    let backup = directory.join(format!("{}.{}", file_name(path)?, Local::now().format(BACKUP_DATE_FORMAT)));
+    write_atomic(&backup, &fs::read(path)?)?;
+
+    // Rotate
+    for (_, old) in backups.iter().filter(|(_, old)| *old != backup).skip(config.max_backups.max(1) - 1) {
+        if let Err(err) = fs::remove_file(old) {
+            error!("Failed to remove old backup {} : {}", old.display(), err);
+        }
+    }
+    Ok(backup)
+}
+
+/// Write the file content to a temporary file, flush it to the disk, then replace the file.
+/// The file is never left partially written.
+pub fn write_atomic(path: &Path, content: &[u8]) -> Result<(), Error> {
+    let parent = assert_some!(path.parent(), "Failed to get parent path")?;
+    fs::create_dir_all(parent)?;
+    let temp = parent.join(format!(".{}.tmp", file_name(path)?));
+    {
+        let mut file = File::create(&temp)?;
+        file.write_all(content)?;
+        file.sync_all()?;
+    }
+    fs::rename(&temp, path)?;
+    // Persist the rename itself
+    #[cfg(unix)]
+    File::open(parent)?.sync_all()?;
+    Ok(())
+}
+
+/// Backup the previous version of the file, then write the new content atomically
+pub fn save_file(path: &Path, content: &[u8], config: &BackupConfig) -> Result<(), Error> {
+    if let Err(err) = backup_file(path, config) {
+        warn_or_defer(format!("Failed to backup {} : {}", path.display(), err));
+    }
+    write_atomic(path, content)
+}
+
+/// Read and deserialize a json file. If the file is missing or invalid, the newest valid backup is used instead.
+pub fn load_json<T: DeserializeOwned>(path: &Path) -> Result<T, Error> {
+    let error = match fs::read_to_string(path).map_err(Error::from).and_then(|data| Ok(serde_json::from_str(&data)?)) {
+        Ok(data) => { return Ok(data) }
+        Err(err) => { err }
+    };
+
+    for (date, backup) in list_backups(path)? {
+        if let Ok(data) = fs::read_to_string(&backup).map_err(Error::from).and_then(|data| Ok(serde_json::from_str(&data)?)) {
+            let date: DateTime<Local> = assert_some!(date.and_local_timezone(Local).earliest(), "Invalid backup date")?;
+            warn_or_defer(format!("Failed to load {} ({}). Using backup from {} instead", path.display(), error, date.format("%d/%m/%Y %H:%M:%S")));
+            return Ok(data);
+        }
+    }
+    Err(error)
+}
@@ -0,0 +1,102 @@
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::Error;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serenity::all::Http;
+use tokio::sync::mpsc;
+use tracing::{error, info};
+use crate::config::Config;
+use crate::global_interface::BidibipSharedData;
+
+/// Result of a module config reload
+pub enum ConfigReload {
+    Reloaded,
+    // The new config was rejected, the previous one is kept
+    Invalid(Vec<String>),
+    // The file could not be read or deserialized
+    Unreadable(String),
+    Unsupported,
+    UnknownModule,
+}
+
+impl Display for ConfigReload {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigReload::Reloaded => { f.write_str("Configuration rechargée") }
+            ConfigReload::Invalid(issues) => {
+                write!(f, "Configuration invalide, l'ancienne configuration est conservée :")?;
+                for issue in issues {
+                    write!(f, "\n- {}", issue)?;
+                }
+                Ok(())
+            }
+            ConfigReload::Unreadable(error) => { write!(f, "Impossible de lire la configuration : {}", error) }
+            ConfigReload::Unsupported => { f.write_str("Ce module n'a pas de configuration rechargeable") }
+            ConfigReload::UnknownModule => { f.write_str("Ce module n'existe pas") }
+        }
+    }
+}
+
+/// Delay used to group the events of a single file modification
+const DEBOUNCE_DELAY: Duration = Duration::from_millis(500);
+
+/// Watch the module config directory, and reload the module configs when their file is modified
+pub struct ConfigWatcher {
+    _watcher: RecommendedWatcher,
+}
+
+impl ConfigWatcher {
+    pub fn start(shared_data: Arc<BidibipSharedData>, http: Arc<Http>) -> Result<Self, Error> {
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            match event {
+                Ok(event) => {
+                    if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                        for path in event.paths {
+                            let _ = sender.send(path);
+                        }
+                    }
+                }
+                Err(err) => { error!("Config watcher error : {}", err) }
+            }
+        })?;
+        watcher.watch(&Config::get().module_config_directory, RecursiveMode::NonRecursive)?;
+
+        tokio::spawn(async move {
+            while let Some(path) = receiver.recv().await {
+                tokio::time::sleep(DEBOUNCE_DELAY).await;
+                let mut paths = HashSet::from([path]);
+                while let Ok(path) = receiver.try_recv() {
+                    paths.insert(path);
+                }
+
+                for path in paths {
+                    let module = match module_name(&path) {
+                        None => { continue; }
+                        Some(module) => { module }
+                    };
+                    // Skip the files written by the bot itself
+                    if !Config::get().module_config_changed(&path) {
+                        continue;
+                    }
+                    match shared_data.reload_module_config(&http, module.as_str()).await {
+                        ConfigReload::Reloaded => { info!("Reloaded config of module {} from {}", module, path.display()) }
+                        ConfigReload::UnknownModule => {}
+                        result => { error!("Failed to reload config of module {} : {}", module, result) }
+                    }
+                }
+            }
+        });
+
+        info!("Watching module configs in {}", Config::get().module_config_directory.display());
+        Ok(Self { _watcher: watcher })
+    }
+}
+
+/// Module owning the given config file (<module>_config.json)
+fn module_name(path: &Path) -> Option<String> {
+    path.file_name()?.to_str()?.strip_suffix("_config.json").map(|name| name.to_string())
+}
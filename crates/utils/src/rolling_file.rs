@@ -0,0 +1,187 @@
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use chrono::{DateTime, NaiveDate, Utc};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use tracing_subscriber::fmt::MakeWriter;
+use crate::config::LogRotationConfig;
+
+/// When the log files are rotated, and how long the rotated files are kept
+#[derive(Clone, Debug)]
+pub struct RotationPolicy {
+    /// Start a new file every day
+    pub daily: bool,
+    /// Start a new file when the current one would exceed this size in bytes (0 = no limit)
+    pub max_file_size: u64,
+    /// Compress the rotated files with gzip
+    pub compress: bool,
+    /// Delete the rotated files older than this
+    pub max_age: Option<Duration>,
+    /// Delete the oldest rotated files when they exceed this total size in bytes (0 = no limit)
+    pub max_total_size: u64,
+}
+
+impl From<&LogRotationConfig> for RotationPolicy {
+    fn from(config: &LogRotationConfig) -> Self {
+        Self {
+            daily: config.daily,
+            max_file_size: config.max_file_size_mb * 1024 * 1024,
+            compress: config.compress,
+            max_age: match config.max_age_days {
+                0 => { None }
+                days => { Some(Duration::from_secs(days * 24 * 3600)) }
+            },
+            max_total_size: config.max_total_size_mb * 1024 * 1024,
+        }
+    }
+}
+
+struct RollingState {
+    directory: PathBuf,
+    name: String,
+    policy: RotationPolicy,
+    file: File,
+    size: u64,
+    day: NaiveDate,
+}
+
+/// Log file `<name>.log`, rotated to `<name>_<date>.log(.gz)` according to the rotation policy
+#[derive(Clone)]
+pub struct RollingFile {
+    state: Arc<Mutex<RollingState>>,
+}
+
+impl RollingFile {
+    /// Archive the file left by the previous run, then start a new one
+    pub fn open(directory: &Path, name: &str, policy: RotationPolicy) -> io::Result<Self> {
+        fs::create_dir_all(directory)?;
+        let path = current_path(directory, name);
+        if path.exists() {
+            let last_write_time: DateTime<Utc> = fs::metadata(&path)?.modified()?.into();
+            archive(directory, name, &policy, last_write_time)?;
+        } else {
+            apply_retention(directory, name, &policy);
+        }
+        let file = OpenOptions::new().append(true).create(true).open(&path)?;
+        Ok(Self { state: Arc::new(Mutex::new(RollingState { directory: directory.to_path_buf(), name: name.to_string(), policy, file, size: 0, day: Utc::now().date_naive() })) })
+    }
+}
+
+impl RollingState {
+    fn should_rotate(&self, incoming: usize) -> bool {
+        if self.size == 0 {
+            return false;
+        }
+        (self.policy.daily && Utc::now().date_naive() != self.day) || (self.policy.max_file_size > 0 && self.size + incoming as u64 > self.policy.max_file_size)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        archive(&self.directory, &self.name, &self.policy, Utc::now())?;
+        self.file = OpenOptions::new().append(true).create(true).open(current_path(&self.directory, &self.name))?;
+        self.size = 0;
+        self.day = Utc::now().date_naive();
+        Ok(())
+    }
+}
+
+fn current_path(directory: &Path, name: &str) -> PathBuf {
+    directory.join(format!("{name}.log"))
+}
+
+/// Rename the current file, then compress it and delete the old files in the background
+fn archive(directory: &Path, name: &str, policy: &RotationPolicy, date: DateTime<Utc>) -> io::Result<()> {
+    let date = date.format("%Y-%m-%d_%H-%M-%S%.3f");
+    let mut archived = directory.join(format!("{name}_{date}.log"));
+    let mut index = 1;
+    while archived.exists() || archived.with_extension("log.gz").exists() {
+        archived = directory.join(format!("{name}_{date}_{index}.log"));
+        index += 1;
+    }
+    fs::rename(current_path(directory, name), &archived)?;
+
+    let (directory, name, policy) = (directory.to_path_buf(), name.to_string(), policy.clone());
+    std::thread::spawn(move || {
+        if policy.compress {
+            if let Err(err) = compress(&archived) {
+                // The logger can't log its own errors
+                eprintln!("Failed to compress log file {} : {}", archived.display(), err);
+            }
+        }
+        apply_retention(&directory, &name, &policy);
+    });
+    Ok(())
+}
+
+fn compress(path: &Path) -> io::Result<()> {
+    let compressed = path.with_extension("log.gz");
+    let mut encoder = GzEncoder::new(File::create(&compressed)?, Compression::default());
+    io::copy(&mut File::open(path)?, &mut encoder)?;
+    encoder.finish()?.sync_all()?;
+    fs::remove_file(path)
+}
+
+/// Delete the rotated files that are too old, then the oldest ones until they fit in the maximum total size
+fn apply_retention(directory: &Path, name: &str, policy: &RotationPolicy) {
+    let prefix = format!("{name}_");
+    let mut archives: Vec<(PathBuf, SystemTime, u64)> = match fs::read_dir(directory) {
+        Ok(entries) => {
+            entries.flatten()
+                .filter(|entry| entry.file_name().to_str().is_some_and(|file| file.starts_with(&prefix) && (file.ends_with(".log") || file.ends_with(".log.gz"))))
+                .filter_map(|entry| entry.metadata().ok().and_then(|metadata| Some((entry.path(), metadata.modified().ok()?, metadata.len()))))
+                .collect()
+        }
+        Err(err) => {
+            eprintln!("Failed to read log directory {} : {}", directory.display(), err);
+            return;
+        }
+    };
+    // Most recent first
+    archives.sort_by_key(|(_, modified, _)| std::cmp::Reverse(*modified));
+
+    let mut total_size = 0;
+    for (path, modified, size) in archives {
+        total_size += size;
+        let too_old = policy.max_age.is_some_and(|max_age| modified.elapsed().is_ok_and(|age| age > max_age));
+        let too_big = policy.max_total_size > 0 && total_size > policy.max_total_size;
+        if too_old || too_big {
+            if let Err(err) = fs::remove_file(&path) {
+                eprintln!("Failed to delete old log file {} : {}", path.display(), err);
+            }
+        }
+    }
+}
+
+pub struct RollingFileWriter(Arc<Mutex<RollingState>>);
+
+impl Write for RollingFileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut state = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        if state.should_rotate(buf.len()) {
+            // Keep writing in the current file if it can't be rotated
+            if let Err(err) = state.rotate() {
+                eprintln!("Failed to rotate log file {} : {}", state.name, err);
+            }
+        }
+        state.file.write_all(buf)?;
+        state.size += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.lock().unwrap_or_else(|err| err.into_inner()).file.flush()
+    }
+}
+
+impl<'a> MakeWriter<'a> for RollingFile {
+    type Writer = RollingFileWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RollingFileWriter(self.state.clone())
+    }
+}
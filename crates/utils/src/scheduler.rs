@@ -0,0 +1,228 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+use anyhow::Error;
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serenity::all::Context;
+use tokio::sync::{Notify, RwLock};
+use tracing::{error, info, warn};
+use crate::config::Config;
+use crate::dispatch::{task_error, EventContext, EventKind};
+use crate::error::BidibipError;
+use crate::global_interface::BidibipSharedData;
+use crate::module::{BidibipModule, LoadModule};
+use crate::storage::{Storage, Table};
+
+/// Longest delay between two checks of the jobs (jobs of disabled modules are checked again when they are enabled)
+const MAX_SLEEP: Duration = Duration::from_secs(60);
+
+/// When a job should run
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub enum JobSchedule {
+    // Run once at the given date
+    Once(DateTime<Utc>),
+    // Run again every given number of seconds
+    Every(u64),
+    // Cron expression with seconds (ex: `0 0 * * * *` runs every hour)
+    Cron(String),
+}
+
+impl JobSchedule {
+    /// Check the cron expression before storing it
+    pub fn cron(expression: &str) -> Result<Self, Error> {
+        cron::Schedule::from_str(expression).map_err(|err| Error::msg(format!("Invalid cron expression '{}' : {}", expression, err)))?;
+        Ok(Self::Cron(expression.to_string()))
+    }
+
+    /// First run of the job, or None if it should not run anymore
+    fn first_run(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            JobSchedule::Once(date) => { Some(*date) }
+            _ => { self.next_run(now) }
+        }
+    }
+
+    /// Next run after the given date. Missed occurrences are not repeated.
+    fn next_run(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            JobSchedule::Once(_) => { None }
+            JobSchedule::Every(seconds) => { Some(after + chrono::Duration::seconds(*seconds as i64)) }
+            JobSchedule::Cron(expression) => { cron::Schedule::from_str(expression).ok()?.after(&after).next() }
+        }
+    }
+}
+
+impl Display for JobSchedule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JobSchedule::Once(date) => { write!(f, "une fois (<t:{}:f>)", date.timestamp()) }
+            JobSchedule::Every(seconds) => { write!(f, "toutes les {}s", seconds) }
+            JobSchedule::Cron(expression) => { write!(f, "cron `{}`", expression) }
+        }
+    }
+}
+
+/// A job registered by a module, run by BidibipModule::run_job()
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ScheduledJob {
+    pub module: String,
+    pub name: String,
+    pub payload: Value,
+    pub schedule: JobSchedule,
+    pub next_run: DateTime<Utc>,
+}
+
+impl ScheduledJob {
+    pub fn payload<T: DeserializeOwned>(&self) -> Result<T, Error> {
+        Ok(serde_json::from_value(self.payload.clone())?)
+    }
+}
+
+/// Delayed and recurring jobs of the modules. Jobs are stored in the storage so they survive restarts,
+/// and the jobs that should have run while the bot was stopped are run once at startup.
+pub struct Scheduler {
+    table: Table<u64, ScheduledJob>,
+    jobs: RwLock<BTreeMap<u64, ScheduledJob>>,
+    next_id: AtomicU64,
+    wake: Notify,
+    started: AtomicBool,
+}
+
+impl Scheduler {
+    pub fn load() -> Result<Self, Error> {
+        let table = Storage::get().shared_table("scheduled_jobs")?;
+        let jobs: BTreeMap<u64, ScheduledJob> = table.entries()?.into_iter().collect();
+        let next_id = jobs.keys().max().map(|id| id + 1).unwrap_or(1);
+        Ok(Self { table, jobs: RwLock::new(jobs), next_id: AtomicU64::new(next_id), wake: Notify::new(), started: AtomicBool::new(false) })
+    }
+
+    /// Register a job of the given module. Returns the job id.
+    pub async fn schedule<Module: LoadModule<Module> + BidibipModule>(&self, name: &str, payload: impl Serialize, schedule: JobSchedule) -> Result<u64, Error> {
+        if schedule == JobSchedule::Every(0) {
+            return Err(Error::msg("A recurring job should have a delay of at least one second"));
+        }
+        let next_run = match schedule.first_run(Utc::now()) {
+            None => { return Err(Error::msg(format!("Job {} of {} would never run", name, Module::name()))) }
+            Some(next_run) => { next_run }
+        };
+        let job = ScheduledJob { module: Module::name().to_string(), name: name.to_string(), payload: serde_json::to_value(payload)?, schedule, next_run };
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        self.table.set(&id, &job)?;
+        self.jobs.write().await.insert(id, job);
+        self.wake.notify_one();
+        Ok(id)
+    }
+
+    /// Register a job unless the module already has a job with this name (for recurring jobs registered at startup).
+    /// Returns the id of the new or existing job.
+    pub async fn schedule_once_named<Module: LoadModule<Module> + BidibipModule>(&self, name: &str, payload: impl Serialize, schedule: JobSchedule) -> Result<u64, Error> {
+        if let Some(id) = self.jobs.read().await.iter().find(|(_, job)| job.module == Module::name() && job.name == name).map(|(id, _)| *id) {
+            return Ok(id);
+        }
+        self.schedule::<Module>(name, payload, schedule).await
+    }
+
+    /// Every pending job, sorted by id
+    pub async fn jobs(&self) -> Vec<(u64, ScheduledJob)> {
+        self.jobs.read().await.iter().map(|(id, job)| (*id, job.clone())).collect()
+    }
+
+    /// Remove a pending job. Returns the removed job, or None if there is no job with this id.
+    pub async fn cancel(&self, id: u64) -> Result<Option<ScheduledJob>, Error> {
+        let mut jobs = self.jobs.write().await;
+        if !jobs.contains_key(&id) {
+            return Ok(None);
+        }
+        self.table.remove(&id)?;
+        Ok(jobs.remove(&id))
+    }
+
+    /// Remove the due jobs of the enabled modules, or plan their next run for the recurring ones
+    async fn take_due_jobs(&self, now: DateTime<Utc>, enabled_modules: &HashSet<String>) -> Vec<(u64, ScheduledJob)> {
+        let mut jobs = self.jobs.write().await;
+        let due: Vec<u64> = jobs.iter().filter(|(_, job)| job.next_run <= now && enabled_modules.contains(&job.module)).map(|(id, _)| *id).collect();
+        let mut due_jobs = vec![];
+        for id in due {
+            let job = match jobs.get(&id) {
+                None => { continue; }
+                Some(job) => { job.clone() }
+            };
+            let result = match job.schedule.next_run(now) {
+                None => { self.table.remove(&id).map(|_| { jobs.remove(&id); }) }
+                Some(next_run) => {
+                    let mut next_job = job.clone();
+                    next_job.next_run = next_run;
+                    self.table.set(&id, &next_job).map(|_| { jobs.insert(id, next_job); })
+                }
+            };
+            match result {
+                Ok(_) => { due_jobs.push((id, job)) }
+                // The job is not run, so it doesn't run twice if the storage is not updated
+                Err(err) => { error!("Failed to update scheduled job {} : {}", id, err) }
+            }
+        }
+        due_jobs
+    }
+
+    /// Date of the next run among the jobs of the enabled modules
+    async fn next_wake(&self, enabled_modules: &HashSet<String>) -> Option<DateTime<Utc>> {
+        self.jobs.read().await.values().filter(|job| enabled_modules.contains(&job.module)).map(|job| job.next_run).min()
+    }
+
+    /// Start running the jobs. Ready is sent again after a reconnection : the scheduler is only started once.
+    pub(crate) fn start(shared_data: Arc<BidibipSharedData>, ctx: Context) {
+        if shared_data.scheduler().started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        tokio::spawn(async move {
+            let scheduler = shared_data.scheduler();
+            let mut catching_up = true;
+            loop {
+                let enabled_modules: HashSet<String> = shared_data.get_enabled_modules().await.into_iter().map(|module| module.name.clone()).collect();
+                let now = Utc::now();
+                for (id, job) in scheduler.take_due_jobs(now, &enabled_modules).await {
+                    if catching_up {
+                        info!("Catching up job {} '{}' of {} planned at {}", id, job.name, job.module, job.next_run);
+                    }
+                    Self::run(&shared_data, &ctx, id, job).await;
+                }
+                catching_up = false;
+
+                let sleep = scheduler.next_wake(&enabled_modules).await.map(|date| (date - Utc::now()).to_std().unwrap_or_default()).unwrap_or(MAX_SLEEP).min(MAX_SLEEP);
+                tokio::select! {
+                    _ = tokio::time::sleep(sleep) => {}
+                    _ = scheduler.wake.notified() => {}
+                }
+            }
+        });
+    }
+
+    /// Run the job in its own task, with the same timeout as the events.
+    /// Failures are reported like the errors of the event hooks, and count towards the auto-disable of the module.
+    async fn run(shared_data: &Arc<BidibipSharedData>, ctx: &Context, id: u64, job: ScheduledJob) {
+        let module = match shared_data.get_enabled_modules().await.into_iter().find(|module| module.name == job.module) {
+            None => { return warn!("Module {} of job {} is not enabled anymore", job.module, id) }
+            Some(module) => { module }
+        };
+        let timeout = Duration::from_millis(Config::get().dispatch.module_timeout_ms);
+        let (shared_data, ctx) = (shared_data.clone(), ctx.clone());
+        tokio::spawn(async move {
+            let name = job.name.clone();
+            let (hook, hook_ctx) = (module.module.clone(), ctx.clone());
+            let task = tokio::spawn(async move { tokio::time::timeout(timeout, hook.run_job(hook_ctx, job)).await });
+            let result = match task.await {
+                Ok(Ok(result)) => { result }
+                Ok(Err(_)) => { Err(BidibipError::msg(format!("timed out after {}ms", timeout.as_millis()))) }
+                Err(err) => { Err(task_error(err)) }
+            };
+            let result = result.map_err(|err| BidibipError::msg(format!("job {} '{}' : {}", id, name, err)));
+            shared_data.report_result(&ctx, &module, &EventContext::new(EventKind::Job), result).await;
+        });
+    }
+}
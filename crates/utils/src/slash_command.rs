@@ -0,0 +1,377 @@
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+use serenity::all::{Attachment, AutocompleteChoice, ChannelId, CommandInteraction, CommandOptionType, CommandType, CreateAutocompleteResponse, CreateCommandOption, CreateInteractionResponse, Http, PartialChannel, ResolvedOption, ResolvedValue, Role, RoleId, User, UserId};
+use crate::create_command_detailed::CreateCommandDetailed;
+use crate::utilities::{CommandHelper, ResultDebug, TruncateText};
+
+/// Error raised when a command interaction doesn't match its declaration.
+/// The message is sent to the user as is.
+#[derive(Debug)]
+pub struct CommandParseError {
+    msg: String,
+}
+
+impl CommandParseError {
+    pub fn msg<T: Display>(message: T) -> Self {
+        Self { msg: message.to_string() }
+    }
+}
+
+impl Display for CommandParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.msg.as_str())
+    }
+}
+
+/// A value type that maps to a single Discord option type
+pub trait CommandValue: Sized {
+    const OPTION_TYPE: CommandOptionType;
+
+    /// Read the value, or None if the resolved value is not of the expected type
+    fn from_value(value: &ResolvedValue) -> Option<Self>;
+
+    /// Add type specific settings to the option declaration (choices, channel types...)
+    fn configure(option: CreateCommandOption) -> CreateCommandOption { option }
+}
+
+/// A typed command option. Implemented for every CommandValue (required option) and Option<CommandValue> (optional)
+pub trait CommandOptionValue: Sized {
+    fn create_option(name: &str, description: &str) -> CreateCommandOption;
+
+    /// Parse the value of the option, value is None when the user didn't provide it
+    fn parse(name: &str, value: Option<&ResolvedValue>) -> Result<Self, CommandParseError>;
+}
+
+impl<T: CommandValue> CommandOptionValue for T {
+    fn create_option(name: &str, description: &str) -> CreateCommandOption {
+        T::configure(CreateCommandOption::new(T::OPTION_TYPE, name, description).required(true))
+    }
+
+    fn parse(name: &str, value: Option<&ResolvedValue>) -> Result<Self, CommandParseError> {
+        match value {
+            None => { Err(CommandParseError::msg(format!("L'option `{}` est obligatoire", name))) }
+            Some(value) => {
+                T::from_value(value).ok_or_else(|| CommandParseError::msg(format!("L'option `{}` n'a pas le bon type", name)))
+            }
+        }
+    }
+}
+
+impl<T: CommandValue> CommandOptionValue for Option<T> {
+    fn create_option(name: &str, description: &str) -> CreateCommandOption {
+        T::configure(CreateCommandOption::new(T::OPTION_TYPE, name, description).required(false))
+    }
+
+    fn parse(name: &str, value: Option<&ResolvedValue>) -> Result<Self, CommandParseError> {
+        match value {
+            None => { Ok(None) }
+            Some(value) => { Ok(Some(<T as CommandOptionValue>::parse(name, Some(value))?)) }
+        }
+    }
+}
+
+impl CommandValue for String {
+    const OPTION_TYPE: CommandOptionType = CommandOptionType::String;
+    fn from_value(value: &ResolvedValue) -> Option<Self> {
+        if let ResolvedValue::String(value) = value { Some(value.to_string()) } else { None }
+    }
+}
+
+impl CommandValue for bool {
+    const OPTION_TYPE: CommandOptionType = CommandOptionType::Boolean;
+    fn from_value(value: &ResolvedValue) -> Option<Self> {
+        if let ResolvedValue::Boolean(value) = value { Some(*value) } else { None }
+    }
+}
+
+impl CommandValue for i64 {
+    const OPTION_TYPE: CommandOptionType = CommandOptionType::Integer;
+    fn from_value(value: &ResolvedValue) -> Option<Self> {
+        if let ResolvedValue::Integer(value) = value { Some(*value) } else { None }
+    }
+}
+
+impl CommandValue for f64 {
+    const OPTION_TYPE: CommandOptionType = CommandOptionType::Number;
+    fn from_value(value: &ResolvedValue) -> Option<Self> {
+        if let ResolvedValue::Number(value) = value { Some(*value) } else { None }
+    }
+}
+
+impl CommandValue for User {
+    const OPTION_TYPE: CommandOptionType = CommandOptionType::User;
+    fn from_value(value: &ResolvedValue) -> Option<Self> {
+        if let ResolvedValue::User(user, _) = value { Some((*user).clone()) } else { None }
+    }
+}
+
+impl CommandValue for UserId {
+    const OPTION_TYPE: CommandOptionType = CommandOptionType::User;
+    fn from_value(value: &ResolvedValue) -> Option<Self> {
+        if let ResolvedValue::User(user, _) = value { Some(user.id) } else { None }
+    }
+}
+
+impl CommandValue for PartialChannel {
+    const OPTION_TYPE: CommandOptionType = CommandOptionType::Channel;
+    fn from_value(value: &ResolvedValue) -> Option<Self> {
+        if let ResolvedValue::Channel(channel) = value { Some((*channel).clone()) } else { None }
+    }
+}
+
+impl CommandValue for ChannelId {
+    const OPTION_TYPE: CommandOptionType = CommandOptionType::Channel;
+    fn from_value(value: &ResolvedValue) -> Option<Self> {
+        if let ResolvedValue::Channel(channel) = value { Some(channel.id) } else { None }
+    }
+}
+
+impl CommandValue for Role {
+    const OPTION_TYPE: CommandOptionType = CommandOptionType::Role;
+    fn from_value(value: &ResolvedValue) -> Option<Self> {
+        if let ResolvedValue::Role(role) = value { Some((*role).clone()) } else { None }
+    }
+}
+
+impl CommandValue for RoleId {
+    const OPTION_TYPE: CommandOptionType = CommandOptionType::Role;
+    fn from_value(value: &ResolvedValue) -> Option<Self> {
+        if let ResolvedValue::Role(role) = value { Some(role.id) } else { None }
+    }
+}
+
+impl CommandValue for Attachment {
+    const OPTION_TYPE: CommandOptionType = CommandOptionType::Attachment;
+    fn from_value(value: &ResolvedValue) -> Option<Self> {
+        if let ResolvedValue::Attachment(attachment) = value { Some((*attachment).clone()) } else { None }
+    }
+}
+
+/// A set of options (the options of a command, or of a subcommand)
+pub trait CommandOptions: Sized {
+    /// True if the options are a list of subcommands (see command_subcommands!)
+    const SUBCOMMANDS: bool = false;
+
+    fn options() -> Vec<CreateCommandOption>;
+
+    fn parse(options: &[ResolvedOption]) -> Result<Self, CommandParseError>;
+}
+
+/// A chat input command
+pub trait SlashCommand: CommandOptions {
+    const NAME: &'static str;
+    const DESCRIPTION: &'static str;
+
+    /// Command declaration. Permissions can be added by the module in fetch_commands()
+    fn create_command() -> CreateCommandDetailed {
+        CreateCommandDetailed::new(Self::NAME)
+            .description(Self::DESCRIPTION)
+            .kind(CommandType::ChatInput)
+            .set_options(Self::options())
+    }
+}
+
+/// Typed list of the commands handled by a module (see command_router!)
+pub trait CommandRouter: Sized {
+    /// Returns None if the command doesn't belong to this router
+    fn route(command: &CommandInteraction) -> Option<Result<Self, CommandParseError>>;
+}
+
+/// Parse the value of the given option
+pub fn parse_option<T: CommandOptionValue>(options: &[ResolvedOption], name: &str) -> Result<T, CommandParseError> {
+    T::parse(name, options.iter().find(|option| option.name == name).map(|option| &option.value))
+}
+
+/// Declare a subcommand (or a subcommand group if T contains subcommands)
+pub fn create_subcommand<T: CommandOptions>(name: &str, description: &str) -> CreateCommandOption {
+    let mut option = CreateCommandOption::new(if T::SUBCOMMANDS { CommandOptionType::SubCommandGroup } else { CommandOptionType::SubCommand }, name, description);
+    for sub_option in T::options() {
+        option = option.add_sub_option(sub_option);
+    }
+    option
+}
+
+/// Get the name and the options of the selected subcommand (or subcommand group)
+pub fn find_subcommand<'a, 'b>(options: &'b [ResolvedOption<'a>]) -> Result<(&'a str, &'b [ResolvedOption<'a>]), CommandParseError> {
+    match options.first() {
+        Some(ResolvedOption { name, value: ResolvedValue::SubCommand(options), .. }) |
+        Some(ResolvedOption { name, value: ResolvedValue::SubCommandGroup(options), .. }) => {
+            Ok((name, options.as_slice()))
+        }
+        _ => { Err(CommandParseError::msg("Aucune sous-commande n'a été spécifiée")) }
+    }
+}
+
+#[serenity::async_trait]
+pub trait CommandParser {
+    /// Parse the command using the given router. Returns None if the command is not handled by
+    /// this router, or if the command is invalid (in which case the user is notified)
+    async fn parse_command<T: CommandRouter + Send>(&self, http: &Arc<Http>) -> Option<T>;
+}
+
+#[serenity::async_trait]
+impl CommandParser for CommandInteraction {
+    async fn parse_command<T: CommandRouter + Send>(&self, http: &Arc<Http>) -> Option<T> {
+        match T::route(self)? {
+            Ok(command) => { Some(command) }
+            Err(err) => {
+                self.respond_user_error(http, err).await;
+                None
+            }
+        }
+    }
+}
+
+/// Enable autocompletion on an option (to be used as option customization in command_options!)
+pub fn autocomplete(option: CreateCommandOption) -> CreateCommandOption {
+    option.set_autocomplete(true)
+}
+
+#[serenity::async_trait]
+pub trait AutocompleteHelper {
+    /// Name and current value of the option the user is filling
+    fn focused_option(&self) -> Option<(String, String)>;
+
+    /// Value of another string option already filled by the user
+    fn string_option(&self, name: &str) -> Option<String>;
+
+    /// Suggest the candidates containing the current value (case-insensitive, at most 25)
+    async fn suggest(&self, http: &Http, candidates: Vec<String>);
+}
+
+#[serenity::async_trait]
+impl AutocompleteHelper for CommandInteraction {
+    fn focused_option(&self) -> Option<(String, String)> {
+        self.data.autocomplete().map(|option| (option.name.to_string(), option.value.to_string()))
+    }
+
+    fn string_option(&self, name: &str) -> Option<String> {
+        fn find(options: &[ResolvedOption], name: &str) -> Option<String> {
+            for option in options {
+                match &option.value {
+                    ResolvedValue::String(value) if option.name == name => { return Some(value.to_string()) }
+                    ResolvedValue::SubCommand(options) | ResolvedValue::SubCommandGroup(options) => {
+                        if let Some(value) = find(options, name) {
+                            return Some(value);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            None
+        }
+        find(&self.data.options(), name)
+    }
+
+    async fn suggest(&self, http: &Http, candidates: Vec<String>) {
+        let value = self.focused_option().map(|(_, value)| value.to_lowercase()).unwrap_or_default();
+        let choices = candidates.into_iter()
+            .filter(|candidate| candidate.to_lowercase().contains(value.as_str()))
+            .take(25)
+            .map(|candidate| AutocompleteChoice::new(candidate.truncate_text(100), candidate.truncate_text(100)))
+            .collect();
+        self.create_response(http, CreateInteractionResponse::Autocomplete(CreateAutocompleteResponse::new().set_choices(choices))).await.on_fail("Failed to send autocomplete response");
+    }
+}
+
+/// Declare a struct of typed options. Each field is declared with its option name and description,
+/// and optionally a function to customize the option (choices, channel types...).
+/// Option<T> fields are optional options.
+/// ```ignore
+/// command_options! {
+///     pub struct SetModuleEnabled {
+///         module: String => ("module", "nom du module concerné"),
+///         enabled: bool => ("activer", "active ou désactive le module"),
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! command_options {
+    ($(#[$meta:meta])* $vis:vis struct $name:ident { $($field:ident : $ty:ty => ($option:literal, $description:literal $(, $customize:expr)?)),* $(,)? }) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $(pub $field: $ty),*
+        }
+
+        impl $crate::slash_command::CommandOptions for $name {
+            fn options() -> Vec<serenity::all::CreateCommandOption> {
+                vec![$({
+                    let option = <$ty as $crate::slash_command::CommandOptionValue>::create_option($option, $description);
+                    $(let option = ($customize)(option);)?
+                    option
+                }),*]
+            }
+
+            #[allow(unused)]
+            fn parse(options: &[serenity::all::ResolvedOption]) -> Result<Self, $crate::slash_command::CommandParseError> {
+                Ok(Self {
+                    $($field: $crate::slash_command::parse_option::<$ty>(options, $option)?),*
+                })
+            }
+        }
+    };
+}
+
+/// Declare an enum of subcommands. A variant can itself contain subcommands, it is then
+/// declared as a subcommand group.
+/// ```ignore
+/// command_subcommands! {
+///     pub enum ConfigCommand {
+///         Get(ConfigGet) => ("get", "lit une valeur"),
+///         Set(ConfigSet) => ("set", "modifie une valeur"),
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! command_subcommands {
+    ($(#[$meta:meta])* $vis:vis enum $name:ident { $($variant:ident($ty:ty) => ($sub:literal, $description:literal)),* $(,)? }) => {
+        $(#[$meta])*
+        $vis enum $name {
+            $($variant($ty)),*
+        }
+
+        impl $crate::slash_command::CommandOptions for $name {
+            const SUBCOMMANDS: bool = true;
+
+            fn options() -> Vec<serenity::all::CreateCommandOption> {
+                vec![$($crate::slash_command::create_subcommand::<$ty>($sub, $description)),*]
+            }
+
+            fn parse(options: &[serenity::all::ResolvedOption]) -> Result<Self, $crate::slash_command::CommandParseError> {
+                let (name, options) = $crate::slash_command::find_subcommand(options)?;
+                $(if name == $sub {
+                    return Ok(Self::$variant(<$ty as $crate::slash_command::CommandOptions>::parse(options)?));
+                })*
+                Err($crate::slash_command::CommandParseError::msg(format!("Sous-commande inconnue : {}", name)))
+            }
+        }
+    };
+}
+
+/// Declare the list of slash commands handled by a module
+/// ```ignore
+/// command_router! {
+///     enum UtilitiesCommand {
+///         Modules(ModulesCommand),
+///         SetModuleEnabled(SetModuleEnabledCommand),
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! command_router {
+    ($(#[$meta:meta])* $vis:vis enum $name:ident { $($variant:ident($ty:ty)),* $(,)? }) => {
+        $(#[$meta])*
+        $vis enum $name {
+            $($variant($ty)),*
+        }
+
+        impl $crate::slash_command::CommandRouter for $name {
+            fn route(command: &serenity::all::CommandInteraction) -> Option<Result<Self, $crate::slash_command::CommandParseError>> {
+                $(if command.data.name == <$ty as $crate::slash_command::SlashCommand>::NAME {
+                    return Some(<$ty as $crate::slash_command::CommandOptions>::parse(&command.data.options()).map(Self::$variant));
+                })*
+                None
+            }
+        }
+    };
+}
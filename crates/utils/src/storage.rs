@@ -0,0 +1,212 @@
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use anyhow::Error;
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use tracing::info;
+use crate::module::{BidibipModule, LoadModule};
+
+static GLOBAL_STORAGE: OnceLock<Storage> = OnceLock::new();
+
+/// Runtime data of the modules (warns, votes...), stored in a sqlite database.
+/// Settings stay in the json config files.
+pub struct Storage {
+    connection: Mutex<Connection>,
+}
+
+impl Storage {
+    /// Open (or create) the database at the given path
+    pub fn init(path: PathBuf) -> Result<(), Error> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let connection = Connection::open(&path)?;
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        // Keep track of the json data that have already been imported
+        connection.execute("CREATE TABLE IF NOT EXISTS _imports (name TEXT PRIMARY KEY, date INTEGER NOT NULL)", [])?;
+        // Version of the values stored in each table (see TableMigration)
+        connection.execute("CREATE TABLE IF NOT EXISTS _schema (name TEXT PRIMARY KEY, version INTEGER NOT NULL)", [])?;
+
+        if GLOBAL_STORAGE.set(Self { connection: Mutex::new(connection) }).is_err() {
+            return Err(Error::msg("Storage have already been initialized"));
+        }
+        info!("Opened storage {}", path.display());
+        Ok(())
+    }
+
+    pub fn get() -> &'static Self {
+        match GLOBAL_STORAGE.get() {
+            None => { panic!("Global storage have not been initialized using Storage::init(path)") }
+            Some(elem) => { elem }
+        }
+    }
+
+    /// Get (or create) a table owned by the given module. The stored values are upgraded with the
+    /// migrations of the module for this table (LoadModule::table_migrations) before the table is returned.
+    pub fn table<Module: LoadModule<Module> + BidibipModule, K: Serialize + DeserializeOwned, V: Serialize + DeserializeOwned>(&self, name: &str) -> Result<Table<K, V>, Error> {
+        self.create_table(format!("{}_{}", Module::name(), name), &Module::table_migrations(name))
+    }
+
+    /// Get (or create) a table used by the bot itself rather than by a module
+    pub(crate) fn shared_table<K: Serialize + DeserializeOwned, V: Serialize + DeserializeOwned>(&self, name: &str) -> Result<Table<K, V>, Error> {
+        self.create_table(format!("_{}", name), &[])
+    }
+
+    fn create_table<K: Serialize + DeserializeOwned, V: Serialize + DeserializeOwned>(&self, name: String, migrations: &[TableMigration]) -> Result<Table<K, V>, Error> {
+        let table = Table { name, _phantom: PhantomData };
+        self.transaction(|tx| {
+            let exists = tx.tx.query_row("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1", params![table.name], |_| Ok(())).optional()?.is_some();
+            tx.tx.execute(format!("CREATE TABLE IF NOT EXISTS \"{}\" (key TEXT PRIMARY KEY, value TEXT NOT NULL)", table.name).as_str(), [])?;
+            // A new table already has the latest version. Existing tables without a version predate the migrations.
+            let version = match exists {
+                true => { tx.tx.query_row("SELECT version FROM _schema WHERE name = ?1", params![table.name], |row| row.get::<_, usize>(0)).optional()?.unwrap_or_default() }
+                false => { migrations.len() }
+            };
+            tx.migrate(&table.name, version, migrations)
+        })?;
+        Ok(table)
+    }
+
+    /// Run the given operations atomically. Changes are discarded if an error is returned.
+    pub fn transaction<T>(&self, operations: impl FnOnce(&StorageTransaction) -> Result<T, Error>) -> Result<T, Error> {
+        let mut connection = self.connection.lock().map_err(|_| Error::msg("Storage lock is poisoned"))?;
+        let transaction = StorageTransaction { tx: connection.transaction()? };
+        let result = operations(&transaction)?;
+        transaction.tx.commit()?;
+        Ok(result)
+    }
+}
+
+/// Upgrade step of the values of a table. The step registered at index N upgrades the values from version N to N + 1.
+pub struct TableMigration {
+    pub description: &'static str,
+    pub upgrade: fn(&mut Value) -> Result<(), Error>,
+}
+
+impl TableMigration {
+    pub fn new(description: &'static str, upgrade: fn(&mut Value) -> Result<(), Error>) -> Self {
+        Self { description, upgrade }
+    }
+}
+
+/// A typed key-value table. Keys and values are serialized as json.
+pub struct Table<K: Serialize + DeserializeOwned, V: Serialize + DeserializeOwned> {
+    name: String,
+    _phantom: PhantomData<(K, V)>,
+}
+
+impl<K: Serialize + DeserializeOwned, V: Serialize + DeserializeOwned> Table<K, V> {
+    pub fn get(&self, key: &K) -> Result<Option<V>, Error> {
+        Storage::get().transaction(|tx| tx.get(self, key))
+    }
+
+    pub fn set(&self, key: &K, value: &V) -> Result<(), Error> {
+        Storage::get().transaction(|tx| tx.set(self, key, value))
+    }
+
+    pub fn remove(&self, key: &K) -> Result<(), Error> {
+        Storage::get().transaction(|tx| tx.remove(self, key))
+    }
+
+    pub fn contains(&self, key: &K) -> Result<bool, Error> {
+        Ok(self.get(key)?.is_some())
+    }
+
+    pub fn entries(&self) -> Result<Vec<(K, V)>, Error> {
+        Storage::get().transaction(|tx| tx.entries(self))
+    }
+
+    /// Read, modify and write back a value atomically. The closure receives None if the key doesn't exist.
+    pub fn update<T>(&self, key: &K, update: impl FnOnce(&mut Option<V>) -> T) -> Result<T, Error> {
+        Storage::get().transaction(|tx| {
+            let mut value = tx.get(self, key)?;
+            let result = update(&mut value);
+            match &value {
+                None => { tx.remove(self, key)? }
+                Some(value) => { tx.set(self, key, value)? }
+            }
+            Ok(result)
+        })
+    }
+
+    /// Import data that used to be stored in json files. The import is only done once,
+    /// existing keys are not overwritten. Returns the number of imported entries.
+    pub fn import(&self, entries: impl IntoIterator<Item=(K, V)>) -> Result<usize, Error> {
+        Storage::get().transaction(|tx| {
+            if tx.tx.query_row("SELECT 1 FROM _imports WHERE name = ?1", params![self.name], |_| Ok(())).optional()?.is_some() {
+                return Ok(0);
+            }
+            let mut count = 0;
+            for (key, value) in entries {
+                count += tx.tx.execute(format!("INSERT OR IGNORE INTO \"{}\" (key, value) VALUES (?1, ?2)", self.name).as_str(), params![serde_json::to_string(&key)?, serde_json::to_string(&value)?])?;
+            }
+            tx.tx.execute("INSERT INTO _imports (name, date) VALUES (?1, ?2)", params![self.name, chrono::Utc::now().timestamp()])?;
+            if count > 0 {
+                info!("Imported {} entries into table {}", count, self.name);
+            }
+            Ok(count)
+        })
+    }
+}
+
+/// Operations run inside Storage::transaction()
+pub struct StorageTransaction<'a> {
+    tx: Transaction<'a>,
+}
+
+impl StorageTransaction<'_> {
+    /// Upgrade every value of the table from the given version to the latest one
+    fn migrate(&self, table: &str, version: usize, migrations: &[TableMigration]) -> Result<(), Error> {
+        if version > migrations.len() {
+            return Err(Error::msg(format!("Table {} version {} is newer than the supported version {}", table, version, migrations.len())));
+        }
+        if version < migrations.len() {
+            let mut statement = self.tx.prepare(format!("SELECT key, value FROM \"{}\"", table).as_str())?;
+            let rows = statement.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?.collect::<Result<Vec<_>, _>>()?;
+            for (key, value) in rows {
+                let mut value: Value = serde_json::from_str(&value)?;
+                for (index, migration) in migrations.iter().enumerate().skip(version) {
+                    if let Err(err) = (migration.upgrade)(&mut value) {
+                        return Err(Error::msg(format!("Failed to migrate table {} from version {} to {} ({}) : {}", table, index, index + 1, migration.description, err)));
+                    }
+                }
+                self.tx.execute(format!("UPDATE \"{}\" SET value = ?1 WHERE key = ?2", table).as_str(), params![serde_json::to_string(&value)?, key])?;
+            }
+            info!("Migrated table {} from version {} to {}", table, version, migrations.len());
+        }
+        self.tx.execute("INSERT OR REPLACE INTO _schema (name, version) VALUES (?1, ?2)", params![table, migrations.len()])?;
+        Ok(())
+    }
+
+    pub fn get<K: Serialize + DeserializeOwned, V: Serialize + DeserializeOwned>(&self, table: &Table<K, V>, key: &K) -> Result<Option<V>, Error> {
+        let value: Option<String> = self.tx.query_row(format!("SELECT value FROM \"{}\" WHERE key = ?1", table.name).as_str(), params![serde_json::to_string(key)?], |row| row.get(0)).optional()?;
+        match value {
+            None => { Ok(None) }
+            Some(value) => { Ok(Some(serde_json::from_str(&value)?)) }
+        }
+    }
+
+    pub fn set<K: Serialize + DeserializeOwned, V: Serialize + DeserializeOwned>(&self, table: &Table<K, V>, key: &K, value: &V) -> Result<(), Error> {
+        self.tx.execute(format!("INSERT OR REPLACE INTO \"{}\" (key, value) VALUES (?1, ?2)", table.name).as_str(), params![serde_json::to_string(key)?, serde_json::to_string(value)?])?;
+        Ok(())
+    }
+
+    pub fn remove<K: Serialize + DeserializeOwned, V: Serialize + DeserializeOwned>(&self, table: &Table<K, V>, key: &K) -> Result<(), Error> {
+        self.tx.execute(format!("DELETE FROM \"{}\" WHERE key = ?1", table.name).as_str(), params![serde_json::to_string(key)?])?;
+        Ok(())
+    }
+
+    pub fn entries<K: Serialize + DeserializeOwned, V: Serialize + DeserializeOwned>(&self, table: &Table<K, V>) -> Result<Vec<(K, V)>, Error> {
+        let mut statement = self.tx.prepare(format!("SELECT key, value FROM \"{}\"", table.name).as_str())?;
+        let rows = statement.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+        let mut entries = vec![];
+        for row in rows {
+            let (key, value) = row?;
+            entries.push((serde_json::from_str(&key)?, serde_json::from_str(&value)?));
+        }
+        Ok(entries)
+    }
+}
@@ -36,7 +36,7 @@ impl Username {
     }
 
     pub fn full(&self) -> String {
-        format!("{} `{} | {}`", self.id.mention().to_string(), self.server_name, self.handle)
+        format!("{} `{} | {}`", self.id.mention(), self.server_name, self.handle)
     }
 
     #[allow(unused)]
@@ -78,7 +78,9 @@ impl<T: Display> TruncateText for T {
     fn truncate_text(&self, max: usize) -> String {
         let string = format!("{self}");
         if string.len() > max {
-            format!("{}..", string[0..max - 2].to_string())
+            // Don't cut a multi-byte character
+            let end = (0..=max.saturating_sub(2)).rev().find(|index| string.is_char_boundary(*index)).unwrap_or_default();
+            format!("{}..", &string[0..end])
         } else {
             string
         }
@@ -197,4 +199,11 @@ macro_rules! assert_warn_some {
             tracing::warn!("Unexpected none value : {}", $msg);
         }
     }};
-}
\ No newline at end of file
+}
+/// True if the request failed because the channel, message or member doesn't exist anymore
+pub fn is_not_found(err: &serenity::Error) -> bool {
+    match err {
+        serenity::Error::Http(err) => { err.status_code().is_some_and(|status| status.as_u16() == 404) }
+        _ => { false }
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use anyhow::Error;
+use serenity::all::{ChannelId, GuildId, Http, RoleId};
+use crate::config::Config;
+
+/// Channels and roles of the guild, used to check that configured ids still exist
+pub struct GuildResources {
+    channels: HashSet<ChannelId>,
+    roles: HashSet<RoleId>,
+    // Resources of each guild, when fetched for several guilds
+    guilds: HashMap<GuildId, GuildResources>,
+}
+
+impl GuildResources {
+    pub async fn fetch(http: &Http, guild: GuildId) -> Result<Self, Error> {
+        Ok(Self {
+            channels: guild.channels(http).await?.into_keys().collect(),
+            roles: guild.roles(http).await?.into_keys().collect(),
+            guilds: HashMap::new(),
+        })
+    }
+
+    /// Channels and roles of several guilds (module configs can reference any handled guild)
+    pub async fn fetch_all(http: &Http, guilds: &[GuildId]) -> Result<Self, Error> {
+        let mut resources = Self { channels: HashSet::new(), roles: HashSet::new(), guilds: HashMap::new() };
+        for guild in guilds {
+            let guild_resources = Self::fetch(http, *guild).await?;
+            resources.channels.extend(guild_resources.channels.iter().cloned());
+            resources.roles.extend(guild_resources.roles.iter().cloned());
+            resources.guilds.insert(*guild, guild_resources);
+        }
+        Ok(resources)
+    }
+
+    /// Resources of a single guild
+    fn of_guild(&self, guild: GuildId) -> Option<&GuildResources> {
+        if self.guilds.is_empty() {
+            Some(self)
+        } else {
+            self.guilds.get(&guild)
+        }
+    }
+}
+
+/// Collect the problems of a config. Existence checks are skipped if the guild resources are not available.
+pub struct ConfigValidator<'a> {
+    guild: Option<&'a GuildResources>,
+    issues: Vec<String>,
+}
+
+impl<'a> ConfigValidator<'a> {
+    pub fn new(guild: Option<&'a GuildResources>) -> Self {
+        Self { guild, issues: vec![] }
+    }
+
+    /// The channel should be set and exist in the guild
+    pub fn channel(&mut self, field: &str, id: ChannelId) {
+        if id == 0 {
+            self.issues.push(format!("`{}` n'est pas défini", field));
+        } else if let Some(guild) = self.guild {
+            if !guild.channels.contains(&id) {
+                self.issues.push(format!("`{}` : le salon {} n'existe pas", field, id));
+            }
+        }
+    }
+
+    /// The role should be set and exist in the guild
+    pub fn role(&mut self, field: &str, id: RoleId) {
+        if id == 0 {
+            self.issues.push(format!("`{}` n'est pas défini", field));
+        } else if let Some(guild) = self.guild {
+            if !guild.roles.contains(&id) {
+                self.issues.push(format!("`{}` : le rôle {} n'existe pas", field, id));
+            }
+        }
+    }
+
+    /// Check the section of a config dedicated to the given guild, against the channels and roles of this guild only
+    pub fn guild_section(&mut self, guild: GuildId, validate: impl FnOnce(&mut ConfigValidator)) {
+        let mut validator = ConfigValidator::new(self.guild.and_then(|resources| resources.of_guild(guild)));
+        validate(&mut validator);
+        let scope = if guild == Config::get().server_id { String::new() } else { format!("serveur {} : ", guild) };
+        self.issues.extend(validator.issues.into_iter().map(|issue| format!("{}{}", scope, issue)));
+    }
+
+    pub fn check<T: Display>(&mut self, condition: bool, message: T) {
+        if !condition {
+            self.issues.push(message.to_string());
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    pub fn issues(self) -> Vec<String> {
+        self.issues
+    }
+}
+
+/// Every problem found in the global config and the module configs
+#[derive(Default)]
+pub struct ValidationReport {
+    sections: Vec<(String, Vec<String>)>,
+    disabled_modules: Vec<String>,
+}
+
+impl ValidationReport {
+    pub fn add<T: Display>(&mut self, scope: T, issues: Vec<String>) {
+        if !issues.is_empty() {
+            self.sections.push((scope.to_string(), issues));
+        }
+    }
+
+    /// The module was disabled because of invalid config
+    pub fn disable_module(&mut self, module: &str) {
+        self.disabled_modules.push(module.to_string());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sections.is_empty()
+    }
+}
+
+impl Display for ValidationReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return f.write_str("Configuration valide");
+        }
+        write!(f, "Problèmes de configuration :")?;
+        for (scope, issues) in &self.sections {
+            write!(f, "\n**{}**", scope)?;
+            for issue in issues {
+                write!(f, "\n- {}", issue)?;
+            }
+        }
+        if !self.disabled_modules.is_empty() {
+            write!(f, "\nModules désactivés (mode dégradé) : {}", self.disabled_modules.join(", "))?;
+        }
+        Ok(())
+    }
+}
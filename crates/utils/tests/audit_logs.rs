@@ -0,0 +1,23 @@
+use std::time::Duration;
+use tracing::info;
+use test_support::{init_environment, TestBot, LOG_CHANNEL};
+use utils::logger::{init_logger, set_log_filter, LogSink, AUDIT_TARGET};
+
+#[tokio::test]
+async fn audit_logs_ignore_the_discord_filter() {
+    init_environment();
+    let connector = init_logger();
+    let bot = TestBot::start().await.expect("Failed to start test bot");
+    connector.init_for_channel(LOG_CHANNEL, bot.ctx.http.clone());
+
+    set_log_filter(LogSink::Discord, "off").expect("Failed to set the discord filter");
+    info!("Filtered log");
+    info!(target: AUDIT_TARGET, "moderator changed config warn.public_warn_channel : 1 -> 2");
+
+    // The logs are grouped for two seconds before being sent
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    let sent = bot.discord.wait_for("POST", &format!("/channels/{}/messages", LOG_CHANNEL)).await.expect("The audit log should be sent");
+    let description = sent.body.unwrap()["embeds"][0]["description"].as_str().unwrap().to_string();
+    assert!(description.contains("changed config warn.public_warn_channel"), "Unexpected logs {}", description);
+    assert!(!description.contains("Filtered log"));
+}
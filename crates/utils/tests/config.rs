@@ -0,0 +1,31 @@
+use serde_json::json;
+use utils::config::Config;
+
+#[test]
+fn legacy_metrics_section_configures_the_http_listener() {
+    let directory = std::env::temp_dir().join(format!("bidibip-config-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).expect("Failed to create test directory");
+    let config = json!({
+        "token": "test-token",
+        "server_id": 1000,
+        "application_id": 1001,
+        "log_directory": directory.join("logs"),
+        "module_config_directory": directory.join("modules"),
+        "disabled_modules": [],
+        "channels": {"log_channel": 1002, "staff_channel": 1003},
+        "roles": {"support": 1010, "member": 1011, "helper": 1012, "administrator": 1013, "mute": 1014},
+        "cache_message_size": 100,
+        "metrics": {"enabled": true, "address": "127.0.0.1:9000"},
+        "http": {"address": "127.0.0.1:9100"},
+    });
+    std::fs::write(directory.join("config.json"), config.to_string()).expect("Failed to write test config");
+    // Variables written for the old section name are still applied
+    std::env::set_var("BIDIBIP_METRICS__MAX_EVENT_AGE_S", "60");
+
+    Config::init(directory.join("config.json")).expect("Failed to load test config");
+
+    let http = &Config::get().http;
+    assert!(http.enabled);
+    assert_eq!(http.address, "127.0.0.1:9100", "The new section name should win");
+    assert_eq!(http.max_event_age_s, 60);
+}
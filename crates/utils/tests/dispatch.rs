@@ -0,0 +1,50 @@
+use std::sync::Arc;
+use anyhow::Error;
+use serenity::all::{ChannelId, Context, EventHandler, Interaction};
+use serenity::all::Interaction::Component;
+use test_support::fixtures::{interaction_callback_path, next_id, ComponentInteractionBuilder, MemberBuilder, MessageBuilder, UserBuilder};
+use test_support::{TestBot, APPLICATION};
+use utils::dispatch::ModuleEvents;
+use utils::error::BidibipError;
+use utils::global_interface::BidibipSharedData;
+use utils::module::{BidibipModule, LoadModule};
+
+/// Module deferring the interactions, then failing to handle them
+struct Deferring;
+
+#[serenity::async_trait]
+impl LoadModule<Deferring> for Deferring {
+    fn name() -> &'static str { "deferring" }
+    fn description() -> &'static str { "" }
+    fn events() -> ModuleEvents { ModuleEvents::INTERACTION_CREATE }
+    async fn load(_: &Arc<BidibipSharedData>) -> Result<Deferring, Error> { Ok(Deferring) }
+}
+
+#[serenity::async_trait]
+impl BidibipModule for Deferring {
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) -> Result<(), BidibipError> {
+        if let Component(component) = interaction {
+            component.defer_ephemeral(&ctx.http).await?;
+        }
+        Err(BidibipError::msg("Failed after deferring"))
+    }
+}
+
+#[tokio::test]
+async fn deferred_interaction_gets_an_error_followup() {
+    let bot = TestBot::start().await.expect("Failed to start test bot");
+    bot.load_module::<Deferring>().await.expect("Failed to load module");
+    let message = MessageBuilder::new(ChannelId::new(next_id()), &UserBuilder::bidibip(), "Message");
+    let click = ComponentInteractionBuilder::button("deferring_button", &message, &MemberBuilder::new(&UserBuilder::new("user"))).build();
+    let followup_path = format!("/webhooks/{}/{}", APPLICATION, click.token);
+    bot.discord.respond("POST", &followup_path, MessageBuilder::new(message.build().channel_id, &UserBuilder::bidibip(), "Erreur").json());
+
+    bot.interface.interaction_create(bot.ctx.clone(), Interaction::Component(click.clone())).await;
+
+    assert_eq!(bot.discord.requests_to("POST", &interaction_callback_path(click.id, &click.token)).len(), 2, "The error response should be tried first");
+    let followups = bot.discord.requests_to("POST", &followup_path);
+    assert_eq!(followups.len(), 1, "The user should be told something went wrong");
+    let body = followups[0].body.as_ref().expect("Followup without body");
+    assert!(body["content"].as_str().unwrap().contains("Mince alors"));
+    assert_eq!(body["flags"], 64, "The error should only be shown to the user");
+}
@@ -0,0 +1,27 @@
+use std::fs;
+use std::time::Duration;
+use test_support::{init_environment, TestBot, LOG_CHANNEL};
+use utils::logger::init_logger;
+use utils::persistence::{load_json, save_file, BackupConfig};
+
+#[tokio::test]
+async fn backup_fallback_reaches_the_log_channel() {
+    let directory = init_environment().join("early_logs");
+    fs::create_dir_all(&directory).expect("Failed to create config directory");
+    let path = directory.join("warn_config.json");
+    save_file(&path, b"{\"value\": 1}", &BackupConfig::default()).unwrap();
+    save_file(&path, b"{\"value\": 2}", &BackupConfig::default()).unwrap();
+    fs::write(&path, "{ corrupted").unwrap();
+
+    // The config is loaded from a backup before the logger and the log channel are ready
+    let _: serde_json::Value = load_json(&path).unwrap();
+    let connector = init_logger();
+    let bot = TestBot::start().await.expect("Failed to start test bot");
+    connector.init_for_channel(LOG_CHANNEL, bot.ctx.http.clone());
+
+    // The logs are grouped for two seconds before being sent
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    let sent = bot.discord.wait_for("POST", &format!("/channels/{}/messages", LOG_CHANNEL)).await.expect("The startup logs should be sent");
+    let description = sent.body.unwrap()["embeds"][0]["description"].as_str().unwrap().to_string();
+    assert!(description.contains("warn_config.json") && description.contains("Using backup"), "Unexpected logs {}", description);
+}
@@ -0,0 +1,65 @@
+use std::net::SocketAddr;
+use serde_json::Value;
+use test_support::http::http_get;
+use test_support::TestBot;
+use utils::http_server::{start_server, HttpConfig};
+
+/// Status line and JSON body of the response
+async fn get(address: SocketAddr, path: &str) -> (String, Value) {
+    let (status, body) = http_get(address, path).await;
+    (status, serde_json::from_str(&body).expect("Invalid JSON body"))
+}
+
+async fn start(bot: &TestBot) -> SocketAddr {
+    let config = HttpConfig { enabled: true, address: "127.0.0.1:0".to_string(), ..Default::default() };
+    start_server(&config, bot.shared_data.clone()).await.expect("Failed to start HTTP listener")
+}
+
+#[tokio::test]
+async fn health_follows_the_gateway_connection() {
+    let bot = TestBot::start().await.expect("Failed to start test bot");
+    let address = start(&bot).await;
+
+    let (status, body) = get(address, "/healthz").await;
+    assert_eq!(status, "HTTP/1.1 503 Service Unavailable");
+    assert_eq!(body["gateway_connected"], false);
+
+    bot.shared_data.health().set_connected(true);
+    bot.shared_data.health().record_event();
+    let (status, body) = get(address, "/healthz").await;
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    assert_eq!(body["last_event_s"], 0);
+
+    bot.shared_data.health().set_connected(false);
+    assert_eq!(get(address, "/healthz").await.0, "HTTP/1.1 503 Service Unavailable");
+}
+
+#[tokio::test]
+async fn readiness_waits_for_the_startup() {
+    let bot = TestBot::start().await.expect("Failed to start test bot");
+    let address = start(&bot).await;
+    bot.shared_data.health().set_connected(true);
+
+    let (status, body) = get(address, "/readyz").await;
+    assert_eq!(status, "HTTP/1.1 503 Service Unavailable");
+    assert_eq!(body["startup_finished"], false);
+
+    bot.shared_data.health().set_startup_finished();
+    assert_eq!(get(address, "/readyz").await.0, "HTTP/1.1 200 OK");
+}
+
+#[tokio::test]
+async fn readiness_reports_the_failed_modules() {
+    let bot = TestBot::start().await.expect("Failed to start test bot");
+    let address = start(&bot).await;
+    bot.shared_data.health().set_connected(true);
+    bot.shared_data.health().set_startup_finished();
+
+    bot.shared_data.register_load_failure("warn", "Invalid config".to_string()).await;
+
+    let (status, body) = get(address, "/readyz").await;
+    assert_eq!(status, "HTTP/1.1 503 Service Unavailable");
+    assert_eq!(body["failed_modules"]["warn"], "Invalid config");
+    // A module that failed to load does not make the bot unhealthy
+    assert_eq!(get(address, "/healthz").await.0, "HTTP/1.1 200 OK");
+}
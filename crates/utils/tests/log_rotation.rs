@@ -0,0 +1,92 @@
+use std::fs;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tracing_subscriber::fmt::MakeWriter;
+use utils::rolling_file::{RollingFile, RotationPolicy};
+
+fn log_directory(test: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("bidibip-rotation-{}-{}", std::process::id(), test));
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).expect("Failed to create log directory");
+    directory
+}
+
+fn policy() -> RotationPolicy {
+    RotationPolicy { daily: false, max_file_size: 0, compress: false, max_age: None, max_total_size: 0 }
+}
+
+/// Rotated files, waiting for the background compression and retention
+fn archives(directory: &Path, expected: usize, extension: &str) -> Vec<String> {
+    let list = || {
+        let mut files: Vec<String> = fs::read_dir(directory).unwrap().flatten().map(|entry| entry.file_name().to_string_lossy().to_string()).filter(|file| file != "log.log").collect();
+        files.sort();
+        files
+    };
+    for _ in 0..100 {
+        if list().len() == expected && list().iter().all(|file| file.ends_with(extension)) {
+            return list();
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    panic!("Expected {} rotated files, got {:?}", expected, list())
+}
+
+fn archive(directory: &Path, name: &str, size: usize, age: Duration) {
+    let file = File::create(directory.join(name)).unwrap();
+    file.set_len(size as u64).unwrap();
+    file.set_modified(SystemTime::now() - age).unwrap();
+}
+
+#[test]
+fn file_is_rotated_when_it_is_too_big() {
+    let directory = log_directory("size");
+    let file = RollingFile::open(&directory, "log", RotationPolicy { max_file_size: 100, ..policy() }).unwrap();
+
+    for index in 0..3 {
+        file.make_writer().write_all(format!("{:<59}\n", index).as_bytes()).unwrap();
+    }
+
+    assert_eq!(archives(&directory, 2, ".log").len(), 2);
+    assert!(fs::read_to_string(directory.join("log.log")).unwrap().starts_with('2'));
+}
+
+#[test]
+fn previous_run_is_archived_and_compressed() {
+    let directory = log_directory("compress");
+    fs::write(directory.join("log.log"), "previous run\n").unwrap();
+
+    let file = RollingFile::open(&directory, "log", RotationPolicy { compress: true, ..policy() }).unwrap();
+    file.make_writer().write_all(b"new run\n").unwrap();
+
+    let archived = archives(&directory, 1, ".log.gz");
+    assert!(archived[0].starts_with("log_"), "Unexpected archive {:?}", archived);
+    assert_eq!(fs::read(directory.join(&archived[0])).unwrap()[0..2], [0x1f, 0x8b], "The archive should be gzip");
+    assert_eq!(fs::read_to_string(directory.join("log.log")).unwrap(), "new run\n");
+}
+
+#[test]
+fn old_archives_are_deleted() {
+    let directory = log_directory("age");
+    archive(&directory, "log_2020-01-01.log.gz", 10, Duration::from_secs(40 * 24 * 3600));
+    archive(&directory, "log_2020-02-01.log.gz", 10, Duration::from_secs(3600));
+    archive(&directory, "error_2020-01-01.log.gz", 10, Duration::from_secs(40 * 24 * 3600));
+
+    RollingFile::open(&directory, "log", RotationPolicy { max_age: Some(Duration::from_secs(30 * 24 * 3600)), ..policy() }).unwrap();
+
+    // Only the archives of the rotated file are removed
+    assert_eq!(archives(&directory, 2, ".log.gz"), vec!["error_2020-01-01.log.gz", "log_2020-02-01.log.gz"]);
+}
+
+#[test]
+fn oldest_archives_are_deleted_above_the_total_size() {
+    let directory = log_directory("total");
+    archive(&directory, "log_1.log.gz", 40, Duration::from_secs(3 * 3600));
+    archive(&directory, "log_2.log.gz", 40, Duration::from_secs(2 * 3600));
+    archive(&directory, "log_3.log.gz", 40, Duration::from_secs(3600));
+
+    RollingFile::open(&directory, "log", RotationPolicy { max_total_size: 100, ..policy() }).unwrap();
+
+    assert_eq!(archives(&directory, 2, ".log.gz"), vec!["log_2.log.gz", "log_3.log.gz"]);
+}
@@ -0,0 +1,124 @@
+use std::time::Duration;
+use serde_json::json;
+use serenity::all::ChannelId;
+use tracing::Level;
+use test_support::{init_environment_with, TestBot, LOG_CHANNEL};
+use utils::logger::{DiscordLogConnector, LogEntry};
+
+const WARN_LOG_CHANNEL: ChannelId = ChannelId::new(7000);
+
+async fn start() -> TestBot {
+    init_environment_with(json!({"logging": {"channels": {"modules::warn": WARN_LOG_CHANNEL}}}));
+    TestBot::start().await.expect("Failed to start test bot")
+}
+
+fn entry(level: Level, message: &str) -> LogEntry {
+    LogEntry { level, target: "modules::test".to_string(), line: Some(12), message: message.to_string() }
+}
+
+fn connect(bot: &TestBot, capacity: usize) -> DiscordLogConnector {
+    let connector = DiscordLogConnector::new(capacity, Duration::from_millis(50));
+    connector.init_for_channel(LOG_CHANNEL, bot.ctx.http.clone());
+    connector
+}
+
+#[tokio::test]
+async fn logs_are_grouped_in_a_single_message() {
+    let bot = start().await;
+    let connector = connect(&bot, 100);
+
+    connector.push(entry(Level::INFO, "Module started"));
+    for _ in 0..5 {
+        connector.push(entry(Level::ERROR, "Failed to reach the database"));
+    }
+
+    let sent = bot.discord.wait_for("POST", &format!("/channels/{}/messages", LOG_CHANNEL)).await.expect("The logs should be sent");
+    let body = sent.body.expect("Log message without body");
+    let description = body["embeds"][0]["description"].as_str().unwrap();
+    assert!(description.starts_with(":green_circle: `modules::test:12` Module started\n"));
+    assert!(description.contains(":red_circle: `modules::test:12` Failed to reach the database (x5)"));
+    assert!(body["content"].as_str().unwrap().contains("<@&"), "Errors should ping the support");
+    assert_eq!(bot.discord.requests_to("POST", &format!("/channels/{}/messages", LOG_CHANNEL)).len(), 1);
+}
+
+#[tokio::test]
+async fn full_queue_drops_logs_with_a_summary() {
+    let bot = start().await;
+    let connector = connect(&bot, 3);
+
+    // The worker can't run before the next await : only the first logs fit in the queue
+    for index in 0..10 {
+        connector.push(entry(Level::WARN, &format!("Warning {index}")));
+    }
+
+    let sent = bot.discord.wait_for("POST", &format!("/channels/{}/messages", LOG_CHANNEL)).await.expect("The logs should be sent");
+    let body = sent.body.expect("Log message without body");
+    let description = body["embeds"][0]["description"].as_str().unwrap();
+    assert!(description.contains("Warning 2"));
+    assert!(!description.contains("Warning 3"));
+    assert!(description.contains("7 log(s) dropped"));
+}
+
+#[tokio::test]
+async fn failed_sends_are_not_fatal() {
+    let bot = start().await;
+    bot.discord.respond_error("POST", &format!("/channels/{}/messages", LOG_CHANNEL), 500);
+    let connector = connect(&bot, 100);
+
+    connector.push(entry(Level::ERROR, "First failure"));
+    bot.discord.wait_for("POST", &format!("/channels/{}/messages", LOG_CHANNEL)).await.expect("The logs should be sent");
+    bot.discord.clear_requests();
+
+    // The worker is still alive
+    connector.push(entry(Level::ERROR, "Second failure"));
+    let sent = bot.discord.wait_for("POST", &format!("/channels/{}/messages", LOG_CHANNEL)).await.expect("The worker should survive the failure");
+    assert!(sent.body.unwrap()["embeds"][0]["description"].as_str().unwrap().contains("Second failure"));
+}
+
+#[tokio::test]
+async fn module_logs_are_routed_to_their_channel() {
+    let bot = start().await;
+    let connector = connect(&bot, 100);
+
+    connector.push(LogEntry { target: "modules::warn::history".to_string(), ..entry(Level::WARN, "Sanction recorded") });
+    connector.push(entry(Level::INFO, "Module started"));
+
+    let routed = bot.discord.wait_for("POST", &format!("/channels/{}/messages", WARN_LOG_CHANNEL)).await.expect("The warn logs should be sent to their channel");
+    assert!(routed.body.unwrap()["embeds"][0]["description"].as_str().unwrap().contains("Sanction recorded"));
+    let main = bot.discord.wait_for("POST", &format!("/channels/{}/messages", LOG_CHANNEL)).await.expect("The other logs should be sent to the log channel");
+    let description = main.body.unwrap()["embeds"][0]["description"].as_str().unwrap().to_string();
+    assert!(description.contains("Module started"));
+    assert!(!description.contains("Sanction recorded"));
+}
+
+#[tokio::test]
+async fn support_is_not_pinged_again_during_the_cooldown() {
+    let bot = start().await;
+    let connector = connect(&bot, 100);
+
+    connector.push(entry(Level::ERROR, "First failure"));
+    let first = bot.discord.wait_for("POST", &format!("/channels/{}/messages", LOG_CHANNEL)).await.expect("The logs should be sent");
+    assert!(first.body.unwrap()["content"].as_str().is_some_and(|content| content.contains("<@&")));
+    bot.discord.clear_requests();
+
+    connector.push(entry(Level::ERROR, "Second failure"));
+    let second = bot.discord.wait_for("POST", &format!("/channels/{}/messages", LOG_CHANNEL)).await.expect("The logs should be sent");
+    assert!(second.body.unwrap()["content"].is_null(), "The support was pinged less than 5 minutes ago");
+}
+
+#[tokio::test]
+async fn logs_written_before_the_channel_is_known_are_queued() {
+    let bot = start().await;
+    let connector = DiscordLogConnector::new(3, Duration::from_millis(50));
+
+    for index in 0..5 {
+        connector.push(entry(Level::WARN, &format!("Startup warning {index}")));
+    }
+    connector.init_for_channel(LOG_CHANNEL, bot.ctx.http.clone());
+
+    let sent = bot.discord.wait_for("POST", &format!("/channels/{}/messages", LOG_CHANNEL)).await.expect("The queued logs should be sent");
+    let description = sent.body.unwrap()["embeds"][0]["description"].as_str().unwrap().to_string();
+    assert!(description.contains("Startup warning 0") && description.contains("Startup warning 2"));
+    assert!(!description.contains("Startup warning 3"));
+    assert!(description.contains("2 log(s) dropped"));
+}
@@ -0,0 +1,38 @@
+use std::collections::HashMap;
+use serenity::all::ChannelId;
+use test_support::init_environment;
+use utils::config::LoggingConfig;
+use utils::logger::{init_logger, log_filters, set_log_filter, LogSink};
+
+fn filter(sink: LogSink) -> String {
+    log_filters().into_iter().find(|(other, _)| *other == sink).map(|(_, directives)| directives).expect("Missing filter")
+}
+
+/// The logger is global : every step runs in the same test
+#[tokio::test]
+async fn filters_can_be_replaced_at_runtime() {
+    init_environment();
+    init_logger();
+    assert_eq!(filter(LogSink::Discord), "info,log=off");
+
+    set_log_filter(LogSink::Discord, "warn,modules::warn=debug").expect("Failed to set the discord filter");
+    assert_eq!(filter(LogSink::Discord), "warn,modules::warn=debug");
+
+    // Invalid directives leave the current filter
+    assert!(set_log_filter(LogSink::Stdout, "modules=verbose").is_err());
+    assert_eq!(filter(LogSink::Stdout), "info");
+}
+
+#[test]
+fn most_specific_target_channel_wins() {
+    let logging = LoggingConfig {
+        channels: HashMap::from([("modules".to_string(), ChannelId::new(1)), ("modules::warn".to_string(), ChannelId::new(2))]),
+        ..Default::default()
+    };
+    assert_eq!(logging.channel_for("modules::warn"), Some(ChannelId::new(2)));
+    assert_eq!(logging.channel_for("modules::warn::history"), Some(ChannelId::new(2)));
+    assert_eq!(logging.channel_for("modules::anti_spam"), Some(ChannelId::new(1)));
+    // Prefixes only match whole path segments
+    assert_eq!(logging.channel_for("modules_extra"), None);
+    assert_eq!(logging.channel_for("utils::logger"), None);
+}
@@ -0,0 +1,42 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use utils::persistence::{list_backups, load_json, save_file, take_early_warnings, BackupConfig};
+
+fn config_directory(test: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("bidibip-persistence-{}-{}", std::process::id(), test));
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).expect("Failed to create config directory");
+    directory
+}
+
+#[test]
+fn every_save_is_backed_up() {
+    let path = config_directory("save").join("warn_config.json");
+    let config = BackupConfig { max_backups: 3 };
+
+    for value in 0..5 {
+        save_file(&path, format!("{{\"value\": {value}}}").as_bytes(), &config).unwrap();
+        std::thread::sleep(Duration::from_millis(5));
+    }
+
+    let backups = list_backups(&path).unwrap();
+    assert_eq!(backups.len(), 3);
+    // The newest backup holds the content replaced by the last save
+    assert_eq!(fs::read_to_string(&backups[0].1).unwrap(), "{\"value\": 3}");
+}
+
+#[test]
+fn backup_fallback_is_reported_once_the_logger_starts() {
+    let path = config_directory("fallback").join("warn_config.json");
+    let config = BackupConfig::default();
+    save_file(&path, b"{\"value\": 1}", &config).unwrap();
+    save_file(&path, b"{\"value\": 2}", &config).unwrap();
+    fs::write(&path, "{ corrupted").unwrap();
+
+    let value: serde_json::Value = load_json(&path).unwrap();
+
+    assert_eq!(value["value"], 1);
+    let warnings = take_early_warnings();
+    assert!(warnings.iter().any(|warning| warning.contains("warn_config.json") && warning.contains("Using backup")), "Unexpected warnings {:?}", warnings);
+}
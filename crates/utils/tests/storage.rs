@@ -0,0 +1,84 @@
+use std::sync::{Arc, Once};
+use anyhow::Error;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utils::dispatch::ModuleEvents;
+use utils::global_interface::BidibipSharedData;
+use utils::module::{BidibipModule, LoadModule};
+use utils::storage::{Storage, TableMigration};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Item {
+    title: String,
+}
+
+/// Module storing items with a `name` field, before the migration renaming it to `title`
+struct Before;
+/// Same module, with the migration
+struct After;
+
+impl BidibipModule for Before {}
+impl BidibipModule for After {}
+
+#[serenity::async_trait]
+impl LoadModule<Before> for Before {
+    fn name() -> &'static str { "storage_test" }
+    fn description() -> &'static str { "" }
+    fn events() -> ModuleEvents { ModuleEvents::empty() }
+    async fn load(_: &Arc<BidibipSharedData>) -> Result<Before, Error> { Ok(Before) }
+}
+
+#[serenity::async_trait]
+impl LoadModule<After> for After {
+    fn name() -> &'static str { "storage_test" }
+    fn description() -> &'static str { "" }
+    fn events() -> ModuleEvents { ModuleEvents::empty() }
+    fn table_migrations(table: &str) -> Vec<TableMigration> {
+        match table {
+            "items" => {
+                vec![TableMigration::new("rename name to title", |value| {
+                    let item = value.as_object_mut().ok_or(Error::msg("Item is not an object"))?;
+                    let name = item.remove("name").ok_or(Error::msg("Missing name"))?;
+                    item.insert("title".to_string(), name);
+                    Ok(())
+                })]
+            }
+            _ => { vec![] }
+        }
+    }
+    async fn load(_: &Arc<BidibipSharedData>) -> Result<After, Error> { Ok(After) }
+}
+
+fn open_storage() {
+    static OPEN: Once = Once::new();
+    OPEN.call_once(|| {
+        let directory = std::env::temp_dir().join(format!("bidibip-storage-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        Storage::init(directory.join("bidibip.sqlite")).expect("Failed to open storage");
+    });
+}
+
+#[test]
+fn stored_values_are_migrated_once() {
+    open_storage();
+
+    let before = Storage::get().table::<Before, String, Value>("items").unwrap();
+    before.set(&"first".to_string(), &serde_json::json!({"name": "Annonce"})).unwrap();
+
+    let after = Storage::get().table::<After, String, Item>("items").unwrap();
+    assert_eq!(after.get(&"first".to_string()).unwrap(), Some(Item { title: "Annonce".to_string() }));
+
+    // The table is already at the latest version : the migration would fail if it ran again
+    let after = Storage::get().table::<After, String, Item>("items").unwrap();
+    assert_eq!(after.entries().unwrap().len(), 1);
+}
+
+#[test]
+fn new_tables_start_at_the_latest_version() {
+    open_storage();
+
+    let table = Storage::get().table::<After, String, Item>("items_new").unwrap();
+    table.set(&"first".to_string(), &Item { title: "Annonce".to_string() }).unwrap();
+    let table = Storage::get().table::<After, String, Item>("items_new").unwrap();
+    assert_eq!(table.get(&"first".to_string()).unwrap(), Some(Item { title: "Annonce".to_string() }));
+}
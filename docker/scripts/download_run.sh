@@ -16,4 +16,4 @@ chmod u+x /opt/bidibip/bidibip
 rm /opt/bidibip/bidibip.zip
 
 # Run
-/opt/bidibip/bidibip
\ No newline at end of file
+/opt/bidibip/bidibip --config /opt/bidibip/config.json
\ No newline at end of file